@@ -0,0 +1,208 @@
+use m_mazing_core::prelude::*;
+use m_mazing_core::render::{
+    camera::AutoFitCamera, render_board::spawn_hint_highlight, RenderState,
+};
+
+use crate::*;
+
+/// Game as seen by the local player
+#[derive(Debug, Resource)]
+pub struct LocalGame {
+    pub state: GameState,
+    pub player: PlayerId,
+}
+
+impl LocalGame {
+    /// First legal way for the local player to make `pawn` perform `action`.
+    ///
+    /// Slides go as far as possible.
+    pub fn quick_pawn_action(&self, pawn: Pawn, action: BoardAction) -> Option<PawnAction> {
+        let mut actions = self
+            .state
+            .legal_actions(self.player, pawn)
+            .into_iter()
+            .filter(|pawn_action| pawn_action.action == action);
+        match action {
+            BoardAction::Slide(_) => actions.next_back(),
+            BoardAction::Warp | BoardAction::Escalator | BoardAction::Explore => actions.next(),
+        }
+    }
+
+    /// Player after the one currently alerted, skipping the local player
+    pub fn next_do_something_target(&self) -> Option<PlayerId> {
+        let num_players = self.state.num_players();
+        let current = self.state.do_something_target().unwrap_or(self.player);
+        (1..num_players)
+            .map(|offset| PlayerId((current.0 + offset) % num_players))
+            .find(|target| *target != self.player)
+    }
+}
+
+/// Action the local player wants to perform
+#[derive(Debug, Clone, Event)]
+pub struct SubmitAction(pub Action);
+
+/// Marks entities that are re-spawned whenever the game state changes
+#[derive(Component)]
+pub struct BoardEntity;
+
+pub fn apply_submitted_actions(
+    mut game: ResMut<LocalGame>,
+    mut net: Option<ResMut<NetGame>>,
+    mut submitted: EventReader<SubmitAction>,
+) {
+    for SubmitAction(action) in submitted.read() {
+        if let Some(net) = net.as_mut() {
+            if let Some(state) = net.submit(action) {
+                game.state = state.clone();
+            }
+            continue;
+        }
+        let player = game.player;
+        match game.state.apply_action(player, action) {
+            Ok(events) => {
+                for event in events {
+                    info!("event {:?}", event);
+                }
+            }
+            Err(err) => warn!("Rejected action {:?}: {}", action, err),
+        }
+    }
+}
+
+/// Bots in the other seats of a local game
+#[derive(Debug, Resource)]
+pub struct LocalBots(pub Vec<Bot>);
+
+pub fn local_bots_system(
+    time: Res<Time>,
+    mut bots: ResMut<LocalBots>,
+    mut game: ResMut<LocalGame>,
+) {
+    for bot in bots.0.iter_mut() {
+        let Some(action) = bot.tick(&game.state, time.delta()) else {
+            continue;
+        };
+        match game.state.apply_action(bot.player(), &action) {
+            Ok(events) => {
+                for event in events {
+                    info!("{:?} event {:?}", bot.player(), event);
+                }
+            }
+            Err(err) => debug!("Bot {:?} failed to perform action: {}", bot.player(), err),
+        }
+    }
+}
+
+pub fn spawn_board(
+    game: Res<LocalGame>,
+    render: Res<RenderState>,
+    board_entities: Query<Entity, With<BoardEntity>>,
+    mut commands: Commands,
+) {
+    if !(game.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    debug!("spawning board");
+    for entity in board_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for placed in game.state.board().tiles() {
+        let id = placed.spawn(&render, &mut commands);
+        commands.entity(id).insert(BoardEntity);
+    }
+    for pawn in Pawn::ALL {
+        if let Some(coord) = game.state.pawn_coord(pawn) {
+            let id = pawn.spawn(coord, &render, &mut commands);
+            commands.entity(id).insert(BoardEntity);
+        }
+    }
+}
+
+/// Shown while the local player is alerted by the "Do Something!" pawn
+#[derive(Component)]
+pub struct DoSomethingAlert;
+
+pub fn spawn_do_something_alert(
+    game: Res<LocalGame>,
+    render: Res<RenderState>,
+    alerts: Query<Entity, With<DoSomethingAlert>>,
+    mut commands: Commands,
+) {
+    if !(game.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    for entity in alerts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if game.state.do_something_target() != Some(game.player) {
+        return;
+    }
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            text: Text::from_section(
+                "Do Something!",
+                TextStyle {
+                    font_size: render.theme.font_size,
+                    color: render.theme.do_something_color,
+                    ..default()
+                },
+            ),
+            ..default()
+        },
+        DoSomethingAlert,
+    ));
+}
+
+/// Present in practice mode, where the local player is shown hints
+#[derive(Debug, Resource)]
+pub struct Practice;
+
+#[derive(Component)]
+pub struct HintHighlight;
+
+pub fn spawn_hint_highlights(
+    game: Res<LocalGame>,
+    render: Res<RenderState>,
+    highlights: Query<Entity, With<HintHighlight>>,
+    mut commands: Commands,
+) {
+    if !(game.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let cells: BoardCellSet = game
+        .state
+        .hints(game.player)
+        .into_iter()
+        .map(|hint| hint.action.dest)
+        .collect();
+    for coord in cells.iter() {
+        let id = spawn_hint_highlight(coord, &render, &mut commands);
+        commands.entity(id).insert(HintHighlight);
+    }
+}
+
+/// Keep the camera fit to the explored board
+pub fn fit_camera_to_board(game: Res<LocalGame>, mut cameras: Query<&mut AutoFitCamera>) {
+    if !game.is_changed() {
+        return;
+    }
+
+    let rect = game.state.board().world_rect();
+    for mut auto_fit in cameras.iter_mut() {
+        auto_fit.set_target(rect);
+    }
+}
@@ -0,0 +1,93 @@
+use m_mazing_core::prelude::*;
+use m_mazing_core::render::{render_board::spawn_highlight, RenderState};
+
+use crate::*;
+
+/// Pawn that keyboard actions apply to
+#[derive(Debug, Default, Resource)]
+pub struct SelectedPawn(pub Option<Pawn>);
+
+#[derive(Component)]
+pub struct SelectionHighlight;
+
+pub fn keyboard_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<GameInput>>,
+    game: Res<LocalGame>,
+    mut selected: ResMut<SelectedPawn>,
+    mut render: ResMut<RenderState>,
+    mut submit: EventWriter<SubmitAction>,
+) {
+    for input in bindings.just_pressed_actions(&keyboard_input) {
+        debug!("keyboard input {:?}", input);
+        let action = match input {
+            GameInput::SelectPawn(pawn) => {
+                selected.0 = Some(pawn);
+                continue;
+            }
+            GameInput::NextPawn => {
+                let on_board: Vec<Pawn> = Pawn::ALL
+                    .iter()
+                    .copied()
+                    .filter(|pawn| game.state.pawn_coord(*pawn).is_some())
+                    .collect();
+                let next_idx = selected
+                    .0
+                    .and_then(|pawn| on_board.iter().position(|p| *p == pawn))
+                    .map_or(0, |idx| idx + 1);
+                selected.0 = on_board.get(next_idx % on_board.len().max(1)).copied();
+                continue;
+            }
+            GameInput::Slide(dir) => BoardAction::Slide(dir),
+            GameInput::Warp => BoardAction::Warp,
+            GameInput::Escalator => BoardAction::Escalator,
+            GameInput::Explore => BoardAction::Explore,
+            GameInput::DoSomething => {
+                match game.next_do_something_target() {
+                    Some(target) => {
+                        submit.send(SubmitAction(Action::DoSomething(target)));
+                    }
+                    None => info!("Nobody to ask to do something"),
+                }
+                continue;
+            }
+            GameInput::NextTheme => {
+                let (name, theme) = Theme::next_builtin(&render.theme);
+                info!("Switching to theme {:?}", name);
+                render.theme = theme;
+                continue;
+            }
+        };
+
+        let Some(pawn) = selected.0 else {
+            info!("No pawn selected");
+            continue;
+        };
+        match game.quick_pawn_action(pawn, action) {
+            Some(pawn_action) => {
+                submit.send(SubmitAction(Action::BoardAction(pawn_action)));
+            }
+            None => debug!("{:?} cannot {:?}", pawn, action),
+        }
+    }
+}
+
+pub fn spawn_selection_highlight(
+    game: Res<LocalGame>,
+    selected: Res<SelectedPawn>,
+    render: Res<RenderState>,
+    highlights: Query<Entity, With<SelectionHighlight>>,
+    mut commands: Commands,
+) {
+    if !(game.is_changed() || selected.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(coord) = selected.0.and_then(|pawn| game.state.pawn_coord(pawn)) {
+        let id = spawn_highlight(coord, &render, &mut commands);
+        commands.entity(id).insert(SelectionHighlight);
+    }
+}
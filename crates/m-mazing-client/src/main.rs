@@ -1,13 +1,33 @@
-use anyhow::Result;
-use bevy::prelude::*;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
 use clap::Parser;
 
 use m_mazing_core::{
     bevy::{self, log::LogPlugin},
     bevy_prototype_lyon::plugin::ShapePlugin,
     log_level,
+    prelude::*,
+    render::{
+        camera::{camera_auto_fit_system, AutoFitCamera},
+        theme::{theme_clear_color_system, theme_reload_system, ThemeFile},
+        RenderState,
+    },
 };
 
+use m_mazing_protocol::message::RoomCode;
+
+use bevy::ecs as bevy_ecs; // needed for Component derive
+use bevy::prelude::*;
+
+mod game;
+mod keyboard;
+mod mouse;
+mod net;
+mod replay;
+mod touch;
+use crate::{game::*, keyboard::*, mouse::*, net::*, replay::*, touch::*};
+
 /// Game client
 #[derive(Parser, Debug, Clone)]
 #[clap(about, version, author)]
@@ -19,19 +39,243 @@ pub struct Args {
     /// Quiet log
     #[clap(long, short, action = clap::ArgAction::Count, conflicts_with = "verbose")]
     quiet: u8,
+
+    /// Number of players
+    #[clap(long, short = 'n', default_value = "1")]
+    players: u8,
+
+    /// Seat of the local player (0-based)
+    #[clap(long, short, default_value = "0")]
+    player: u8,
+
+    /// Key bindings config file
+    #[clap(long, short)]
+    key_bindings: Option<PathBuf>,
+
+    /// Print key bindings in config file format and exit
+    #[clap(long)]
+    print_key_bindings: bool,
+
+    /// Builtin theme name (light, dark, high-contrast, colorblind) or theme file to watch
+    #[clap(long, default_value = "light")]
+    theme: String,
+
+    /// Play online on the game server at this address
+    #[clap(long)]
+    server: Option<String>,
+
+    /// Code of the room to join; a new room is created if not given
+    #[clap(long, requires = "server", value_parser = parse_room_code)]
+    room: Option<RoomCode>,
+
+    /// When creating a room, stop the sand timer while a player is disconnected
+    #[clap(long, requires = "server")]
+    pause_when_vacant: bool,
+
+    /// When creating a room, do not let players use the "Do Something!" pawn
+    #[clap(long, requires = "server")]
+    no_do_something: bool,
+
+    /// Bots of this difficulty (easy, normal, hard) play the other seats; online, the
+    /// seats of the created room nobody takes
+    #[clap(long, conflicts_with = "replay")]
+    bots: Option<BotDifficulty>,
+
+    /// Name shown to other players in the room
+    #[clap(long, default_value = "player")]
+    name: String,
+
+    /// Seed of a local game, as 16 hex digits or any phrase such as a date; random if not
+    /// given
+    #[clap(long, conflicts_with = "server")]
+    seed: Option<String>,
+
+    /// Practice a local game with hints: cells the local player's actions could bring a
+    /// pawn closer to its goal are shaded
+    #[clap(long, conflicts_with_all = ["server", "replay"])]
+    practice: bool,
+
+    /// Watch a replay file instead of playing
+    #[clap(long, conflicts_with = "server")]
+    replay: Option<PathBuf>,
+}
+
+fn parse_room_code(code: &str) -> Result<RoomCode, String> {
+    RoomCode::parse(code).ok_or_else(|| format!("Invalid room code {code:?}"))
+}
+
+fn setup_system(game: Res<LocalGame>, mut commands: Commands) {
+    info!("Game seed {}", GameRng::format_seed(game.state.seed()));
+    commands.spawn((
+        Camera2dBundle::default(),
+        AutoFitCamera {
+            target: game.state.board().world_rect(),
+            margin: 1.0,
+            ..default()
+        },
+    ));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+enum ClientSystemSet {
+    Input,
+    Apply,
+    Render,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
     let level = log_level(args.verbose, args.quiet);
 
-    App::new()
-        .insert_resource(Msaa::Sample4)
+    let bindings = KeyBindings::<GameInput>::load(args.key_bindings.as_deref())?;
+    if args.print_key_bindings {
+        print!("{}", bindings.to_config_string());
+        return Ok(());
+    }
+    let conflicts = bindings.conflicts();
+    if !conflicts.is_empty() {
+        anyhow::bail!("Conflicting key bindings: {:?}", conflicts);
+    }
+
+    let (theme, theme_file) = match Theme::builtin(&args.theme) {
+        Some(theme) => (theme, None),
+        None => {
+            let path = PathBuf::from(&args.theme);
+            let theme = Theme::load(&path).context("Failed to load theme")?;
+            (theme, Some(ThemeFile::new(path)))
+        }
+    };
+
+    let tileset = game_tileset().context("Failed to parse game tiles")?;
+    let viewer = match &args.replay {
+        Some(path) => {
+            let replay = Replay::load(path)?;
+            let playback =
+                ReplayPlayback::new(replay, &tileset).context("Failed to check replay")?;
+            Some(ReplayViewer::new(playback))
+        }
+        None => None,
+    };
+    let state = match &viewer {
+        Some(viewer) => viewer.playback.state().clone(),
+        None => {
+            let seed = match &args.seed {
+                Some(seed) => GameRng::parse_seed(seed),
+                None => GameRng::random_seed(),
+            };
+            GameState::new(args.players, &Scenario::first(), &tileset, seed)
+                .context("Failed to create game")?
+        }
+    };
+    if viewer.is_none() && state.player_actions(PlayerId(args.player)).is_none() {
+        anyhow::bail!(
+            "Invalid player {} for {} player game",
+            args.player,
+            args.players
+        );
+    }
+
+    let mut app = App::new();
+    if let Some(theme_file) = theme_file {
+        app.insert_resource(theme_file);
+    }
+    if let Some(addr) = &args.server {
+        let request = match args.room {
+            Some(code) => RoomRequest::Join(code),
+            None => RoomRequest::Create {
+                num_players: args.players,
+                pause_when_vacant: args.pause_when_vacant,
+                do_something: !args.no_do_something,
+                bots: args.bots,
+            },
+        };
+        app.insert_resource(NetGame::connect(addr, args.name, request)?)
+            .add_systems(Update, net_receive_system.in_set(ClientSystemSet::Input));
+    }
+    if let (Some(difficulty), None) = (args.bots, &args.server) {
+        let bots = (0..args.players)
+            .filter(|player| *player != args.player)
+            .map(|player| Bot::new(PlayerId(player), difficulty))
+            .collect();
+        app.insert_resource(LocalBots(bots))
+            .add_systems(Update, local_bots_system.in_set(ClientSystemSet::Apply));
+    }
+    if args.practice {
+        app.insert_resource(Practice);
+    }
+    if let Some(viewer) = viewer {
+        app.insert_resource(viewer)
+            .insert_resource(KeyBindings::<ReplayInput>::default())
+            .add_systems(
+                Update,
+                (replay_input_system, replay_playback_system)
+                    .chain()
+                    .in_set(ClientSystemSet::Input),
+            );
+    }
+    app.insert_resource(Msaa::Sample4)
+        .insert_resource(LocalGame {
+            state,
+            player: PlayerId(args.player),
+        })
+        .insert_resource(RenderState { theme })
+        .insert_resource(bindings)
+        .init_resource::<PawnDrag>()
+        .init_resource::<SelectedPawn>()
+        .init_resource::<TouchTracker>()
+        .init_resource::<ActionWheel>()
+        .add_event::<SubmitAction>()
+        .add_event::<Gesture>()
         .add_plugins(DefaultPlugins.set(LogPlugin {
             level,
             ..Default::default()
         }))
         .add_plugins(ShapePlugin)
+        .add_systems(Startup, setup_system)
+        .configure_sets(
+            Update,
+            (
+                ClientSystemSet::Input,
+                ClientSystemSet::Apply,
+                ClientSystemSet::Render,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                keyboard_input_system,
+                mouse_drag_system,
+                theme_reload_system,
+                (touch_gesture_system, touch_action_system).chain(),
+            )
+                .in_set(ClientSystemSet::Input),
+        )
+        .add_systems(
+            Update,
+            apply_submitted_actions
+                .run_if(not(resource_exists::<ReplayViewer>))
+                .in_set(ClientSystemSet::Apply),
+        )
+        .add_systems(
+            Update,
+            spawn_hint_highlights
+                .run_if(resource_exists::<Practice>)
+                .in_set(ClientSystemSet::Render),
+        )
+        .add_systems(
+            Update,
+            (
+                spawn_board,
+                spawn_drag_highlights,
+                spawn_selection_highlight,
+                spawn_action_wheel,
+                spawn_do_something_alert,
+                (fit_camera_to_board, camera_auto_fit_system).chain(),
+                theme_clear_color_system,
+            )
+                .in_set(ClientSystemSet::Render),
+        )
         .run();
 
     Ok(())
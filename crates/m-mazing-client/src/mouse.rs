@@ -0,0 +1,117 @@
+use bevy::window::PrimaryWindow;
+use m_mazing_core::prelude::*;
+use m_mazing_core::render::{render_board::spawn_highlight, RenderState};
+
+use crate::*;
+
+/// Pawn being dragged with the mouse and where it may be dropped
+#[derive(Debug, Default, Resource)]
+pub struct PawnDrag {
+    pub pawn: Option<Pawn>,
+    pub targets: Vec<PawnAction>,
+}
+
+impl PawnDrag {
+    fn start(game: &LocalGame, pawn: Pawn) -> Self {
+        let targets = game
+            .state
+            .legal_actions(game.player, pawn)
+            .into_iter()
+            .filter(|action| action.action != BoardAction::Explore)
+            .collect();
+        Self {
+            pawn: Some(pawn),
+            targets,
+        }
+    }
+
+    fn target(&self, coord: BoardCoord) -> Option<PawnAction> {
+        self.targets
+            .iter()
+            .copied()
+            .find(|action| action.dest == coord)
+    }
+}
+
+#[derive(Component)]
+pub struct DragHighlight;
+
+/// Board cell under the cursor
+pub fn cursor_board_coord(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<BoardCoord> {
+    let cursor = window.cursor_position()?;
+    let world = camera.viewport_to_world_2d(camera_transform, cursor)?;
+    Some(BoardCoord::from_world(world))
+}
+
+pub fn mouse_drag_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    game: Res<LocalGame>,
+    mut drag: ResMut<PawnDrag>,
+    mut selected: ResMut<SelectedPawn>,
+    mut submit: EventWriter<SubmitAction>,
+) {
+    let pressed = mouse_input.just_pressed(MouseButton::Left);
+    let released = mouse_input.just_released(MouseButton::Left);
+    if !(pressed || released) {
+        return;
+    }
+
+    let coord = match (windows.get_single(), cameras.get_single()) {
+        (Ok(window), Ok((camera, camera_transform))) => {
+            cursor_board_coord(window, camera, camera_transform)
+        }
+        _ => None,
+    };
+
+    if pressed {
+        *drag = match coord.and_then(|coord| game.state.pawn_at(coord)) {
+            Some(pawn) => {
+                selected.0 = Some(pawn);
+                PawnDrag::start(&game, pawn)
+            }
+            None => PawnDrag::default(),
+        };
+        debug!("drag start {:?}", drag);
+    }
+
+    if released {
+        match coord.and_then(|coord| drag.target(coord)) {
+            Some(action) => {
+                submit.send(SubmitAction(Action::BoardAction(action)));
+            }
+            None => {
+                if drag.pawn.is_some() {
+                    debug!("drag cancelled");
+                }
+            }
+        }
+        *drag = PawnDrag::default();
+    }
+}
+
+pub fn spawn_drag_highlights(
+    drag: Res<PawnDrag>,
+    render: Res<RenderState>,
+    highlights: Query<Entity, With<DragHighlight>>,
+    mut commands: Commands,
+) {
+    if !(drag.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // several actions may lead to the same cell
+    let cells: BoardCellSet = drag.targets.iter().map(|action| action.dest).collect();
+    for coord in cells.iter() {
+        let id = spawn_highlight(coord, &render, &mut commands);
+        commands.entity(id).insert(DragHighlight);
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+use m_mazing_protocol::prelude::*;
+
+use crate::*;
+
+/// Time between attempts to get back into the game after the connection dropped
+const RECONNECT_PERIOD: Duration = Duration::from_secs(2);
+
+/// Connect to `addr` and start a thread that reads server messages
+fn open(addr: &str) -> Result<(TcpStream, Receiver<ServerMessage>)> {
+    let sock_addr = addr
+        .to_socket_addrs()?
+        .next()
+        .with_context(|| format!("No address for {addr}"))?;
+    let mut stream = TcpStream::connect_timeout(&sock_addr, RECONNECT_PERIOD)
+        .with_context(|| format!("Failed to connect to {addr}"))?;
+    stream.set_nodelay(true)?;
+    handshake(&mut stream).context("Failed handshake with server")?;
+
+    let (sender, incoming) = mpsc::channel();
+    let mut reader = stream.try_clone()?;
+    std::thread::spawn(move || loop {
+        match read_message(&mut reader) {
+            Ok(msg) => {
+                if sender.send(msg).is_err() {
+                    return;
+                }
+            }
+            Err(err) => {
+                warn!("Lost connection to server: {err}");
+                return;
+            }
+        }
+    });
+    Ok((stream, incoming))
+}
+
+/// Room to join, or settings of the room to create
+#[derive(Debug, Clone)]
+pub enum RoomRequest {
+    Create {
+        num_players: u8,
+        pause_when_vacant: bool,
+        do_something: bool,
+        bots: Option<BotDifficulty>,
+    },
+    Join(RoomCode),
+}
+
+/// Connection to a game server; present only when playing online
+#[derive(Resource)]
+pub struct NetGame {
+    addr: String,
+    stream: TcpStream,
+
+    /// Filled by a reader thread, since reads block
+    incoming: Mutex<Receiver<ServerMessage>>,
+    request: RoomRequest,
+    you: Option<MemberId>,
+
+    /// Room and token to get back in with after the connection drops
+    session: Option<(RoomCode, SessionToken)>,
+    game: Option<PredictedGame>,
+
+    /// Set while disconnected; time of the next reconnect attempt
+    retry_at: Option<Instant>,
+}
+
+impl NetGame {
+    pub fn connect(addr: &str, name: String, request: RoomRequest) -> Result<Self> {
+        let (mut stream, incoming) = open(addr)?;
+        let msg = match &request {
+            RoomRequest::Create { .. } => ClientMessage::CreateRoom { name },
+            RoomRequest::Join(code) => ClientMessage::JoinRoom {
+                code: code.clone(),
+                name,
+            },
+        };
+        write_message(&mut stream, &msg)?;
+
+        Ok(Self {
+            addr: addr.to_string(),
+            stream,
+            incoming: Mutex::new(incoming),
+            request,
+            you: None,
+            session: None,
+            game: None,
+            retry_at: None,
+        })
+    }
+
+    /// Try to take back our seat with a new connection
+    fn reconnect(&mut self) -> Result<()> {
+        let (code, token) = self.session.clone().context("Not in a room")?;
+        let (mut stream, incoming) = open(&self.addr)?;
+        let next_event = self
+            .game
+            .as_ref()
+            .map_or(0, PredictedGame::num_confirmed_events);
+        write_message(
+            &mut stream,
+            &ClientMessage::Reconnect {
+                code,
+                token,
+                next_event,
+            },
+        )?;
+        self.stream = stream;
+        self.incoming = Mutex::new(incoming);
+        Ok(())
+    }
+
+    fn send(&mut self, msg: &ClientMessage) {
+        if let Err(err) = write_message(&mut self.stream, msg) {
+            warn!("Failed to send to server: {err}");
+        }
+    }
+
+    fn handle_room(&mut self, room: &RoomInfo) {
+        let is_host = Some(room.host) == self.you;
+        let me = room
+            .members
+            .iter()
+            .find(|member| Some(member.id) == self.you);
+        if !room.in_game && me.is_some_and(|me| !me.ready) {
+            self.send(&ClientMessage::SetReady(true));
+        }
+
+        // the host starts as soon as the room is full, or bots can fill it, and everyone
+        // is ready
+        let all_ready = room.members.iter().all(|member| member.ready);
+        let full = room.config.bots.is_some()
+            || room.members.len() == usize::from(room.config.num_players);
+        if is_host && !room.in_game && all_ready && full {
+            self.send(&ClientMessage::StartGame);
+        }
+    }
+
+    /// Handle a message from the server; returns true if the shown game changed
+    fn handle(&mut self, msg: ServerMessage, tileset: &[(String, Tile)]) -> bool {
+        match msg {
+            ServerMessage::RoomJoined { you, room, token } => {
+                info!(
+                    "Joined room {}; others join with --room {}",
+                    room.code, room.code
+                );
+                self.you = Some(you);
+                self.session = Some((room.code.clone(), token));
+                if let RoomRequest::Create {
+                    num_players,
+                    pause_when_vacant,
+                    do_something,
+                    bots,
+                } = self.request
+                {
+                    let config = RoomConfig {
+                        num_players,
+                        pause_when_vacant,
+                        do_something,
+                        bots,
+                        ..room.config
+                    };
+                    if !room.in_game && room.config != config {
+                        self.send(&ClientMessage::Configure(config));
+                    }
+                }
+                self.handle_room(&room);
+            }
+            ServerMessage::RoomUpdate(room) => self.handle_room(&room),
+            ServerMessage::RoomLeft { kicked } => {
+                warn!("Left room{}", if kicked { "; kicked by host" } else { "" });
+                self.session = None;
+            }
+            ServerMessage::RoleAssignment {
+                player,
+                num_players,
+                scenario,
+                seed,
+                actions,
+            } => {
+                info!("Playing as {player:?} with actions {actions:?}");
+                let Some(scenario) = Scenario::builtin(scenario) else {
+                    error!("Server chose unknown scenario {scenario}");
+                    return false;
+                };
+                match GameState::new(num_players, &scenario, tileset, seed) {
+                    Ok(state) => {
+                        self.game = Some(PredictedGame::new(state, player));
+                        return true;
+                    }
+                    Err(err) => error!("Failed to create game: {err}"),
+                }
+            }
+            ServerMessage::TimerSync(timer) => debug!("Timer {timer:?}"),
+            ServerMessage::Pong { .. } => (),
+            ServerMessage::Events { .. }
+            | ServerMessage::Snapshot { .. }
+            | ServerMessage::Error(ServerError {
+                kind: ServerErrorKind::ActionRejected { .. },
+                ..
+            }) => {
+                let Some(game) = self.game.as_mut() else {
+                    return false;
+                };
+                if let ServerMessage::Error(err) = &msg {
+                    warn!("Server rejected action: {}", err.msg);
+                }
+                match game.handle(&msg) {
+                    Ok(rolled_back) => {
+                        for action in rolled_back {
+                            info!("Rolled back {action:?}");
+                        }
+                    }
+                    Err(err) => error!("Out of sync with server: {err}"),
+                }
+                return true;
+            }
+            ServerMessage::Error(err) => warn!("Server error {:?}: {}", err.kind, err.msg),
+        }
+        false
+    }
+
+    /// Predict `action` and send it to the server
+    pub fn submit(&mut self, action: &Action) -> Option<&GameState> {
+        let game = self.game.as_mut()?;
+        match game.submit(action.clone()) {
+            Ok(msg) => {
+                self.send(&msg);
+                self.game.as_ref().map(PredictedGame::state)
+            }
+            Err(err) => {
+                warn!("Rejected action {:?}: {}", action, err);
+                None
+            }
+        }
+    }
+}
+
+pub fn net_receive_system(
+    mut net: ResMut<NetGame>,
+    mut game: ResMut<LocalGame>,
+    mut tileset: Local<Option<Vec<(String, Tile)>>>,
+) {
+    let tileset = tileset.get_or_insert_with(|| game_tileset().expect("valid game tiles"));
+    let mut changed = false;
+    loop {
+        let msg = net.incoming.lock().expect("reader lock").try_recv();
+        match msg {
+            Ok(msg) => changed |= net.handle(msg, tileset),
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => {
+                let now = Instant::now();
+                match net.retry_at {
+                    None => {
+                        error!("Disconnected from server");
+                        net.retry_at = Some(now);
+                    }
+                    Some(retry_at) if retry_at <= now && net.session.is_some() => {
+                        match net.reconnect() {
+                            Ok(()) => {
+                                info!("Reconnected to server");
+                                net.retry_at = None;
+                                continue;
+                            }
+                            Err(err) => debug!("Failed to reconnect: {err:#}"),
+                        }
+                        net.retry_at = Some(now + RECONNECT_PERIOD);
+                    }
+                    Some(_) => (),
+                }
+                break;
+            }
+        }
+    }
+
+    if changed {
+        if let Some(predicted) = net.game.as_ref() {
+            game.state = predicted.state().clone();
+            game.player = predicted.player();
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+/// How far one seek key press moves
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 16.0;
+
+/// Replay controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReplayInput {
+    TogglePause,
+    SeekBack,
+    SeekForward,
+    SlowDown,
+    SpeedUp,
+    Restart,
+}
+
+impl InputAction for ReplayInput {
+    const ALL: &'static [Self] = &[
+        Self::TogglePause,
+        Self::SeekBack,
+        Self::SeekForward,
+        Self::SlowDown,
+        Self::SpeedUp,
+        Self::Restart,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::TogglePause => "toggle-pause",
+            Self::SeekBack => "seek-back",
+            Self::SeekForward => "seek-forward",
+            Self::SlowDown => "slow-down",
+            Self::SpeedUp => "speed-up",
+            Self::Restart => "restart",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::TogglePause => "pause or resume",
+            Self::SeekBack => "go back 5 seconds",
+            Self::SeekForward => "go forward 5 seconds",
+            Self::SlowDown => "play at half speed",
+            Self::SpeedUp => "play at double speed",
+            Self::Restart => "go to the start",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Self::TogglePause => &[KeyCode::Space],
+            Self::SeekBack => &[KeyCode::ArrowLeft],
+            Self::SeekForward => &[KeyCode::ArrowRight],
+            Self::SlowDown => &[KeyCode::ArrowDown],
+            Self::SpeedUp => &[KeyCode::ArrowUp],
+            Self::Restart => &[KeyCode::Home],
+        }
+    }
+}
+
+/// Replay being watched instead of playing a game
+#[derive(Debug, Resource)]
+pub struct ReplayViewer {
+    pub playback: ReplayPlayback,
+    pub paused: bool,
+    pub speed: f32,
+}
+
+impl ReplayViewer {
+    pub fn new(playback: ReplayPlayback) -> Self {
+        Self {
+            playback,
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    fn seek(&mut self, time: Duration, game: &mut LocalGame) {
+        if self.playback.seek(time) {
+            game.state = self.playback.state().clone();
+        }
+    }
+
+    fn log_position(&self) {
+        info!(
+            "Replay at {:.1}s of {:.1}s, {}x speed{}",
+            self.playback.time().as_secs_f32(),
+            self.playback.length().as_secs_f32(),
+            self.speed,
+            if self.paused { ", paused" } else { "" }
+        );
+    }
+}
+
+pub fn replay_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<ReplayInput>>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut game: ResMut<LocalGame>,
+) {
+    for input in bindings.just_pressed_actions(&keyboard_input) {
+        let time = viewer.playback.time();
+        match input {
+            ReplayInput::TogglePause => viewer.paused = !viewer.paused,
+            ReplayInput::SeekBack => viewer.seek(time.saturating_sub(SEEK_STEP), &mut game),
+            ReplayInput::SeekForward => viewer.seek(time + SEEK_STEP, &mut game),
+            ReplayInput::SlowDown => viewer.speed = (viewer.speed / 2.0).max(MIN_SPEED),
+            ReplayInput::SpeedUp => viewer.speed = (viewer.speed * 2.0).min(MAX_SPEED),
+            ReplayInput::Restart => viewer.seek(Duration::ZERO, &mut game),
+        }
+        viewer.log_position();
+    }
+}
+
+pub fn replay_playback_system(
+    time: Res<Time>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut game: ResMut<LocalGame>,
+) {
+    if viewer.paused || viewer.playback.is_finished() {
+        return;
+    }
+    let target = viewer.playback.time() + time.delta().mul_f32(viewer.speed);
+    viewer.seek(target, &mut game);
+    if viewer.playback.is_finished() {
+        info!("Replay finished: {:?}", viewer.playback.state().phase());
+    }
+}
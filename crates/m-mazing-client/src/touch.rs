@@ -0,0 +1,490 @@
+use bevy::input::touch::{TouchInput, TouchPhase};
+use m_mazing_core::prelude::*;
+use m_mazing_core::render::{camera::AutoFitCamera, render_board::spawn_highlight, RenderState};
+
+use crate::*;
+
+/// Max distance (logical pixels) a finger may move and still count as a tap
+const TAP_MAX_DISTANCE: f32 = 20.0;
+
+/// How long (seconds) a finger must be held still to count as a long press
+const LONG_PRESS_SECS: f32 = 0.5;
+
+/// Pinch zoom limits relative to the auto fit zoom
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 5.0;
+
+/// Recognized touch gesture; positions are in screen (window) coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Event)]
+pub enum Gesture {
+    Tap(Vec2),
+    Swipe {
+        start: Vec2,
+        direction: CartesianDirection,
+    },
+    LongPress(Vec2),
+
+    /// Two-finger pinch; `scale` is the ratio of the new and old finger distance
+    Pinch {
+        center: Vec2,
+        scale: f32,
+    },
+
+    /// Two-finger pan by the movement of the center between the fingers
+    Pan(Vec2),
+}
+
+#[derive(Debug, Clone)]
+struct TrackedTouch {
+    id: u64,
+    start: Vec2,
+    position: Vec2,
+    start_secs: f32,
+
+    /// Touch is (or was) part of a two-finger gesture or already produced a long press
+    consumed: bool,
+}
+
+/// Fingers currently on the screen
+#[derive(Debug, Default, Resource)]
+pub struct TouchTracker {
+    touches: Vec<TrackedTouch>,
+}
+
+impl TouchTracker {
+    fn two_finger_positions(&self) -> Option<(Vec2, Vec2)> {
+        match self.touches.as_slice() {
+            [a, b] => Some((a.position, b.position)),
+            _ => None,
+        }
+    }
+}
+
+fn swipe_direction(delta: Vec2) -> CartesianDirection {
+    // screen coordinates have y going down
+    if delta.x.abs() >= delta.y.abs() {
+        if delta.x > 0.0 {
+            CartesianDirection::Right
+        } else {
+            CartesianDirection::Left
+        }
+    } else if delta.y > 0.0 {
+        CartesianDirection::Down
+    } else {
+        CartesianDirection::Up
+    }
+}
+
+pub fn touch_gesture_system(
+    time: Res<Time>,
+    mut touch_events: EventReader<TouchInput>,
+    mut tracker: ResMut<TouchTracker>,
+    mut gestures: EventWriter<Gesture>,
+) {
+    let now = time.elapsed_seconds();
+
+    for event in touch_events.read() {
+        match event.phase {
+            TouchPhase::Started => {
+                tracker.touches.push(TrackedTouch {
+                    id: event.id,
+                    start: event.position,
+                    position: event.position,
+                    start_secs: now,
+                    consumed: false,
+                });
+                if tracker.touches.len() > 1 {
+                    for touch in tracker.touches.iter_mut() {
+                        touch.consumed = true;
+                    }
+                }
+            }
+            TouchPhase::Moved => {
+                let before = tracker.two_finger_positions();
+                if let Some(touch) = tracker.touches.iter_mut().find(|t| t.id == event.id) {
+                    touch.position = event.position;
+                }
+                if let (Some((a0, b0)), Some((a1, b1))) = (before, tracker.two_finger_positions()) {
+                    let center0 = 0.5 * (a0 + b0);
+                    let center1 = 0.5 * (a1 + b1);
+                    let dist0 = a0.distance(b0);
+                    let dist1 = a1.distance(b1);
+                    if dist0 > f32::EPSILON && dist1 > f32::EPSILON && dist0 != dist1 {
+                        gestures.send(Gesture::Pinch {
+                            center: center1,
+                            scale: dist1 / dist0,
+                        });
+                    }
+                    if center0 != center1 {
+                        gestures.send(Gesture::Pan(center1 - center0));
+                    }
+                }
+            }
+            TouchPhase::Ended => {
+                let Some(idx) = tracker.touches.iter().position(|t| t.id == event.id) else {
+                    continue;
+                };
+                let touch = tracker.touches.remove(idx);
+                if touch.consumed {
+                    continue;
+                }
+                let delta = event.position - touch.start;
+                if delta.length() <= TAP_MAX_DISTANCE {
+                    gestures.send(Gesture::Tap(touch.start));
+                } else {
+                    gestures.send(Gesture::Swipe {
+                        start: touch.start,
+                        direction: swipe_direction(delta),
+                    });
+                }
+            }
+            TouchPhase::Canceled => {
+                tracker.touches.retain(|t| t.id != event.id);
+            }
+        }
+    }
+
+    for touch in tracker.touches.iter_mut() {
+        let held_still = touch.position.distance(touch.start) <= TAP_MAX_DISTANCE;
+        if !touch.consumed && held_still && now - touch.start_secs >= LONG_PRESS_SECS {
+            touch.consumed = true;
+            gestures.send(Gesture::LongPress(touch.start));
+        }
+    }
+}
+
+/// Actions offered around a long-pressed pawn
+#[derive(Debug, Default, Resource)]
+pub struct ActionWheel {
+    pub pawn: Option<Pawn>,
+    pub options: Vec<(PawnAction, BoardCoord)>,
+}
+
+impl ActionWheel {
+    const ACTIONS: [BoardAction; 3] = [
+        BoardAction::Warp,
+        BoardAction::Escalator,
+        BoardAction::Explore,
+    ];
+
+    /// Options are placed on the cells above the pawn
+    const OFFSETS: [BoardCoord; 3] = [
+        BoardCoord::new(-1, -1),
+        BoardCoord::new(0, -1),
+        BoardCoord::new(1, -1),
+    ];
+
+    fn open(game: &LocalGame, pawn: Pawn) -> Self {
+        let Some(pawn_coord) = game.state.pawn_coord(pawn) else {
+            return Self::default();
+        };
+        let options = Self::ACTIONS
+            .iter()
+            .filter_map(|action| game.quick_pawn_action(pawn, *action))
+            .zip(Self::OFFSETS)
+            .map(|(action, offset)| (action, pawn_coord + offset))
+            .collect();
+        Self {
+            pawn: Some(pawn),
+            options,
+        }
+    }
+
+    fn label(action: BoardAction) -> &'static str {
+        match action {
+            BoardAction::Warp => "warp",
+            BoardAction::Escalator => "esc",
+            BoardAction::Explore => "explore",
+            BoardAction::Slide(_) => "slide",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ActionWheelItem;
+
+fn screen_to_world(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    screen_pos: Vec2,
+) -> Option<Vec2> {
+    camera.viewport_to_world_2d(camera_transform, screen_pos)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn touch_action_system(
+    mut gestures: EventReader<Gesture>,
+    mut cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        &mut Transform,
+        &mut OrthographicProjection,
+        &mut AutoFitCamera,
+    )>,
+    game: Res<LocalGame>,
+    mut selected: ResMut<SelectedPawn>,
+    mut wheel: ResMut<ActionWheel>,
+    mut submit: EventWriter<SubmitAction>,
+) {
+    let Ok((camera, camera_global, mut camera_transform, mut projection, mut auto_fit)) =
+        cameras.get_single_mut()
+    else {
+        return;
+    };
+    let board_coord = |screen_pos: Vec2| {
+        screen_to_world(camera, camera_global, screen_pos).map(BoardCoord::from_world)
+    };
+
+    for gesture in gestures.read() {
+        debug!("gesture {:?}", gesture);
+        match *gesture {
+            Gesture::Tap(pos) => {
+                let coord = board_coord(pos);
+                if wheel.pawn.is_some() {
+                    let chosen = wheel
+                        .options
+                        .iter()
+                        .find(|(_action, option_coord)| Some(*option_coord) == coord)
+                        .map(|(action, _coord)| *action);
+                    if let Some(action) = chosen {
+                        submit.send(SubmitAction(Action::BoardAction(action)));
+                    }
+                    *wheel = ActionWheel::default();
+                    continue;
+                }
+                if let Some(pawn) = coord.and_then(|coord| game.state.pawn_at(coord)) {
+                    selected.0 = Some(pawn);
+                }
+            }
+            Gesture::Swipe { start, direction } => {
+                let pawn = board_coord(start)
+                    .and_then(|coord| game.state.pawn_at(coord))
+                    .or(selected.0);
+                let Some(pawn) = pawn else {
+                    continue;
+                };
+                selected.0 = Some(pawn);
+                match game.quick_pawn_action(pawn, BoardAction::Slide(direction)) {
+                    Some(action) => {
+                        submit.send(SubmitAction(Action::BoardAction(action)));
+                    }
+                    None => debug!("{:?} cannot slide {:?}", pawn, direction),
+                }
+            }
+            Gesture::LongPress(pos) => {
+                if let Some(pawn) = board_coord(pos).and_then(|coord| game.state.pawn_at(coord)) {
+                    selected.0 = Some(pawn);
+                    *wheel = ActionWheel::open(&game, pawn);
+                }
+            }
+            Gesture::Pinch { scale, .. } => {
+                // manual zoom overrides the auto fit until the board changes
+                auto_fit.enabled = false;
+                let fit_scale = camera
+                    .logical_viewport_size()
+                    .map_or(projection.scale, |size| auto_fit.fit(size).scale);
+                projection.scale =
+                    (projection.scale / scale).clamp(fit_scale * MIN_ZOOM, fit_scale * MAX_ZOOM);
+            }
+            Gesture::Pan(delta) => {
+                auto_fit.enabled = false;
+                // pan using the world positions under the fingers so zoom is accounted for
+                let center = camera
+                    .logical_viewport_size()
+                    .map_or(Vec2::ZERO, |size| 0.5 * size);
+                if let (Some(from), Some(to)) = (
+                    screen_to_world(camera, camera_global, center),
+                    screen_to_world(camera, camera_global, center + delta),
+                ) {
+                    camera_transform.translation -= (to - from).extend(0.0);
+                }
+            }
+        }
+    }
+}
+
+pub fn spawn_action_wheel(
+    wheel: Res<ActionWheel>,
+    render: Res<RenderState>,
+    items: Query<Entity, With<ActionWheelItem>>,
+    mut commands: Commands,
+) {
+    if !(wheel.is_changed() || render.is_changed()) {
+        return;
+    }
+
+    for entity in items.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (action, coord) in wheel.options.iter() {
+        let id = spawn_highlight(*coord, &render, &mut commands);
+        let label = commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    ActionWheel::label(action.action),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: render.theme.font_color,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, 1.0).with_scale(Vec3::splat(0.01)),
+                ..default()
+            })
+            .id();
+        commands
+            .entity(id)
+            .insert(ActionWheelItem)
+            .push_children(&[label]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .init_resource::<TouchTracker>()
+            .add_event::<TouchInput>()
+            .add_event::<Gesture>()
+            .add_systems(Update, touch_gesture_system);
+        app.update();
+        app
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, position: Vec2) {
+        app.world.send_event(TouchInput {
+            phase,
+            position,
+            window: Entity::PLACEHOLDER,
+            force: None,
+            id,
+        });
+    }
+
+    fn gestures(app: &mut App) -> Vec<Gesture> {
+        app.world
+            .resource_mut::<Events<Gesture>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn tap() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, Vec2::new(100., 100.));
+        app.update();
+        touch(&mut app, 0, TouchPhase::Moved, Vec2::new(105., 100.));
+        touch(&mut app, 0, TouchPhase::Ended, Vec2::new(105., 100.));
+        app.update();
+
+        assert_eq!(gestures(&mut app), [Gesture::Tap(Vec2::new(100., 100.))]);
+    }
+
+    #[test]
+    fn swipe() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, Vec2::new(100., 100.));
+        app.update();
+        touch(&mut app, 0, TouchPhase::Moved, Vec2::new(110., 160.));
+        touch(&mut app, 0, TouchPhase::Ended, Vec2::new(110., 160.));
+        app.update();
+
+        assert_eq!(
+            gestures(&mut app),
+            [Gesture::Swipe {
+                start: Vec2::new(100., 100.),
+                direction: CartesianDirection::Down,
+            }]
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, Vec2::new(100., 100.));
+        app.update();
+        assert_eq!(gestures(&mut app), []);
+
+        for _ in 0..(LONG_PRESS_SECS / FRAME.as_secs_f32()).ceil() as u32 {
+            app.update();
+        }
+        assert_eq!(
+            gestures(&mut app),
+            [Gesture::LongPress(Vec2::new(100., 100.))]
+        );
+
+        // releasing after a long press is not a tap
+        touch(&mut app, 0, TouchPhase::Ended, Vec2::new(100., 100.));
+        app.update();
+        assert_eq!(gestures(&mut app), []);
+    }
+
+    #[test]
+    fn pinch_and_pan() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, Vec2::new(100., 100.));
+        touch(&mut app, 1, TouchPhase::Started, Vec2::new(200., 100.));
+        app.update();
+
+        // spread fingers apart symmetrically: zoom without pan
+        touch(&mut app, 0, TouchPhase::Moved, Vec2::new(50., 100.));
+        touch(&mut app, 1, TouchPhase::Moved, Vec2::new(250., 100.));
+        app.update();
+        let zoom = gestures(&mut app);
+        assert!(matches!(
+            zoom.as_slice(),
+            [
+                Gesture::Pinch { .. },
+                Gesture::Pan(_),
+                Gesture::Pinch { .. },
+                Gesture::Pan(_)
+            ]
+        ));
+        let total_scale: f32 = zoom
+            .iter()
+            .filter_map(|g| match g {
+                Gesture::Pinch { scale, .. } => Some(*scale),
+                _ => None,
+            })
+            .product();
+        assert!((total_scale - 2.0).abs() < 1e-4, "scale = {}", total_scale);
+
+        // move both fingers the same way: net pan without net zoom
+        touch(&mut app, 0, TouchPhase::Moved, Vec2::new(50., 130.));
+        touch(&mut app, 1, TouchPhase::Moved, Vec2::new(250., 130.));
+        app.update();
+        let moved = gestures(&mut app);
+        let pan: Vec2 = moved
+            .iter()
+            .filter_map(|g| match g {
+                Gesture::Pan(delta) => Some(*delta),
+                _ => None,
+            })
+            .sum();
+        let scale: f32 = moved
+            .iter()
+            .filter_map(|g| match g {
+                Gesture::Pinch { scale, .. } => Some(*scale),
+                _ => None,
+            })
+            .product();
+        assert_eq!(pan, Vec2::new(0., 30.));
+        assert!((scale - 1.0).abs() < 1e-4, "scale = {}", scale);
+
+        // lifting fingers after a two-finger gesture does not tap
+        touch(&mut app, 0, TouchPhase::Ended, Vec2::new(50., 130.));
+        touch(&mut app, 1, TouchPhase::Ended, Vec2::new(250., 130.));
+        app.update();
+        assert_eq!(gestures(&mut app), []);
+    }
+}
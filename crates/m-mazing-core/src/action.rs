@@ -1,6 +1,9 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
 use crate::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BoardAction {
     Escalator,
     Explore,
@@ -8,8 +11,63 @@ pub enum BoardAction {
     Warp,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+impl BoardAction {
+    pub const ALL: [BoardAction; 7] = [
+        Self::Escalator,
+        Self::Explore,
+        Self::Slide(CartesianDirection::Up),
+        Self::Slide(CartesianDirection::Left),
+        Self::Slide(CartesianDirection::Down),
+        Self::Slide(CartesianDirection::Right),
+        Self::Warp,
+    ];
+}
+
+/// `BoardAction` performed with a specific pawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PawnAction {
+    pub pawn: Pawn,
+    pub action: BoardAction,
+
+    /// Cell the pawn ends up on.
+    /// For `BoardAction::Explore`, the entrance cell of the newly explored tile.
+    pub dest: BoardCoord,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
-    BoardAction(BoardAction),
-    //DoSomething(PlayerId)
+    BoardAction(PawnAction),
+
+    /// Put the "Do Something!" pawn in front of another player to alert them
+    DoSomething(PlayerId),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ActionError {
+    #[error("Unknown player {0:?}")]
+    UnknownPlayer(PlayerId),
+
+    #[error("Player {player:?} is not allowed to perform {action:?}")]
+    NotAllowed {
+        player: PlayerId,
+        action: BoardAction,
+    },
+
+    #[error("Pawn {0:?} is not on the board")]
+    PawnNotOnBoard(Pawn),
+
+    #[error("Illegal action {0:?}")]
+    Illegal(PawnAction),
+
+    #[error("No tiles left to explore")]
+    PileEmpty,
+
+    #[error("Unable to place tile: {0}")]
+    Placement(#[from] TilePlacementError),
+
+    #[error("Game is over")]
+    GameOver,
+
+    #[error("Cannot put the \"Do Something!\" pawn in front of {0:?}")]
+    DoSomethingTarget(PlayerId),
 }
@@ -0,0 +1,391 @@
+use crate::prelude::*;
+
+/// Compact copy of a `Board` for fast movement queries, e.g. in searches.
+///
+/// Cells are bits in one `u64` per row, or per column for vertical moves, so a slide is
+/// a few bit operations instead of a walk that looks up each wall on both sides. The
+/// board's bounds must fit in 64 cells each way.
+///
+/// Like `Board`, a `BitBoard` ignores pawns; cells they occupy are passed in as an
+/// [`Occupied`] mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitBoard {
+    /// Board coordinate of bit 0 of the first row
+    origin: BoardCoord,
+    width: u32,
+    height: u32,
+
+    /// Cells a pawn of each capability can step from in each direction, indexed by
+    /// [`capability`] and then [`dir_idx`]; rows for horizontal directions, columns for
+    /// vertical ones
+    steps: [[Vec<u64>; 4]; 2],
+
+    /// Warp cells of each pawn, as rows
+    warps: [Vec<u64>; Pawn::ALL.len()],
+
+    /// Cells with an escalator, as rows
+    escalator_cells: Vec<u64>,
+
+    /// Both ends of each escalator, in tile order
+    escalators: Vec<[BoardCoord; 2]>,
+}
+
+/// Cells occupied by pawns, as rows and as columns of a [`BitBoard`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Occupied {
+    rows: Vec<u64>,
+    cols: Vec<u64>,
+}
+
+/// Pawns that can pass orange-only walls step differently from the others
+fn capability(pawn: Pawn) -> usize {
+    usize::from(pawn == Pawn::Orange)
+}
+
+fn dir_idx(direction: CartesianDirection) -> usize {
+    match direction {
+        CartesianDirection::Right => 0,
+        CartesianDirection::Up => 1,
+        CartesianDirection::Left => 2,
+        CartesianDirection::Down => 3,
+    }
+}
+
+fn is_vertical(direction: CartesianDirection) -> bool {
+    matches!(direction, CartesianDirection::Up | CartesianDirection::Down)
+}
+
+fn bit(masks: &[u64], line: u32, idx: u32) -> bool {
+    masks
+        .get(line as usize)
+        .is_some_and(|mask| mask & (1 << idx) != 0)
+}
+
+fn set_bit(masks: &mut [u64], line: u32, idx: u32) {
+    masks[line as usize] |= 1 << idx;
+}
+
+impl BitBoard {
+    /// Largest number of cells in a row or column
+    pub const MAX_SIZE: u32 = u64::BITS;
+
+    /// `None` if `board` is too large
+    pub fn new(board: &Board) -> Option<Self> {
+        let (min, max) = board.bounds();
+        let width = u32::try_from(max.x - min.x + 1).ok()?;
+        let height = u32::try_from(max.y - min.y + 1).ok()?;
+        if width > Self::MAX_SIZE || height > Self::MAX_SIZE {
+            return None;
+        }
+
+        let rows = vec![0; height as usize];
+        let cols = vec![0; width as usize];
+        let lines = [rows.clone(), cols.clone(), rows.clone(), cols];
+        let mut bits = Self {
+            origin: min,
+            width,
+            height,
+            steps: [lines.clone(), lines],
+            warps: std::array::from_fn(|_| rows.clone()),
+            escalator_cells: rows,
+            escalators: Vec::new(),
+        };
+
+        // tile and tile coordinate of each cell, so walls are found without searching
+        // the tiles
+        let mut cells = vec![None; (width * height) as usize];
+        for placed in board.tiles() {
+            for grid_coord in TileGridCoord::all() {
+                let (x, y) = bits
+                    .local(placed.board_coord(grid_coord))
+                    .expect("tile within bounds");
+                cells[(y * width + x) as usize] = Some((placed, grid_coord));
+            }
+        }
+        let cell_at = |x: u32, y: u32| cells[(y * width + x) as usize];
+
+        for y in 0..height {
+            for x in 0..width {
+                let Some((placed, grid_coord)) = cell_at(x, y) else {
+                    continue;
+                };
+                for dir in CartesianDirection::ALL_DIRECTIONS {
+                    // same as `Board::can_step`
+                    let neighbor = bits.board_coord(x, y).neighbor(dir);
+                    let Some((nx, ny)) = bits.local(neighbor) else {
+                        continue;
+                    };
+                    let Some((neighbor_placed, neighbor_coord)) = cell_at(nx, ny) else {
+                        continue;
+                    };
+                    let wall = placed.tile.cell_wall(grid_coord, dir);
+                    let neighbor_wall = neighbor_placed
+                        .tile
+                        .cell_wall(neighbor_coord, dir.opposite());
+                    for (cap, pawn) in [Pawn::Green, Pawn::Orange].into_iter().enumerate() {
+                        if wall.is_passable(pawn) && neighbor_wall.is_passable(pawn) {
+                            let (line, idx) = if is_vertical(dir) { (x, y) } else { (y, x) };
+                            set_bit(&mut bits.steps[cap][dir_idx(dir)], line, idx);
+                        }
+                    }
+                }
+                if let TileCell::Warp(pawn) = placed.tile.cell_value(grid_coord) {
+                    set_bit(&mut bits.warps[pawn.idx()], y, x);
+                }
+            }
+        }
+        for placed in board.tiles() {
+            for esc in placed.tile.escalators() {
+                let ends = esc.0.map(|coord| placed.board_coord(coord));
+                for end in ends {
+                    let (x, y) = bits.local(end).expect("tile within bounds");
+                    set_bit(&mut bits.escalator_cells, y, x);
+                }
+                bits.escalators.push(ends);
+            }
+        }
+        Some(bits)
+    }
+
+    /// Bit column and row of `coord`, if it is within the bounds
+    fn local(&self, coord: BoardCoord) -> Option<(u32, u32)> {
+        let x = u32::try_from(coord.x - self.origin.x).ok()?;
+        let y = u32::try_from(coord.y - self.origin.y).ok()?;
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    fn board_coord(&self, x: u32, y: u32) -> BoardCoord {
+        BoardCoord::new(self.origin.x + x as i32, self.origin.y + y as i32)
+    }
+
+    /// Mask of `coords`; coordinates outside of the board are left out
+    pub fn occupied(&self, coords: &[BoardCoord]) -> Occupied {
+        let mut occupied = Occupied {
+            rows: vec![0; self.height as usize],
+            cols: vec![0; self.width as usize],
+        };
+        for (x, y) in coords.iter().filter_map(|coord| self.local(*coord)) {
+            set_bit(&mut occupied.rows, y, x);
+            set_bit(&mut occupied.cols, x, y);
+        }
+        occupied
+    }
+
+    /// Same as [`Board::slide_destinations`]
+    pub fn slide_destinations(
+        &self,
+        from: BoardCoord,
+        direction: CartesianDirection,
+        pawn: Pawn,
+        occupied: &Occupied,
+    ) -> Vec<BoardCoord> {
+        let Some((x, y)) = self.local(from) else {
+            return Vec::new();
+        };
+        let (line, idx, blocked) = if is_vertical(direction) {
+            (x, y, &occupied.cols)
+        } else {
+            (y, x, &occupied.rows)
+        };
+        let steps = self.steps[capability(pawn)][dir_idx(direction)][line as usize];
+        let blocked = blocked.get(line as usize).copied().unwrap_or(0);
+
+        // runs of step bits starting at `idx`, up to the first occupied cell past it
+        let forward = matches!(
+            direction,
+            CartesianDirection::Right | CartesianDirection::Down
+        );
+        let len = if forward {
+            let free = blocked.checked_shr(idx + 1).unwrap_or(0).trailing_zeros();
+            (steps >> idx).trailing_ones().min(free)
+        } else {
+            let free = blocked
+                .checked_shl(u64::BITS - idx)
+                .unwrap_or(0)
+                .leading_zeros();
+            (steps << (u64::BITS - 1 - idx)).leading_ones().min(free)
+        };
+
+        (1..=len)
+            .map(|step| {
+                let idx = if forward { idx + step } else { idx - step };
+                if is_vertical(direction) {
+                    self.board_coord(x, idx)
+                } else {
+                    self.board_coord(idx, y)
+                }
+            })
+            .collect()
+    }
+
+    /// Same as [`Board::warp_destinations`], but row by row over the whole board
+    pub fn warp_destinations(&self, pawn: Pawn, occupied: &Occupied) -> Vec<BoardCoord> {
+        let mut dests = Vec::new();
+        for (y, row) in self.warps[pawn.idx()].iter().enumerate() {
+            let mut free = row & !occupied.rows.get(y).copied().unwrap_or(0);
+            while free != 0 {
+                dests.push(self.board_coord(free.trailing_zeros(), y as u32));
+                free &= free - 1;
+            }
+        }
+        dests
+    }
+
+    /// Same as [`Board::escalator_destinations`]
+    pub fn escalator_destinations(&self, from: BoardCoord, occupied: &Occupied) -> Vec<BoardCoord> {
+        let Some((x, y)) = self.local(from) else {
+            return Vec::new();
+        };
+        if !bit(&self.escalator_cells, y, x) {
+            return Vec::new();
+        }
+        self.escalators
+            .iter()
+            .filter_map(|[a, b]| {
+                if from == *a {
+                    Some(*b)
+                } else if from == *b {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .filter(|coord| {
+                let (x, y) = self.local(*coord).expect("escalator within bounds");
+                !bit(&occupied.rows, y, x)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Boards after random actions in a few games
+    fn boards() -> Vec<Board> {
+        let tileset = game_tileset().unwrap();
+        let mut boards = Vec::new();
+        for seed in 0..6 {
+            let mut state =
+                GameState::new(1, &Scenario::builtin(2).unwrap(), &tileset, seed).unwrap();
+            let mut rng = GameRng::new(seed);
+            for _ in 0..400 {
+                let actions: Vec<PawnAction> = Pawn::ALL
+                    .iter()
+                    .flat_map(|pawn| state.legal_actions(PlayerId(0), *pawn))
+                    .collect();
+                if actions.is_empty() {
+                    break;
+                }
+                // explore whenever possible to get large boards
+                let action = actions
+                    .iter()
+                    .find(|action| action.action == BoardAction::Explore)
+                    .copied()
+                    .unwrap_or_else(|| actions[rng.usize(..actions.len())]);
+                let _ = state.apply_action(PlayerId(0), &Action::BoardAction(action));
+            }
+            boards.push(state.board().clone());
+        }
+
+        // no game tile has orange-only walls yet
+        let orange_only = tileset_from_str(
+            "@1a-orange
++-+-+6+-+
+|t  $  4|
++-+ +$+-+
+8   $  3|
++-+ + +-+
+|2    $ 5
++-+ + +-+
+|1    | |
++-+7+-+-+
+E: 23-32
+",
+        )
+        .unwrap();
+        let (name, tile) = orange_only[0].clone();
+        boards.push(Board::new(name, tile));
+        boards
+    }
+
+    fn sorted(mut coords: Vec<BoardCoord>) -> Vec<BoardCoord> {
+        coords.sort();
+        coords
+    }
+
+    #[test]
+    fn same_as_board() {
+        crate::init_logging();
+        let boards = boards();
+        assert!(boards.iter().any(|board| board.tiles().len() > 4));
+
+        for board in boards {
+            let bits = BitBoard::new(&board).unwrap();
+            let (min, max) = board.bounds();
+            let coords: Vec<BoardCoord> = (min.y - 1..=max.y + 1)
+                .flat_map(|y| (min.x - 1..=max.x + 1).map(move |x| BoardCoord::new(x, y)))
+                .collect();
+            let pawns = [
+                BoardCoord::new(1, 1),
+                BoardCoord::new(2, 2),
+                BoardCoord::new(min.x, max.y),
+                BoardCoord::new(max.x, min.y),
+            ];
+
+            for occupied in [&[][..], &pawns] {
+                let mask = bits.occupied(occupied);
+                for from in coords.iter().copied() {
+                    for pawn in Pawn::ALL {
+                        for dir in CartesianDirection::ALL_DIRECTIONS {
+                            assert_eq!(
+                                bits.slide_destinations(from, dir, pawn, &mask),
+                                board.slide_destinations(from, dir, pawn, occupied),
+                                "{pawn:?} sliding {dir:?} from {from:?}"
+                            );
+                        }
+                    }
+                    assert_eq!(
+                        bits.escalator_destinations(from, &mask),
+                        board.escalator_destinations(from, occupied),
+                        "escalator from {from:?}"
+                    );
+                }
+                for pawn in Pawn::ALL {
+                    assert_eq!(
+                        sorted(bits.warp_destinations(pawn, &mask)),
+                        sorted(board.warp_destinations(pawn, occupied)),
+                        "{pawn:?} warping"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let (name, tile) = tileset.iter().find(|(name, _)| name == "1a").unwrap();
+        let mut board = Board::new(name.clone(), tile.clone());
+        assert!(BitBoard::new(&board).is_some());
+
+        let width = Tile::CELL_GRID_WIDTH as i32;
+        let far = BitBoard::MAX_SIZE as i32;
+        board.push_tile(PlacedTile {
+            name: name.clone(),
+            tile: tile.clone(),
+            orientation: Orientation::IDENTITY,
+            origin: BoardCoord::new(far - width, 0),
+        });
+        assert!(BitBoard::new(&board).is_some());
+        board.push_tile(PlacedTile {
+            name: name.clone(),
+            tile: tile.clone(),
+            orientation: Orientation::IDENTITY,
+            origin: BoardCoord::new(0, far),
+        });
+        assert!(BitBoard::new(&board).is_none());
+    }
+}
@@ -0,0 +1,459 @@
+use std::ops::{Add, Sub};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+/// Global cell coordinate on the board.
+///
+/// Same orientation as `TileGridCoord`:
+/// - `x`: goes from left to right
+/// - `y`: goes from top to bottom
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct BoardCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl BoardCoord {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Neighbor one step in `direction`
+    pub fn neighbor(self, direction: CartesianDirection) -> Self {
+        let (dx, dy) = direction.neighbor_transform();
+        Self {
+            x: self.x + dx as i32,
+            y: self.y + dy as i32,
+        }
+    }
+}
+
+impl Add for BoardCoord {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for BoardCoord {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl From<TileGridCoord> for BoardCoord {
+    fn from(coord: TileGridCoord) -> Self {
+        Self::new(coord.x() as i32, coord.y() as i32)
+    }
+}
+
+/// `Tile` that has been placed on the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacedTile {
+    pub name: String,
+    /// Already turned by `orientation`
+    pub tile: Tile,
+
+    /// How the tile was turned from its tileset orientation
+    pub orientation: Orientation,
+
+    /// Board coordinate of the top-left cell
+    pub origin: BoardCoord,
+}
+
+impl PlacedTile {
+    /// Coordinate inside the tile, if `coord` is covered by the tile
+    pub fn grid_coord(&self, coord: BoardCoord) -> Option<TileGridCoord> {
+        let local = coord - self.origin;
+        let x = local.x.try_into().ok()?;
+        let y = local.y.try_into().ok()?;
+        TileGridCoord::new(x, y)
+    }
+
+    pub fn board_coord(&self, coord: TileGridCoord) -> BoardCoord {
+        self.origin + coord.into()
+    }
+
+    /// Board coordinates of all cells in the tile
+    pub fn board_coords(&self) -> impl Iterator<Item = BoardCoord> + '_ {
+        TileGridCoord::all().map(|coord| self.board_coord(coord))
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TilePlacementError {
+    #[error("Tile {0:?} has no entrance")]
+    NoEntrance(String),
+
+    #[error("Tile {name:?} would overlap an already placed tile at {coord:?}")]
+    Overlap { name: String, coord: BoardCoord },
+}
+
+/// Tiles that have been placed so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Board {
+    tiles: Vec<PlacedTile>,
+}
+
+impl Board {
+    /// Board containing only `start_tile`, with its top-left cell at the origin
+    pub fn new(start_name: String, start_tile: Tile) -> Self {
+        Self {
+            tiles: vec![PlacedTile {
+                name: start_name,
+                tile: start_tile,
+                orientation: Orientation::IDENTITY,
+                origin: BoardCoord::default(),
+            }],
+        }
+    }
+
+    pub fn tiles(&self) -> &[PlacedTile] {
+        &self.tiles
+    }
+
+    /// Smallest and largest cell coordinates covered by placed tiles
+    pub fn bounds(&self) -> (BoardCoord, BoardCoord) {
+        let last = Tile::CELL_GRID_WIDTH as i32 - 1;
+        let mut min = self.tiles[0].origin;
+        let mut max = min;
+        for placed in self.tiles.iter() {
+            min.x = min.x.min(placed.origin.x);
+            min.y = min.y.min(placed.origin.y);
+            max.x = max.x.max(placed.origin.x + last);
+            max.y = max.y.max(placed.origin.y + last);
+        }
+        (min, max)
+    }
+
+    /// Placed tile covering `coord` along with the coordinate inside of the tile
+    pub fn tile_at(&self, coord: BoardCoord) -> Option<(&PlacedTile, TileGridCoord)> {
+        self.tiles
+            .iter()
+            .find_map(|placed| Some((placed, placed.grid_coord(coord)?)))
+    }
+
+    fn tile_at_mut(&mut self, coord: BoardCoord) -> Option<(&mut PlacedTile, TileGridCoord)> {
+        self.tiles.iter_mut().find_map(|placed| {
+            let grid_coord = placed.grid_coord(coord)?;
+            Some((placed, grid_coord))
+        })
+    }
+
+    pub fn contains(&self, coord: BoardCoord) -> bool {
+        self.tile_at(coord).is_some()
+    }
+
+    pub fn cell(&self, coord: BoardCoord) -> Option<TileCell> {
+        let (placed, grid_coord) = self.tile_at(coord)?;
+        Some(placed.tile.cell_value(grid_coord))
+    }
+
+    /// Mark cell item (timer, camera, crystal ball) as used/available
+    pub fn set_cell_availability(&mut self, coord: BoardCoord, avail: CellItemAvailability) {
+        if let Some((placed, grid_coord)) = self.tile_at_mut(coord) {
+            placed.tile.set_cell_availability(grid_coord, avail);
+        }
+    }
+
+    /// Wall on the `direction` side of `coord`, as seen from the tile containing `coord`
+    pub fn wall(&self, coord: BoardCoord, direction: CartesianDirection) -> Option<WallState> {
+        let (placed, grid_coord) = self.tile_at(coord)?;
+        Some(placed.tile.cell_wall(grid_coord, direction))
+    }
+
+    /// Whether `pawn` can take a single step from `coord` in `direction`.
+    ///
+    /// Walls between two tiles are checked from both sides.
+    pub fn can_step(&self, coord: BoardCoord, direction: CartesianDirection, pawn: Pawn) -> bool {
+        let neighbor = coord.neighbor(direction);
+        let (Some(wall), Some(neighbor_wall)) = (
+            self.wall(coord, direction),
+            self.wall(neighbor, direction.opposite()),
+        ) else {
+            return false;
+        };
+        wall.is_passable(pawn) && neighbor_wall.is_passable(pawn)
+    }
+
+    /// Cells `pawn` could stop on when sliding from `from` in `direction`.
+    ///
+    /// Cells in `occupied` block the slide.
+    pub fn slide_destinations(
+        &self,
+        from: BoardCoord,
+        direction: CartesianDirection,
+        pawn: Pawn,
+        occupied: &[BoardCoord],
+    ) -> Vec<BoardCoord> {
+        let mut dests = Vec::new();
+        let mut coord = from;
+        while self.can_step(coord, direction, pawn) {
+            coord = coord.neighbor(direction);
+            if occupied.contains(&coord) {
+                break;
+            }
+            dests.push(coord);
+        }
+        dests
+    }
+
+    /// Unoccupied warp cells of the `pawn`'s color
+    pub fn warp_destinations(&self, pawn: Pawn, occupied: &[BoardCoord]) -> Vec<BoardCoord> {
+        self.tiles
+            .iter()
+            .flat_map(|placed| {
+                TileGridCoord::all()
+                    .filter(|coord| placed.tile.cell_value(*coord) == TileCell::Warp(pawn))
+                    .map(|coord| placed.board_coord(coord))
+            })
+            .filter(|coord| !occupied.contains(coord))
+            .collect()
+    }
+
+    /// Unoccupied cells at the other end of escalators starting at `from`
+    pub fn escalator_destinations(
+        &self,
+        from: BoardCoord,
+        occupied: &[BoardCoord],
+    ) -> Vec<BoardCoord> {
+        let Some((placed, grid_coord)) = self.tile_at(from) else {
+            return Vec::new();
+        };
+        placed
+            .tile
+            .escalators()
+            .iter()
+            .filter_map(|esc| esc.coord_neighbor(grid_coord))
+            .map(|coord| placed.board_coord(coord))
+            .filter(|coord| !occupied.contains(coord))
+            .collect()
+    }
+
+    /// Unexplored cells just past an explore wall of `pawn`'s color next to `from`
+    pub fn explore_destinations(&self, from: BoardCoord, pawn: Pawn) -> Vec<BoardCoord> {
+        CartesianDirection::ALL_DIRECTIONS
+            .iter()
+            .copied()
+            .filter(|dir| self.wall(from, *dir) == Some(WallState::Explore(pawn)))
+            .map(|dir| from.neighbor(dir))
+            .filter(|coord| !self.contains(*coord))
+            .collect()
+    }
+
+    /// Place `tile` so that its entrance is at `entrance_coord`, coming from `from`.
+    ///
+    /// The tile is rotated so that the entrance faces `from`.
+    pub fn place_tile(
+        &mut self,
+        from: BoardCoord,
+        entrance_coord: BoardCoord,
+        name: String,
+        tile: Tile,
+    ) -> Result<&PlacedTile, TilePlacementError> {
+        let placed = self.tile_placement(from, entrance_coord, name, tile)?;
+        self.tiles.push(placed);
+        Ok(self.tiles.last().unwrap())
+    }
+
+    /// Where `tile` would be placed by `place_tile()`, without placing it
+    pub fn tile_placement(
+        &self,
+        from: BoardCoord,
+        entrance_coord: BoardCoord,
+        name: String,
+        tile: Tile,
+    ) -> Result<PlacedTile, TilePlacementError> {
+        let Some(want_dir) = CartesianDirection::ALL_DIRECTIONS
+            .iter()
+            .copied()
+            .find(|dir| entrance_coord.neighbor(*dir) == from)
+        else {
+            return Err(TilePlacementError::NoEntrance(name));
+        };
+
+        let Some((_coord, dir)) = tile.entrance() else {
+            return Err(TilePlacementError::NoEntrance(name));
+        };
+        let Some(orientation) = Orientation::ALL
+            .iter()
+            .copied()
+            .filter(|orientation| !orientation.is_mirrored())
+            .find(|orientation| dir.as_oriented(*orientation) == want_dir)
+        else {
+            return Err(TilePlacementError::NoEntrance(name));
+        };
+        let tile = tile.as_oriented(orientation);
+        let Some((entrance, _dir)) = tile.entrance() else {
+            return Err(TilePlacementError::NoEntrance(name));
+        };
+
+        let placed = PlacedTile {
+            name,
+            tile,
+            orientation,
+            origin: entrance_coord - entrance.into(),
+        };
+        let overlap = placed.board_coords().find(|coord| self.contains(*coord));
+        if let Some(coord) = overlap {
+            return Err(TilePlacementError::Overlap {
+                name: placed.name,
+                coord,
+            });
+        }
+        Ok(placed)
+    }
+
+    pub(crate) fn push_tile(&mut self, placed: PlacedTile) {
+        self.tiles.push(placed);
+    }
+}
+
+impl WallState {
+    /// Whether `pawn` can walk through this side of a wall
+    pub fn is_passable(self, pawn: Pawn) -> bool {
+        match self {
+            WallState::Open | WallState::Entrance | WallState::Explore(_) => true,
+            WallState::OrangeOnly => pawn == Pawn::Orange,
+            WallState::Blocked => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board() -> Board {
+        let tileset = game_tileset().unwrap();
+        let (name, tile) = tileset.iter().find(|(name, _)| name == "1a").unwrap();
+        Board::new(name.clone(), tile.clone())
+    }
+
+    fn tile(name: &str) -> Tile {
+        let tileset = game_tileset().unwrap();
+        tileset
+            .into_iter()
+            .find(|(tile_name, _)| tile_name == name)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn slide() {
+        crate::init_logging();
+        let board = board();
+
+        assert_eq!(
+            board.slide_destinations(
+                BoardCoord::new(1, 1),
+                CartesianDirection::Right,
+                Pawn::Green,
+                &[]
+            ),
+            [BoardCoord::new(2, 1), BoardCoord::new(3, 1)]
+        );
+        assert_eq!(
+            board.slide_destinations(
+                BoardCoord::new(1, 1),
+                CartesianDirection::Right,
+                Pawn::Green,
+                &[BoardCoord::new(3, 1)]
+            ),
+            [BoardCoord::new(2, 1)]
+        );
+
+        // explore wall with no tile behind it
+        assert_eq!(
+            board.slide_destinations(
+                BoardCoord::new(2, 0),
+                CartesianDirection::Up,
+                Pawn::Orange,
+                &[]
+            ),
+            []
+        );
+    }
+
+    #[test]
+    fn warp_and_escalator() {
+        crate::init_logging();
+        let board = board();
+
+        assert_eq!(
+            board.warp_destinations(Pawn::Purple, &[]),
+            [BoardCoord::new(3, 0)]
+        );
+        assert_eq!(
+            board.warp_destinations(Pawn::Purple, &[BoardCoord::new(3, 0)]),
+            []
+        );
+        assert_eq!(
+            board.escalator_destinations(BoardCoord::new(2, 3), &[]),
+            [BoardCoord::new(3, 2)]
+        );
+        assert_eq!(board.escalator_destinations(BoardCoord::new(1, 1), &[]), []);
+    }
+
+    #[test]
+    fn explore() {
+        crate::init_logging();
+        let mut board = board();
+
+        assert_eq!(
+            board.explore_destinations(BoardCoord::new(2, 0), Pawn::Orange),
+            [BoardCoord::new(2, -1)]
+        );
+        assert_eq!(
+            board.explore_destinations(BoardCoord::new(2, 0), Pawn::Green),
+            []
+        );
+
+        let placed = board
+            .place_tile(
+                BoardCoord::new(2, 0),
+                BoardCoord::new(2, -1),
+                "3".to_string(),
+                tile("3"),
+            )
+            .unwrap();
+        // tile 3 has entrance on top, so it must be turned around
+        assert_eq!(placed.origin, BoardCoord::new(1, -4));
+        assert_eq!(board.tiles().len(), 2);
+        assert_eq!(
+            board.bounds(),
+            (BoardCoord::new(0, -4), BoardCoord::new(4, 3))
+        );
+
+        assert!(board.can_step(BoardCoord::new(2, 0), CartesianDirection::Up, Pawn::Orange));
+        assert!(board.can_step(
+            BoardCoord::new(2, -1),
+            CartesianDirection::Down,
+            Pawn::Green
+        ));
+        assert_eq!(
+            board.explore_destinations(BoardCoord::new(2, 0), Pawn::Orange),
+            []
+        );
+
+        let err = board.place_tile(
+            BoardCoord::new(2, 0),
+            BoardCoord::new(2, -1),
+            "4".to_string(),
+            tile("4"),
+        );
+        assert!(
+            matches!(err, Err(TilePlacementError::Overlap { .. })),
+            "err = {:?}",
+            err
+        );
+    }
+}
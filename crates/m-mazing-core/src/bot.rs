@@ -0,0 +1,550 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+use crate::solver::{goal_distances, SearchBoard};
+
+/// How well a bot plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotDifficulty {
+    /// Time between seeing the board change and acting on it
+    pub reaction: Duration,
+
+    /// Number of team actions the bot looks ahead, at least 1
+    pub depth: u8,
+}
+
+const BUILTIN_DIFFICULTIES: &[(&str, BotDifficulty)] = &[
+    (
+        "easy",
+        BotDifficulty {
+            reaction: Duration::from_millis(2500),
+            depth: 1,
+        },
+    ),
+    (
+        "normal",
+        BotDifficulty {
+            reaction: Duration::from_millis(1200),
+            depth: 2,
+        },
+    ),
+    (
+        "hard",
+        BotDifficulty {
+            reaction: Duration::from_millis(500),
+            depth: 3,
+        },
+    ),
+];
+
+impl BotDifficulty {
+    /// Names accepted by [`BotDifficulty::builtin`]
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_DIFFICULTIES.iter().map(|(name, _)| *name)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_DIFFICULTIES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, difficulty)| *difficulty)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+    "Unknown difficulty {0:?}, expected one of {:?}",
+    BotDifficulty::builtin_names().collect::<Vec<_>>()
+)]
+pub struct UnknownDifficulty(pub String);
+
+impl FromStr for BotDifficulty {
+    type Err = UnknownDifficulty;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::builtin(s).ok_or_else(|| UnknownDifficulty(s.to_string()))
+    }
+}
+
+impl Default for BotDifficulty {
+    fn default() -> Self {
+        Self::builtin("normal").expect("builtin difficulty")
+    }
+}
+
+/// Computer player in one seat, acting in real time.
+///
+/// Bots see what a human sees: the explored board, the pawns and the number of tiles
+/// left, but not the order of the pile.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    player: PlayerId,
+    difficulty: BotDifficulty,
+
+    /// Game as the bot last saw it
+    seen: Option<GameState>,
+
+    /// Time since `seen` changed or the bot last acted
+    idle: Duration,
+
+    /// `idle` time of the next attempt to act; `None` if there is nothing to do until the
+    /// game changes
+    next_try: Option<Duration>,
+
+    /// Actions that did not change `seen`, e.g. explores where the tile did not fit
+    tried: Vec<PawnAction>,
+
+    /// The team's best plans need nobody to act, e.g. because pawns block each other
+    stuck: bool,
+
+    /// Times the bot saw each position
+    visits: HashMap<Visit, u32>,
+}
+
+impl Bot {
+    /// Reaction times to wait for someone else to act before trying anything
+    const PATIENCE: u32 = 3;
+
+    pub fn new(player: PlayerId, difficulty: BotDifficulty) -> Self {
+        Self {
+            player,
+            difficulty,
+            seen: None,
+            idle: Duration::ZERO,
+            next_try: None,
+            tried: Vec::new(),
+            stuck: false,
+            visits: HashMap::new(),
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    pub fn difficulty(&self) -> BotDifficulty {
+        self.difficulty
+    }
+
+    /// Let `elapsed` time pass; returns the action to submit once the bot has reacted to
+    /// `state`
+    pub fn tick(&mut self, state: &GameState, elapsed: Duration) -> Option<Action> {
+        let reaction = self.difficulty.reaction;
+        if self.seen.as_ref() != Some(state) {
+            self.seen = Some(state.clone());
+            let visit = Visit::new(Position::new(state), state);
+            *self.visits.entry(visit).or_default() += 1;
+            self.idle = Duration::ZERO;
+            self.next_try = Some(reaction);
+            self.tried.clear();
+            self.stuck = false;
+        }
+        self.idle += elapsed;
+        if self.next_try.is_none_or(|next_try| self.idle < next_try) {
+            return None;
+        }
+
+        let action = self.choose_action(state, self.stuck);
+        self.next_try = match action {
+            // tries something else if the action is not accepted
+            Some(action) => {
+                self.tried.push(action);
+                Some(self.idle + reaction)
+            }
+            None if self.stuck => None,
+            None => {
+                self.stuck = true;
+                Some(self.idle + reaction * Self::PATIENCE)
+            }
+        };
+        action.map(Action::BoardAction)
+    }
+
+    /// Best action of the bot's role right now, or `None` if it should leave the next
+    /// move to others
+    pub fn choose(&self, state: &GameState) -> Option<PawnAction> {
+        self.choose_action(state, false)
+    }
+
+    /// Like [`Self::choose`]; when `stuck`, makes the least bad move instead of waiting
+    fn choose_action(&self, state: &GameState, stuck: bool) -> Option<PawnAction> {
+        if state.phase().is_over() {
+            return None;
+        }
+        let allowed = state.player_actions(self.player)?;
+        let mut plan = Plan::new(state, &self.visits);
+        let now = plan.start();
+        let depth = self.difficulty.depth.max(1) - 1;
+
+        let mut best: Option<(Outlook, PawnAction)> = None;
+        for (pawn_action, next) in plan.successors(now, allowed) {
+            if self.tried.contains(&pawn_action) {
+                continue;
+            }
+            let outlook = match next {
+                Some(next) => plan.outlook(next, depth),
+                None => plan.explore_outlook(now),
+            }
+            .after_action();
+            if best.is_none_or(|(best, _)| outlook < best) {
+                best = Some((outlook, pawn_action));
+            }
+        }
+        let (outlook, pawn_action) = best?;
+        if stuck {
+            return Some(pawn_action);
+        }
+
+        // only act when starting one of the team's best plans; the current visit does not
+        // count, so that the bot does not move just to be elsewhere
+        let current = Outlook {
+            score: plan.score(now).saturating_sub(Plan::REVISIT),
+            actions: 0,
+        };
+        let team = plan.outlook(now, depth + 1).min(current);
+        (outlook.score < current.score && outlook <= team).then_some(pawn_action)
+    }
+}
+
+/// Best score the team can reach within the lookahead, and the actions it takes to get
+/// there; earlier fields take precedence when comparing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Outlook {
+    score: u32,
+    actions: u8,
+}
+
+impl Outlook {
+    fn after_action(self) -> Self {
+        Self {
+            actions: self.actions + 1,
+            ..self
+        }
+    }
+}
+
+/// Pawns and phase during lookahead; the board does not change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position {
+    pawns: [Option<BoardCoord>; Pawn::ALL.len()],
+    phase: GamePhase,
+}
+
+impl Position {
+    fn new(state: &GameState) -> Self {
+        Self {
+            pawns: state.pawns(),
+            phase: state.phase(),
+        }
+    }
+}
+
+/// Position on a board with a given number of explored tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Visit {
+    position: Position,
+    num_tiles: usize,
+}
+
+impl Visit {
+    fn new(position: Position, state: &GameState) -> Self {
+        Self {
+            position,
+            num_tiles: state.board().tiles().len(),
+        }
+    }
+}
+
+/// Moves of one pawn toward its goal on the explored board
+struct PawnDistances {
+    goal: HashMap<BoardCoord, u32>,
+
+    /// Moves to a cell the pawn can explore from
+    frontier: HashMap<BoardCoord, u32>,
+}
+
+/// Team plan heuristic shared by all bots, so they agree on who should move.
+///
+/// Scores how far the team is from winning, assuming each pawn takes its shortest way,
+/// and that a pawn without a way to its goal waits for the closest pawn to explore a
+/// tile that costs a fixed number of moves. Lower is better.
+struct Plan<'a> {
+    state: &'a GameState,
+
+    /// Copy of `state` whose pawns are moved around during lookahead
+    scratch: SearchBoard,
+    distances: HashMap<(Pawn, GamePhase), PawnDistances>,
+
+    /// Times each position was seen; going back to a position makes it look worse, so
+    /// the team gets out of dead ends instead of going back and forth
+    visits: &'a HashMap<Visit, u32>,
+}
+
+impl<'a> Plan<'a> {
+    /// Guessed moves to a goal on a tile that is yet to be explored
+    const UNEXPLORED: u32 = 8;
+
+    /// Score of a pawn that has no known way to its goal
+    const STUCK: u32 = 100;
+
+    /// Added while the loot is not stolen, so stealing it always looks like progress
+    const BEFORE_THEFT: u32 = 1000;
+
+    /// Added for each time the position was seen before
+    const REVISIT: u32 = 2;
+
+    fn new(state: &'a GameState, visits: &'a HashMap<Visit, u32>) -> Self {
+        Self {
+            state,
+            scratch: SearchBoard::new(state.clone()),
+            distances: HashMap::new(),
+            visits,
+        }
+    }
+
+    fn start(&self) -> Position {
+        Position::new(self.state)
+    }
+
+    fn is_goal(&self, pawn: Pawn, phase: GamePhase, cell: TileCell) -> bool {
+        match phase {
+            GamePhase::Explore => cell == TileCell::Loot(pawn),
+            GamePhase::Escape => self.state.escape().is_exit(pawn, cell),
+            GamePhase::Won | GamePhase::Lost => false,
+        }
+    }
+
+    fn distances(&mut self, pawn: Pawn, phase: GamePhase) -> &PawnDistances {
+        if !self.distances.contains_key(&(pawn, phase)) {
+            let board = self.state.board();
+            let bits = self.scratch.bits.as_ref();
+            let goal = goal_distances(self.state, bits, pawn, phase, |coord| {
+                board
+                    .cell(coord)
+                    .is_some_and(|cell| self.is_goal(pawn, phase, cell))
+            });
+            let can_explore = self.state.pile_len() > 0;
+            let frontier = goal_distances(self.state, bits, pawn, phase, |coord| {
+                can_explore && !board.explore_destinations(coord, pawn).is_empty()
+            });
+            self.distances
+                .insert((pawn, phase), PawnDistances { goal, frontier });
+        }
+        &self.distances[&(pawn, phase)]
+    }
+
+    /// Whether exploring could still reveal a loot or exit the team needs
+    fn goals_unexplored(&mut self, position: Position) -> bool {
+        let phases: &[GamePhase] = match position.phase {
+            GamePhase::Explore => &[GamePhase::Explore, GamePhase::Escape],
+            GamePhase::Escape => &[GamePhase::Escape],
+            GamePhase::Won | GamePhase::Lost => &[],
+        };
+        Pawn::ALL.iter().any(|pawn| {
+            position.pawns[pawn.idx()].is_some()
+                && phases
+                    .iter()
+                    .any(|phase| self.distances(*pawn, *phase).goal.is_empty())
+        })
+    }
+
+    fn score(&mut self, position: Position) -> u32 {
+        let visits = self.visits.get(&Visit::new(position, self.state));
+        let revisits = visits.map_or(0, |visits| visits * Self::REVISIT);
+        self.base_score(position).saturating_add(revisits)
+    }
+
+    fn base_score(&mut self, position: Position) -> u32 {
+        let mut score = match position.phase {
+            GamePhase::Explore => Self::BEFORE_THEFT,
+            GamePhase::Escape => 0,
+            GamePhase::Won => return 0,
+            GamePhase::Lost => return u32::MAX,
+        };
+        // any pawn can explore the tile another pawn needs
+        let mut frontier: Option<u32> = None;
+        for pawn in Pawn::ALL {
+            if let Some(coord) = position.pawns[pawn.idx()] {
+                if let Some(moves) = self.distances(pawn, position.phase).frontier.get(&coord) {
+                    frontier = Some(frontier.map_or(*moves, |frontier| frontier.min(*moves)));
+                }
+            }
+        }
+
+        for pawn in Pawn::ALL {
+            let Some(coord) = position.pawns[pawn.idx()] else {
+                continue;
+            };
+            let goal = self.distances(pawn, position.phase).goal.get(&coord);
+            score += match (goal, frontier) {
+                (Some(moves), _) => *moves,
+                // the explorer likely has to come back, and exploring takes an action
+                (None, Some(moves)) => 2 * moves + 1 + Self::UNEXPLORED,
+                (None, None) => Self::STUCK,
+            };
+        }
+        score
+    }
+
+    /// Outlook of exploring at `position`, whose result is unknown, before counting the
+    /// explore itself; the position after it is always new
+    fn explore_outlook(&mut self, position: Position) -> Outlook {
+        let score = self.base_score(position);
+        let score = if self.goals_unexplored(position) {
+            score.saturating_sub(1)
+        } else {
+            score
+        };
+        Outlook { score, actions: 0 }
+    }
+
+    /// Ways to perform `actions` at `position` with the position they lead to, or `None`
+    /// for explores
+    fn successors(
+        &mut self,
+        position: Position,
+        actions: &[BoardAction],
+    ) -> Vec<(PawnAction, Option<Position>)> {
+        let mut successors = Vec::new();
+        for pawn_action in self
+            .scratch
+            .pawn_actions(position.pawns, position.phase, actions)
+        {
+            if pawn_action.action == BoardAction::Explore {
+                successors.push((pawn_action, None));
+                continue;
+            }
+            let Ok(events) = self.scratch.state.legal_pawn_action_events(&pawn_action) else {
+                continue;
+            };
+            let mut next = position;
+            for event in events {
+                match event {
+                    GameEvent::PawnMoved { pawn, to, .. } => next.pawns[pawn.idx()] = Some(to),
+                    GameEvent::PawnEscaped(pawn) => next.pawns[pawn.idx()] = None,
+                    GameEvent::LootStolen => next.phase = GamePhase::Escape,
+                    GameEvent::GameWon => next.phase = GamePhase::Won,
+                    _ => (),
+                }
+            }
+            successors.push((pawn_action, Some(next)));
+        }
+        successors
+    }
+
+    /// Best the team can reach from `position` within `depth` actions
+    fn outlook(&mut self, position: Position, depth: u8) -> Outlook {
+        let mut best = Outlook {
+            score: self.score(position),
+            actions: 0,
+        };
+        if depth == 0 || position.phase.is_over() {
+            return best;
+        }
+        for (_, next) in self.successors(position, &BoardAction::ALL) {
+            let outlook = match next {
+                Some(next) => self.outlook(next, depth - 1),
+                None => self.explore_outlook(position),
+            };
+            best = best.min(outlook.after_action());
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    /// Let the bots play; returns the number of actions they performed
+    fn play(state: &mut GameState, bots: &mut [Bot], max_actions: usize) -> usize {
+        let mut performed = 0;
+        let mut idle = Duration::ZERO;
+        while performed < max_actions && !state.phase().is_over() {
+            let mut acted = false;
+            for bot in bots.iter_mut() {
+                if let Some(action) = bot.tick(state, STEP) {
+                    if state.apply_action(bot.player(), &action).is_ok() {
+                        performed += 1;
+                        acted = true;
+                    }
+                }
+            }
+            idle = if acted { Duration::ZERO } else { idle + STEP };
+            if idle > Duration::from_secs(10) {
+                break;
+            }
+        }
+        performed
+    }
+
+    #[test]
+    fn difficulties() {
+        crate::init_logging();
+        for name in BotDifficulty::builtin_names() {
+            let difficulty = BotDifficulty::builtin(name).unwrap();
+            assert!(difficulty.depth >= 1);
+        }
+        assert!(BotDifficulty::builtin("nope").is_none());
+        assert_eq!("hard".parse(), Ok(BotDifficulty::builtin("hard").unwrap()));
+        assert_eq!(
+            "nope".parse::<BotDifficulty>(),
+            Err(UnknownDifficulty("nope".to_string()))
+        );
+        let easy = BotDifficulty::builtin("easy").unwrap();
+        let hard = BotDifficulty::builtin("hard").unwrap();
+        assert!(easy.reaction > hard.reaction && easy.depth < hard.depth);
+    }
+
+    #[test]
+    fn reaction() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let state = GameState::new(1, &Scenario::first(), &tileset, 0).unwrap();
+        let difficulty = BotDifficulty {
+            reaction: Duration::from_secs(1),
+            depth: 1,
+        };
+        let mut bot = Bot::new(PlayerId(0), difficulty);
+        assert_eq!(bot.tick(&state, Duration::ZERO), None);
+        assert_eq!(bot.tick(&state, Duration::from_millis(900)), None);
+        let action = bot.tick(&state, Duration::from_millis(100)).unwrap();
+        assert!(state.action_events(PlayerId(0), &action).is_ok());
+    }
+
+    #[test]
+    fn team_escapes() {
+        crate::init_logging();
+        for num_players in [1, 4] {
+            let mut state = crate::scenario::escape_game(num_players);
+            let mut bots: Vec<Bot> = (0..num_players)
+                .map(|player| Bot::new(PlayerId(player), BotDifficulty::default()))
+                .collect();
+            play(&mut state, &mut bots, 200);
+            assert_eq!(state.phase(), GamePhase::Won, "{num_players} players");
+        }
+    }
+
+    #[test]
+    fn roles() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let mut state = GameState::new(4, &Scenario::first(), &tileset, 0).unwrap();
+        let mut bots: Vec<Bot> = (0..4)
+            .map(|player| Bot::new(PlayerId(player), BotDifficulty::default()))
+            .collect();
+        for bot in bots.iter() {
+            if let Some(pawn_action) = bot.choose(&state) {
+                let allowed = state.player_actions(bot.player()).unwrap();
+                assert!(allowed.contains(&pawn_action.action));
+            }
+        }
+        // the team gets somewhere without a human
+        assert!(play(&mut state, &mut bots, 20) > 0);
+        assert!(state.board().tiles().len() > 1);
+    }
+}
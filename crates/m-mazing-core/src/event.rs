@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Result of an accepted `Action`.
+///
+/// Applying the same events in the same order to identical `GameState`s gives identical states.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    PawnMoved {
+        pawn: Pawn,
+        from: BoardCoord,
+        to: BoardCoord,
+    },
+
+    /// Tile was drawn from the pile and placed on the board
+    TileExplored(PlacedTile),
+
+    /// Every pawn stood on its loot at the same time
+    LootStolen,
+
+    PawnEscaped(Pawn),
+
+    GameWon,
+
+    /// Pawn stopped on an unused timer flip cell, which is now used
+    TimerFlipped(BoardCoord),
+
+    /// Sand timer ran out; decided by whoever keeps the time
+    TimeUp,
+
+    /// "Do Something!" pawn was put in front of `target`, who stays alerted until they act
+    DoSomething {
+        by: PlayerId,
+        target: PlayerId,
+    },
+
+    /// Alerted player performed an action
+    DoSomethingAnswered(PlayerId),
+}
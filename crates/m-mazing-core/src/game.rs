@@ -0,0 +1,676 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamePhase {
+    /// Pawns explore the mall and try to reach their loot
+    Explore,
+
+    /// Loot has been stolen; warps no longer work
+    Escape,
+
+    /// All pawns escaped
+    Won,
+
+    /// Sand timer ran out
+    Lost,
+}
+
+impl GamePhase {
+    pub fn is_over(self) -> bool {
+        matches!(self, Self::Won | Self::Lost)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GameSetupError {
+    #[error("Unsupported number of players {0}")]
+    InvalidNumPlayers(u8),
+
+    #[error("Tile {0:?} not found in tileset")]
+    UnknownTile(String),
+}
+
+/// Complete game state; serializable so it can be sent to players who reconnect
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameState {
+    pub num_players: u8,
+    escape: ScenarioEscape,
+    board: Board,
+    pawns: [Option<BoardCoord>; Pawn::ALL.len()],
+
+    /// Next tile to explore is at the end
+    pile: Vec<(String, Tile)>,
+    phase: GamePhase,
+
+    /// Seed the game was set up with
+    seed: u64,
+    rng: GameRng,
+
+    /// Index into [`game_roles`] dealt to each player
+    roles: Vec<usize>,
+
+    /// Player the "Do Something!" pawn stands in front of
+    do_something: Option<PlayerId>,
+}
+
+impl GameState {
+    /// Cells on the start tile where pawns start, in `Pawn::ALL` order
+    const START_COORDS: [TileGridCoord; Pawn::ALL.len()] = [
+        TileGridCoord { x: 1, y: 1 },
+        TileGridCoord { x: 2, y: 1 },
+        TileGridCoord { x: 1, y: 2 },
+        TileGridCoord { x: 2, y: 2 },
+    ];
+
+    /// New game with the pile shuffled and roles dealt from `seed`
+    pub fn new(
+        num_players: u8,
+        scenario: &Scenario,
+        tileset: &[(String, Tile)],
+        seed: u64,
+    ) -> Result<Self, GameSetupError> {
+        let Some(roles) = crate::role::game_roles(num_players) else {
+            return Err(GameSetupError::InvalidNumPlayers(num_players));
+        };
+        let find_tile = |name: &String| {
+            tileset
+                .iter()
+                .find(|(tile_name, _)| tile_name == name)
+                .cloned()
+                .ok_or_else(|| GameSetupError::UnknownTile(name.clone()))
+        };
+
+        let (start_name, start_tile) = find_tile(&scenario.start_tile_name)?;
+        let mut pile = scenario
+            .explore_tile_names
+            .iter()
+            .map(find_tile)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rng = GameRng::new(seed);
+        rng.shuffle(&mut pile);
+        let mut roles: Vec<usize> = (0..roles.len()).collect();
+        rng.shuffle(&mut roles);
+
+        let board = Board::new(start_name, start_tile);
+        let start = &board.tiles()[0];
+        let pawns = Self::START_COORDS.map(|coord| Some(start.board_coord(coord)));
+
+        Ok(GameState {
+            num_players,
+            escape: scenario.escape,
+            board,
+            pawns,
+            pile,
+            phase: GamePhase::Explore,
+            seed,
+            rng,
+            roles,
+            do_something: None,
+        })
+    }
+
+    pub fn num_players(&self) -> u8 {
+        self.num_players
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        self.phase
+    }
+
+    /// Which exits pawns must escape through
+    pub fn escape(&self) -> ScenarioEscape {
+        self.escape
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Player alerted by the "Do Something!" pawn, until they act
+    pub fn do_something_target(&self) -> Option<PlayerId> {
+        self.do_something
+    }
+
+    /// Number of tiles left to explore
+    pub fn pile_len(&self) -> usize {
+        self.pile.len()
+    }
+
+    /// Position of `pawn`, or `None` if it already escaped
+    pub fn pawn_coord(&self, pawn: Pawn) -> Option<BoardCoord> {
+        self.pawns[pawn.idx()]
+    }
+
+    pub fn pawn_at(&self, coord: BoardCoord) -> Option<Pawn> {
+        Pawn::ALL
+            .iter()
+            .copied()
+            .find(|pawn| self.pawn_coord(*pawn) == Some(coord))
+    }
+
+    fn occupied_coords(&self) -> Vec<BoardCoord> {
+        self.pawns.iter().flatten().copied().collect()
+    }
+
+    /// Tiles left to explore; the next one is at the end
+    pub(crate) fn pile(&self) -> &[(String, Tile)] {
+        &self.pile
+    }
+
+    pub(crate) fn pawns(&self) -> [Option<BoardCoord>; Pawn::ALL.len()] {
+        self.pawns
+    }
+
+    /// Move pawns without going through actions, e.g. to explore alternatives
+    pub(crate) fn set_pawns(
+        &mut self,
+        pawns: [Option<BoardCoord>; Pawn::ALL.len()],
+        phase: GamePhase,
+    ) {
+        self.pawns = pawns;
+        self.phase = phase;
+    }
+
+    /// Actions the role of `player` is allowed to perform
+    pub fn player_actions(&self, player: PlayerId) -> Option<&'static [BoardAction]> {
+        let role = *self.roles.get(player.0 as usize)?;
+        crate::role::game_roles(self.num_players)?
+            .get(role)
+            .copied()
+    }
+
+    /// Every way `pawn` can currently perform `action`, regardless of role
+    pub fn pawn_actions(&self, pawn: Pawn, action: BoardAction) -> Vec<PawnAction> {
+        self.pawn_actions_with(None, pawn, action)
+    }
+
+    /// Same as [`Self::pawn_actions`], with moves looked up in `bits`, a `BitBoard` of
+    /// the board and the mask of the cells the pawns are on, if given
+    pub(crate) fn pawn_actions_with(
+        &self,
+        bits: Option<(&BitBoard, &Occupied)>,
+        pawn: Pawn,
+        action: BoardAction,
+    ) -> Vec<PawnAction> {
+        let Some(from) = self.pawn_coord(pawn) else {
+            return Vec::new();
+        };
+        if self.phase.is_over() {
+            return Vec::new();
+        }
+
+        let occupied = match bits {
+            Some(_) => Vec::new(),
+            None => self.occupied_coords(),
+        };
+        let dests = match (action, bits) {
+            (BoardAction::Slide(dir), Some((bits, mask))) => {
+                bits.slide_destinations(from, dir, pawn, mask)
+            }
+            (BoardAction::Slide(dir), None) => {
+                self.board.slide_destinations(from, dir, pawn, &occupied)
+            }
+            (BoardAction::Warp, _) if self.phase != GamePhase::Explore => Vec::new(),
+            (BoardAction::Warp, Some((bits, mask))) => bits.warp_destinations(pawn, mask),
+            (BoardAction::Warp, None) => self.board.warp_destinations(pawn, &occupied),
+            (BoardAction::Escalator, Some((bits, mask))) => bits.escalator_destinations(from, mask),
+            (BoardAction::Escalator, None) => self.board.escalator_destinations(from, &occupied),
+            (BoardAction::Explore, _) if self.pile.is_empty() => Vec::new(),
+            (BoardAction::Explore, _) => self.board.explore_destinations(from, pawn),
+        };
+        dests
+            .into_iter()
+            .map(|dest| PawnAction { pawn, action, dest })
+            .collect()
+    }
+
+    /// Every action `player` can currently perform with `pawn`
+    pub fn legal_actions(&self, player: PlayerId, pawn: Pawn) -> Vec<PawnAction> {
+        self.player_actions(player)
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|action| self.pawn_actions(pawn, *action))
+            .collect()
+    }
+
+    /// Validate and apply `action` performed by `player`
+    pub fn apply_action(
+        &mut self,
+        player: PlayerId,
+        action: &Action,
+    ) -> Result<Vec<GameEvent>, ActionError> {
+        let events = self.action_events(player, action)?;
+        for event in events.iter() {
+            self.apply_event(event);
+        }
+        Ok(events)
+    }
+
+    /// Events that would result from `player` performing `action`
+    pub fn action_events(
+        &self,
+        player: PlayerId,
+        action: &Action,
+    ) -> Result<Vec<GameEvent>, ActionError> {
+        if self.phase.is_over() {
+            return Err(ActionError::GameOver);
+        }
+
+        let allowed = self
+            .player_actions(player)
+            .ok_or(ActionError::UnknownPlayer(player))?;
+        match action {
+            Action::BoardAction(pawn_action) => {
+                if !allowed.contains(&pawn_action.action) {
+                    return Err(ActionError::NotAllowed {
+                        player,
+                        action: pawn_action.action,
+                    });
+                }
+                let mut events = self.pawn_action_events(pawn_action)?;
+                if self.do_something == Some(player) {
+                    events.push(GameEvent::DoSomethingAnswered(player));
+                }
+                Ok(events)
+            }
+            Action::DoSomething(target) => {
+                if self.player_actions(*target).is_none() {
+                    return Err(ActionError::UnknownPlayer(*target));
+                }
+                if *target == player || self.do_something == Some(*target) {
+                    return Err(ActionError::DoSomethingTarget(*target));
+                }
+                Ok(vec![GameEvent::DoSomething {
+                    by: player,
+                    target: *target,
+                }])
+            }
+        }
+    }
+
+    pub(crate) fn pawn_action_events(
+        &self,
+        pawn_action: &PawnAction,
+    ) -> Result<Vec<GameEvent>, ActionError> {
+        let PawnAction { pawn, action, .. } = *pawn_action;
+        self.pawn_coord(pawn)
+            .ok_or(ActionError::PawnNotOnBoard(pawn))?;
+        if !self.pawn_actions(pawn, action).contains(pawn_action) {
+            return Err(ActionError::Illegal(*pawn_action));
+        }
+        self.legal_pawn_action_events(pawn_action)
+    }
+
+    /// Same as [`Self::pawn_action_events`] for an action that is known to be one of
+    /// [`Self::pawn_actions`], which is not checked again
+    pub(crate) fn legal_pawn_action_events(
+        &self,
+        pawn_action: &PawnAction,
+    ) -> Result<Vec<GameEvent>, ActionError> {
+        let PawnAction { pawn, action, dest } = *pawn_action;
+        let from = self
+            .pawn_coord(pawn)
+            .ok_or(ActionError::PawnNotOnBoard(pawn))?;
+
+        if action == BoardAction::Explore {
+            let (name, tile) = self.pile.last().ok_or(ActionError::PileEmpty)?;
+            let placed = self
+                .board
+                .tile_placement(from, dest, name.clone(), tile.clone())?;
+            return Ok(vec![GameEvent::TileExplored(placed)]);
+        }
+
+        let mut events = vec![GameEvent::PawnMoved {
+            pawn,
+            from,
+            to: dest,
+        }];
+        events.extend(self.arrival_events(pawn, dest));
+        Ok(events)
+    }
+
+    /// Events triggered by `pawn` arriving at `to`
+    fn arrival_events(&self, pawn: Pawn, to: BoardCoord) -> Vec<GameEvent> {
+        let mut pawns = self.pawns;
+        pawns[pawn.idx()] = Some(to);
+
+        let mut events = Vec::new();
+        if !self.phase.is_over()
+            && self.board.cell(to) == Some(TileCell::TimerFlip(CellItemAvailability::Available))
+        {
+            events.push(GameEvent::TimerFlipped(to));
+        }
+
+        match self.phase {
+            GamePhase::Explore => {
+                let all_on_loot = Pawn::ALL.iter().all(|pawn| {
+                    pawns[pawn.idx()].and_then(|coord| self.board.cell(coord))
+                        == Some(TileCell::Loot(*pawn))
+                });
+                if all_on_loot {
+                    events.push(GameEvent::LootStolen);
+                }
+            }
+            GamePhase::Escape => {
+                let is_exit = self
+                    .board
+                    .cell(to)
+                    .is_some_and(|cell| self.escape.is_exit(pawn, cell));
+                if is_exit {
+                    events.push(GameEvent::PawnEscaped(pawn));
+                    pawns[pawn.idx()] = None;
+                    if pawns.iter().all(Option::is_none) {
+                        events.push(GameEvent::GameWon);
+                    }
+                }
+            }
+            GamePhase::Won | GamePhase::Lost => (),
+        }
+        events
+    }
+
+    /// Apply an already validated event
+    pub fn apply_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PawnMoved { pawn, to, .. } => {
+                self.pawns[pawn.idx()] = Some(*to);
+            }
+            GameEvent::TileExplored(placed) => {
+                if let Some(idx) = self
+                    .pile
+                    .iter()
+                    .rposition(|(name, _tile)| *name == placed.name)
+                {
+                    self.pile.remove(idx);
+                }
+                self.board.push_tile(placed.clone());
+            }
+            GameEvent::LootStolen => {
+                self.phase = GamePhase::Escape;
+            }
+            GameEvent::PawnEscaped(pawn) => {
+                self.pawns[pawn.idx()] = None;
+            }
+            GameEvent::GameWon => {
+                self.phase = GamePhase::Won;
+            }
+            GameEvent::TimerFlipped(coord) => {
+                self.board
+                    .set_cell_availability(*coord, CellItemAvailability::Used);
+            }
+            GameEvent::TimeUp => {
+                self.phase = GamePhase::Lost;
+            }
+            GameEvent::DoSomething { target, .. } => {
+                self.do_something = Some(*target);
+            }
+            GameEvent::DoSomethingAnswered(_) => {
+                self.do_something = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PLAYER: PlayerId = PlayerId(0);
+
+    fn game(num_players: u8) -> GameState {
+        let tileset = game_tileset().unwrap();
+        GameState::new(num_players, &Scenario::first(), &tileset, 0).unwrap()
+    }
+
+    fn slide(pawn: Pawn, dir: CartesianDirection, dest: BoardCoord) -> Action {
+        Action::BoardAction(PawnAction {
+            pawn,
+            action: BoardAction::Slide(dir),
+            dest,
+        })
+    }
+
+    #[test]
+    fn new() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+
+        assert!(matches!(
+            GameState::new(0, &Scenario::first(), &tileset, 0),
+            Err(GameSetupError::InvalidNumPlayers(0))
+        ));
+
+        let mut scenario = Scenario::first();
+        scenario.explore_tile_names.push("nope".to_string());
+        assert!(matches!(
+            GameState::new(1, &scenario, &tileset, 0),
+            Err(GameSetupError::UnknownTile(_))
+        ));
+
+        let game = game(1);
+        assert_eq!(game.pile_len(), 8);
+        assert_eq!(game.pawn_at(BoardCoord::new(1, 1)), Some(Pawn::Green));
+        assert_eq!(game.pawn_at(BoardCoord::new(0, 0)), None);
+    }
+
+    #[test]
+    fn move_pawn() {
+        crate::init_logging();
+        let mut game = game(1);
+
+        // yellow pawn at (1, 2) blocks green
+        assert_eq!(
+            game.pawn_actions(Pawn::Green, BoardAction::Slide(CartesianDirection::Down)),
+            []
+        );
+
+        let events = game
+            .apply_action(
+                PLAYER,
+                &slide(Pawn::Green, CartesianDirection::Left, BoardCoord::new(0, 1)),
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [GameEvent::PawnMoved {
+                pawn: Pawn::Green,
+                from: BoardCoord::new(1, 1),
+                to: BoardCoord::new(0, 1),
+            }]
+        );
+        assert_eq!(game.pawn_coord(Pawn::Green), Some(BoardCoord::new(0, 1)));
+
+        let err = game.apply_action(
+            PLAYER,
+            &slide(
+                Pawn::Green,
+                CartesianDirection::Left,
+                BoardCoord::new(-1, 1),
+            ),
+        );
+        assert!(matches!(err, Err(ActionError::Illegal(_))), "{:?}", err);
+    }
+
+    #[test]
+    fn roles() {
+        crate::init_logging();
+        let mut game = game(2);
+        let warp = BoardAction::Warp;
+        let warper = [PlayerId(0), PlayerId(1)]
+            .into_iter()
+            .find(|player| game.player_actions(*player).unwrap().contains(&warp))
+            .unwrap();
+        let other = PlayerId(1 - warper.0);
+
+        let right = slide(
+            Pawn::Green,
+            CartesianDirection::Right,
+            BoardCoord::new(2, 1),
+        );
+        assert_eq!(
+            game.apply_action(other, &right),
+            Err(ActionError::NotAllowed {
+                player: other,
+                action: BoardAction::Slide(CartesianDirection::Right)
+            })
+        );
+        assert_eq!(
+            game.apply_action(PlayerId(2), &right),
+            Err(ActionError::UnknownPlayer(PlayerId(2)))
+        );
+
+        assert!(game
+            .legal_actions(other, Pawn::Purple)
+            .iter()
+            .all(|action| action.action != warp));
+        assert!(game
+            .legal_actions(warper, Pawn::Purple)
+            .iter()
+            .any(|action| action.action == warp));
+    }
+
+    #[test]
+    fn seeded() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let game = |seed| GameState::new(4, &Scenario::first(), &tileset, seed).unwrap();
+        assert_eq!(game(3), game(3));
+        assert_eq!(game(3).seed(), 3);
+
+        // some seed deals other roles or another pile order
+        let first = game(0);
+        assert!((1..10).map(game).any(|other| other.pile != first.pile));
+        assert!((1..10).map(game).any(|other| other.roles != first.roles));
+
+        let mut dealt = first.roles.clone();
+        dealt.sort();
+        assert_eq!(dealt, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn explore() {
+        crate::init_logging();
+        let mut game = game(1);
+
+        let to_explore = slide(Pawn::Orange, CartesianDirection::Up, BoardCoord::new(2, 0));
+        game.apply_action(PLAYER, &to_explore).unwrap();
+
+        let next_name = game.pile.last().unwrap().0.clone();
+        let explore = game.pawn_actions(Pawn::Orange, BoardAction::Explore);
+        assert_eq!(
+            explore,
+            [PawnAction {
+                pawn: Pawn::Orange,
+                action: BoardAction::Explore,
+                dest: BoardCoord::new(2, -1),
+            }]
+        );
+
+        let events = game
+            .apply_action(PLAYER, &Action::BoardAction(explore[0]))
+            .unwrap();
+        assert!(
+            matches!(events.as_slice(), [GameEvent::TileExplored(placed)] if placed.name == next_name),
+            "{:?}",
+            events
+        );
+        assert_eq!(game.pile_len(), 7);
+        assert_eq!(game.board().tiles().len(), 2);
+        assert!(game.board().contains(BoardCoord::new(2, -1)));
+    }
+
+    #[test]
+    fn timer() {
+        crate::init_logging();
+        let mut game = game(1);
+
+        // start tile has a timer flip cell
+        let timer_coord = game
+            .board()
+            .tiles()
+            .iter()
+            .flat_map(|placed| placed.board_coords())
+            .find(|coord| {
+                game.board().cell(*coord)
+                    == Some(TileCell::TimerFlip(CellItemAvailability::Available))
+            })
+            .expect("timer flip cell");
+
+        assert_eq!(
+            game.arrival_events(Pawn::Green, timer_coord),
+            [GameEvent::TimerFlipped(timer_coord)]
+        );
+        game.apply_event(&GameEvent::TimerFlipped(timer_coord));
+        assert_eq!(game.arrival_events(Pawn::Green, timer_coord), []);
+
+        game.apply_event(&GameEvent::TimeUp);
+        assert_eq!(game.phase(), GamePhase::Lost);
+        assert_eq!(
+            game.apply_action(
+                PLAYER,
+                &slide(
+                    Pawn::Orange,
+                    CartesianDirection::Down,
+                    BoardCoord::new(2, 2)
+                ),
+            ),
+            Err(ActionError::GameOver)
+        );
+    }
+
+    #[test]
+    fn do_something() {
+        crate::init_logging();
+        let mut game = game(3);
+        let ask = |target| Action::DoSomething(PlayerId(target));
+
+        assert_eq!(
+            game.apply_action(PlayerId(0), &ask(0)),
+            Err(ActionError::DoSomethingTarget(PlayerId(0)))
+        );
+        assert_eq!(
+            game.apply_action(PlayerId(0), &ask(3)),
+            Err(ActionError::UnknownPlayer(PlayerId(3)))
+        );
+        assert_eq!(
+            game.apply_action(PlayerId(0), &ask(1)),
+            Ok(vec![GameEvent::DoSomething {
+                by: PlayerId(0),
+                target: PlayerId(1)
+            }])
+        );
+        assert_eq!(game.do_something_target(), Some(PlayerId(1)));
+        assert_eq!(
+            game.apply_action(PlayerId(2), &ask(1)),
+            Err(ActionError::DoSomethingTarget(PlayerId(1)))
+        );
+
+        // others acting does not clear the alert
+        let act = |game: &GameState, player| {
+            Pawn::ALL
+                .iter()
+                .flat_map(|pawn| game.legal_actions(player, *pawn))
+                .next()
+                .map(Action::BoardAction)
+                .unwrap()
+        };
+        let action = act(&game, PlayerId(0));
+        game.apply_action(PlayerId(0), &action).unwrap();
+        assert_eq!(game.do_something_target(), Some(PlayerId(1)));
+
+        let action = act(&game, PlayerId(1));
+        let events = game.apply_action(PlayerId(1), &action).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::DoSomethingAnswered(PlayerId(1)))
+        );
+        assert_eq!(game.do_something_target(), None);
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::solver::goal_distances;
+
+/// What a hinted action brings a pawn closer to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HintGoal {
+    /// The pawn's loot, while it is not stolen
+    Loot,
+
+    /// An exit the pawn may escape through
+    Exit,
+
+    /// A tile that may have the loot or exit the pawn has no way to yet
+    Explore,
+}
+
+/// Action that moves the team closer to its current goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hint {
+    pub action: PawnAction,
+    pub goal: HintGoal,
+}
+
+impl GameState {
+    /// Actions the role of `player` could take that bring a pawn closer to its goal.
+    ///
+    /// A pawn with a way to its loot, or to an exit once the loot is stolen, is hinted
+    /// along the shortest ways there; a pawn without one is hinted toward the closest
+    /// cell it can explore from. Other pawns may be in the way, so a hint is a step in
+    /// the right direction, not a full plan.
+    pub fn hints(&self, player: PlayerId) -> Vec<Hint> {
+        let (goal, phase) = match self.phase() {
+            GamePhase::Explore => (HintGoal::Loot, GamePhase::Explore),
+            GamePhase::Escape => (HintGoal::Exit, GamePhase::Escape),
+            GamePhase::Won | GamePhase::Lost => return Vec::new(),
+        };
+        let board = self.board();
+        let bits = BitBoard::new(board);
+        let mut hints = Vec::new();
+        for pawn in Pawn::ALL {
+            let Some(from) = self.pawn_coord(pawn) else {
+                continue;
+            };
+            let actions = self.legal_actions(player, pawn);
+            if actions.is_empty() {
+                continue;
+            }
+
+            let to_goal = goal_distances(self, bits.as_ref(), pawn, phase, |coord| {
+                board.cell(coord).is_some_and(|cell| match goal {
+                    HintGoal::Loot => cell == TileCell::Loot(pawn),
+                    HintGoal::Exit | HintGoal::Explore => self.escape().is_exit(pawn, cell),
+                })
+            });
+            let (distances, goal) = if to_goal.contains_key(&from) {
+                (to_goal, goal)
+            } else {
+                let can_explore = self.pile_len() > 0;
+                let to_frontier = goal_distances(self, bits.as_ref(), pawn, phase, |coord| {
+                    can_explore && !board.explore_destinations(coord, pawn).is_empty()
+                });
+                (to_frontier, HintGoal::Explore)
+            };
+            let Some(current) = distances.get(&from).copied() else {
+                continue;
+            };
+
+            for action in actions {
+                let closer = match action.action {
+                    BoardAction::Explore => goal == HintGoal::Explore,
+                    _ => distances
+                        .get(&action.dest)
+                        .is_some_and(|distance| *distance < current),
+                };
+                if closer {
+                    hints.push(Hint { action, goal });
+                }
+            }
+        }
+        hints
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hints() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let mut state = GameState::new(1, &Scenario::first(), &tileset, 0).unwrap();
+        let player = PlayerId(0);
+
+        // no loot on the start tile, so every pawn heads for a tile to explore
+        let hints = state.hints(player);
+        assert!(!hints.is_empty());
+        assert!(hints.iter().all(|hint| hint.goal == HintGoal::Explore));
+
+        // hints are legal actions of the player's role
+        for hint in hints.iter() {
+            assert!(state
+                .legal_actions(player, hint.action.pawn)
+                .contains(&hint.action));
+        }
+
+        // with the other seats taken, a player only gets hints for their role
+        let two_players = GameState::new(2, &Scenario::first(), &tileset, 0).unwrap();
+        for player in [PlayerId(0), PlayerId(1)] {
+            let allowed = two_players.player_actions(player).unwrap();
+            for hint in two_players.hints(player) {
+                assert!(allowed.contains(&hint.action.action));
+            }
+        }
+
+        // following them explores a tile
+        while state.board().tiles().len() == 1 {
+            let hint = state.hints(player)[0];
+            state
+                .apply_action(player, &Action::BoardAction(hint.action))
+                .unwrap();
+        }
+        state.apply_event(&GameEvent::TimeUp);
+        assert!(state.hints(player).is_empty());
+    }
+
+    #[test]
+    fn escape_hints() {
+        crate::init_logging();
+        let mut state = crate::scenario::escape_game(1);
+        let player = PlayerId(0);
+
+        // following the hints of the first pawn that has any wins the game
+        for _ in 0..100 {
+            if state.phase() == GamePhase::Won {
+                return;
+            }
+            let hint = state.hints(player)[0];
+            if state.pile_len() == 0 {
+                assert_eq!(hint.goal, HintGoal::Exit);
+            }
+            state
+                .apply_action(player, &Action::BoardAction(hint.action))
+                .unwrap();
+        }
+        panic!("hints did not lead to a win");
+    }
+}
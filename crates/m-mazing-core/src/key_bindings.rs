@@ -0,0 +1,503 @@
+use std::{
+    fmt::Debug, fmt::Write, hash::Hash, marker::PhantomData, path::Path, path::PathBuf,
+    str::FromStr,
+};
+
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+/// Something a key can be bound to
+pub trait InputAction: Copy + Eq + Hash + Debug + Send + Sync + 'static {
+    /// Every action, in the order they are listed in config files
+    const ALL: &'static [Self];
+
+    /// Name used in config files
+    fn name(self) -> &'static str;
+
+    /// Short human readable description
+    fn description(self) -> &'static str;
+
+    fn default_keys(self) -> &'static [KeyCode];
+}
+
+/// In-game actions.
+///
+/// The default bindings only use the left hand so the right hand can stay on the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameInput {
+    SelectPawn(Pawn),
+    NextPawn,
+    Slide(CartesianDirection),
+    Warp,
+    Escalator,
+    Explore,
+    DoSomething,
+    NextTheme,
+}
+
+impl InputAction for GameInput {
+    const ALL: &'static [Self] = &[
+        Self::SelectPawn(Pawn::Green),
+        Self::SelectPawn(Pawn::Orange),
+        Self::SelectPawn(Pawn::Yellow),
+        Self::SelectPawn(Pawn::Purple),
+        Self::NextPawn,
+        Self::Slide(CartesianDirection::Up),
+        Self::Slide(CartesianDirection::Left),
+        Self::Slide(CartesianDirection::Down),
+        Self::Slide(CartesianDirection::Right),
+        Self::Warp,
+        Self::Escalator,
+        Self::Explore,
+        Self::DoSomething,
+        Self::NextTheme,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::SelectPawn(Pawn::Green) => "select-green",
+            Self::SelectPawn(Pawn::Orange) => "select-orange",
+            Self::SelectPawn(Pawn::Yellow) => "select-yellow",
+            Self::SelectPawn(Pawn::Purple) => "select-purple",
+            Self::NextPawn => "next-pawn",
+            Self::Slide(CartesianDirection::Up) => "slide-up",
+            Self::Slide(CartesianDirection::Left) => "slide-left",
+            Self::Slide(CartesianDirection::Down) => "slide-down",
+            Self::Slide(CartesianDirection::Right) => "slide-right",
+            Self::Warp => "warp",
+            Self::Escalator => "escalator",
+            Self::Explore => "explore",
+            Self::DoSomething => "do-something",
+            Self::NextTheme => "next-theme",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::SelectPawn(Pawn::Green) => "select green pawn",
+            Self::SelectPawn(Pawn::Orange) => "select orange pawn",
+            Self::SelectPawn(Pawn::Yellow) => "select yellow pawn",
+            Self::SelectPawn(Pawn::Purple) => "select purple pawn",
+            Self::NextPawn => "select next pawn",
+            Self::Slide(CartesianDirection::Up) => "slide up",
+            Self::Slide(CartesianDirection::Left) => "slide left",
+            Self::Slide(CartesianDirection::Down) => "slide down",
+            Self::Slide(CartesianDirection::Right) => "slide right",
+            Self::Warp => "warp",
+            Self::Escalator => "take escalator",
+            Self::Explore => "explore",
+            Self::DoSomething => "do something!",
+            Self::NextTheme => "switch to next theme",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Self::SelectPawn(Pawn::Green) => &[KeyCode::Digit1],
+            Self::SelectPawn(Pawn::Orange) => &[KeyCode::Digit2],
+            Self::SelectPawn(Pawn::Yellow) => &[KeyCode::Digit3],
+            Self::SelectPawn(Pawn::Purple) => &[KeyCode::Digit4],
+            Self::NextPawn => &[KeyCode::Tab],
+            Self::Slide(CartesianDirection::Up) => &[KeyCode::KeyW],
+            Self::Slide(CartesianDirection::Left) => &[KeyCode::KeyA],
+            Self::Slide(CartesianDirection::Down) => &[KeyCode::KeyS],
+            Self::Slide(CartesianDirection::Right) => &[KeyCode::KeyD],
+            Self::Warp => &[KeyCode::KeyQ],
+            Self::Escalator => &[KeyCode::KeyE],
+            Self::Explore => &[KeyCode::KeyR],
+            Self::DoSomething => &[KeyCode::KeyF],
+            Self::NextTheme => &[KeyCode::KeyT],
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KeyBindingsError {
+    #[error("Expected 'action = key, ...' on line {line_number}: {line:?}")]
+    InvalidLine { line_number: u32, line: String },
+
+    #[error("Unknown action {name:?} on line {line_number}")]
+    UnknownAction { line_number: u32, name: String },
+
+    #[error("Unknown key {name:?} on line {line_number}")]
+    UnknownKey { line_number: u32, name: String },
+
+    #[error("Failed to read key bindings from {path:?}: {msg}")]
+    Io { path: PathBuf, msg: String },
+}
+
+/// Key bound to more than one action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyConflict<A> {
+    pub key: KeyCode,
+    pub actions: Vec<A>,
+}
+
+/// Keys bound to each `InputAction`.
+///
+/// Config files have one `action = key, ...` line per action; actions that are not listed
+/// keep their default keys. An action with no keys after the `=` is unbound.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct KeyBindings<A: InputAction> {
+    /// Keys for each action, in `InputAction::ALL` order
+    keys: Vec<Vec<KeyCode>>,
+    _action: PhantomData<A>,
+}
+
+impl<A: InputAction> Default for KeyBindings<A> {
+    fn default() -> Self {
+        Self {
+            keys: A::ALL
+                .iter()
+                .map(|action| action.default_keys().to_vec())
+                .collect(),
+            _action: PhantomData,
+        }
+    }
+}
+
+impl<A: InputAction> KeyBindings<A> {
+    /// Load bindings from config file at `path`, or use the defaults if there is none
+    pub fn load(path: Option<&Path>) -> Result<Self, KeyBindingsError> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        info!("Loading key bindings from {:?}", path);
+        std::fs::read_to_string(path)
+            .map_err(|err| KeyBindingsError::Io {
+                path: path.to_owned(),
+                msg: err.to_string(),
+            })?
+            .parse()
+    }
+
+    fn action_idx(action: A) -> usize {
+        A::ALL
+            .iter()
+            .position(|a| *a == action)
+            .expect("action missing from InputAction::ALL")
+    }
+
+    pub fn keys(&self, action: A) -> &[KeyCode] {
+        &self.keys[Self::action_idx(action)]
+    }
+
+    pub fn set_keys(&mut self, action: A, keys: Vec<KeyCode>) {
+        self.keys[Self::action_idx(action)] = keys;
+    }
+
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: A) -> bool {
+        input.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, action: A) -> bool {
+        input.any_pressed(self.keys(action).iter().copied())
+    }
+
+    /// Actions with a key that was just pressed
+    pub fn just_pressed_actions<'a>(
+        &'a self,
+        input: &'a ButtonInput<KeyCode>,
+    ) -> impl Iterator<Item = A> + 'a {
+        A::ALL
+            .iter()
+            .copied()
+            .filter(|action| self.just_pressed(input, *action))
+    }
+
+    /// Keys that are bound to more than one action
+    pub fn conflicts(&self) -> Vec<KeyConflict<A>> {
+        let mut conflicts: Vec<KeyConflict<A>> = Vec::new();
+        for (action, keys) in A::ALL.iter().zip(self.keys.iter()) {
+            for key in keys {
+                let others: Vec<A> = A::ALL
+                    .iter()
+                    .zip(self.keys.iter())
+                    .filter(|(_action, other_keys)| other_keys.contains(key))
+                    .map(|(action, _keys)| *action)
+                    .collect();
+                if others.len() > 1 && !conflicts.iter().any(|c| c.key == *key) {
+                    debug!(
+                        "key {:?} bound to {:?} conflicts with {:?}",
+                        key, action, others
+                    );
+                    conflicts.push(KeyConflict {
+                        key: *key,
+                        actions: others,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Human readable list of keys for `action`
+    pub fn describe(&self, action: A) -> String {
+        let keys: Vec<&str> = self.keys(action).iter().map(|key| key_name(*key)).collect();
+        if keys.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            keys.join("/")
+        }
+    }
+
+    /// Bindings in config file format
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::new();
+        for (action, keys) in A::ALL.iter().zip(self.keys.iter()) {
+            let keys: Vec<&str> = keys.iter().map(|key| key_name(*key)).collect();
+            writeln!(out, "# {}", action.description()).unwrap();
+            writeln!(out, "{} = {}", action.name(), keys.join(", ")).unwrap();
+        }
+        out
+    }
+}
+
+impl<A: InputAction> FromStr for KeyBindings<A> {
+    type Err = KeyBindingsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bindings = Self::default();
+
+        for (idx, line) in s.lines().enumerate() {
+            let line_number = idx as u32 + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, keys)) = line.split_once('=') else {
+                return Err(KeyBindingsError::InvalidLine {
+                    line_number,
+                    line: line.to_string(),
+                });
+            };
+            let name = name.trim();
+            let action = A::ALL
+                .iter()
+                .copied()
+                .find(|action| action.name() == name)
+                .ok_or_else(|| KeyBindingsError::UnknownAction {
+                    line_number,
+                    name: name.to_string(),
+                })?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| {
+                    key_from_name(key).ok_or_else(|| KeyBindingsError::UnknownKey {
+                        line_number,
+                        name: key.to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            trace!("bind {:?} to {:?}", action, keys);
+            bindings.set_keys(action, keys);
+        }
+
+        Ok(bindings)
+    }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        /// Keys that may appear in config files, named after their `KeyCode` variant
+        const KEY_NAMES: &[(KeyCode, &str)] = &[$((KeyCode::$key, stringify!($key))),*];
+    };
+}
+
+key_names!(
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadEnter,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Backspace,
+    Enter,
+    Escape,
+    Space,
+    Tab,
+    Backquote,
+    Minus,
+    Equal,
+    BracketLeft,
+    BracketRight,
+    Backslash,
+    Semicolon,
+    Quote,
+    Comma,
+    Period,
+    Slash,
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+);
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_key, key_name)| *key_name == name)
+        .map(|(key, _key_name)| *key)
+}
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(k, _key_name)| *k == key)
+        .map(|(_key, key_name)| *key_name)
+        .unwrap_or("?")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_no_conflicts() {
+        crate::init_logging();
+        let bindings = KeyBindings::<GameInput>::default();
+        assert_eq!(bindings.conflicts(), []);
+        assert_eq!(
+            bindings.keys(GameInput::Slide(CartesianDirection::Up)),
+            [KeyCode::KeyW]
+        );
+    }
+
+    #[test]
+    fn parse() {
+        crate::init_logging();
+        let config = "
+# comment
+slide-up = ArrowUp, KeyW
+  warp =
+";
+        let bindings: KeyBindings<GameInput> = config.parse().unwrap();
+        assert_eq!(
+            bindings.keys(GameInput::Slide(CartesianDirection::Up)),
+            [KeyCode::ArrowUp, KeyCode::KeyW]
+        );
+        assert_eq!(bindings.keys(GameInput::Warp), []);
+        assert_eq!(bindings.keys(GameInput::Explore), [KeyCode::KeyR]);
+        assert_eq!(bindings.describe(GameInput::Warp), "(unbound)");
+
+        let round_trip: KeyBindings<GameInput> = bindings.to_config_string().parse().unwrap();
+        assert_eq!(round_trip, bindings);
+    }
+
+    #[test]
+    fn parse_negative() {
+        crate::init_logging();
+        assert_eq!(
+            "slide-up KeyW".parse::<KeyBindings<GameInput>>(),
+            Err(KeyBindingsError::InvalidLine {
+                line_number: 1,
+                line: "slide-up KeyW".to_string()
+            })
+        );
+        assert_eq!(
+            "\nfly = KeyW".parse::<KeyBindings<GameInput>>(),
+            Err(KeyBindingsError::UnknownAction {
+                line_number: 2,
+                name: "fly".to_string()
+            })
+        );
+        assert_eq!(
+            "warp = KeyW, Hyper".parse::<KeyBindings<GameInput>>(),
+            Err(KeyBindingsError::UnknownKey {
+                line_number: 1,
+                name: "Hyper".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        crate::init_logging();
+        let bindings: KeyBindings<GameInput> = "warp = KeyW, KeyR".parse().unwrap();
+        assert_eq!(
+            bindings.conflicts(),
+            [
+                KeyConflict {
+                    key: KeyCode::KeyW,
+                    actions: vec![GameInput::Slide(CartesianDirection::Up), GameInput::Warp],
+                },
+                KeyConflict {
+                    key: KeyCode::KeyR,
+                    actions: vec![GameInput::Warp, GameInput::Explore],
+                },
+            ]
+        );
+    }
+}
@@ -1,16 +1,43 @@
 pub mod action;
+pub mod bitboard;
+pub mod board;
+pub mod bot;
+pub mod event;
+pub mod game;
+pub mod hint;
+pub mod key_bindings;
+pub mod mall;
+pub mod replay;
+pub mod rng;
 pub mod role;
 pub mod scenario;
+pub mod simulation;
+pub mod solver;
 pub mod tile;
+pub mod timer;
 
 pub mod prelude {
     pub use crate::action::*;
+    pub use crate::bitboard::*;
+    pub use crate::board::*;
+    pub use crate::bot::*;
+    pub use crate::event::*;
+    pub use crate::game::*;
+    pub use crate::hint::*;
+    pub use crate::key_bindings::*;
+    pub use crate::mall::*;
     pub use crate::render::{shape::*, theme::*};
+    pub use crate::replay::*;
+    pub use crate::rng::*;
     pub use crate::role::*;
     pub use crate::scenario::*;
+    pub use crate::simulation::*;
+    pub use crate::solver::*;
     pub use crate::tile::{
-        cell::*, direction::*, escalator::*, grid_coord::*, tileset::*, wall::*, *,
+        cell::*, cell_set::*, direction::*, edit::*, escalator::*, fingerprint::*, generator::*,
+        grid_coord::*, orientation::*, tileset::*, wall::*, *,
     };
+    pub use crate::timer::*;
     pub use crate::*;
 }
 
@@ -19,10 +46,13 @@ use bevy::log::Level;
 pub use bevy_prototype_lyon;
 pub mod render;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-//pub struct PlayerId(u32);
+/// Seat index of a player; selects the player's role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub u8);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Pawn {
     Green,
     Orange,
@@ -30,23 +60,11 @@ pub enum Pawn {
     Purple,
 }
 
-#[derive(Clone, Debug)]
-pub struct GameState {
-    pub num_players: u8,
-    _roles: &'static [&'static [action::BoardAction]],
-}
-
-impl GameState {
-    pub fn new(num_players: u8) -> Option<Self> {
-        let _roles = crate::role::game_roles(num_players)?;
-        Some(GameState {
-            num_players,
-            _roles,
-        })
-    }
+impl Pawn {
+    pub const ALL: [Pawn; 4] = [Self::Green, Self::Orange, Self::Yellow, Self::Purple];
 
-    pub fn num_players(&self) -> u8 {
-        self.num_players
+    pub(crate) fn idx(self) -> usize {
+        self as usize
     }
 }
 
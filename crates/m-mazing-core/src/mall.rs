@@ -0,0 +1,373 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MallError {
+    #[error("No start tile in the tileset")]
+    NoStartTile,
+
+    #[error("No tile in the tileset has {0:?}")]
+    MissingTile(TileCell),
+
+    #[error(transparent)]
+    Tile(#[from] TileGenError),
+
+    #[error(transparent)]
+    Setup(#[from] GameSetupError),
+
+    #[error("No winnable mall found in {0} attempts")]
+    NotWinnable(usize),
+}
+
+/// Makes random scenarios, called malls, that are known to be winnable.
+///
+/// Each mall has the loot and exits its escape needs, plus some extra tiles drawn from
+/// the tileset or generated. A mall is only kept if every one of a few shuffles of its
+/// pile is won without the sand timer: by the staged [`Solver`], or by bots if the solver
+/// gives up. A won game is a proof, so the checked shuffles are winnable even if the
+/// solution takes detours, while a mall that is not won may still have been winnable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MallGenerator {
+    pub escape: ScenarioEscape,
+    pub num_players: u8,
+
+    /// Tiles in the pile besides the ones with the loot and exits
+    pub extra_tiles: usize,
+
+    /// Generate the extra tiles with these constraints instead of drawing them from the
+    /// tileset
+    pub generated: Option<TileConstraints>,
+
+    /// Shuffles of the pile that must be won
+    pub shuffles: usize,
+
+    /// Piles to try before giving up
+    pub max_attempts: usize,
+
+    /// Bots that play the shuffles the solver gives up on
+    pub bots: BotDifficulty,
+}
+
+impl Default for MallGenerator {
+    fn default() -> Self {
+        Self {
+            escape: ScenarioEscape::PurpleOnly,
+            num_players: 4,
+            extra_tiles: 3,
+            generated: None,
+            shuffles: 3,
+            max_attempts: 10,
+            bots: BotDifficulty::default(),
+        }
+    }
+}
+
+/// Winnable scenario made by [`MallGenerator::generate`]
+#[derive(Debug, Clone)]
+pub struct Mall {
+    pub scenario: Scenario,
+
+    /// Tileset the mall was made from, followed by the generated tiles
+    pub tileset: Vec<(String, Tile)>,
+
+    /// Seeds of the checked shuffles, to start games with
+    pub seeds: Vec<u64>,
+    pub rating: MallRating,
+}
+
+/// How hard a mall is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MallRating {
+    /// Average actions a win took, as solved or as played by the bots
+    pub actions: f32,
+
+    /// Timer flip cells on the start tile and in the pile
+    pub timer_cells: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MallLevel {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl MallRating {
+    /// Actions the team makes per turn of the sand timer, if every timer cell is used
+    pub fn actions_per_timer(&self) -> f32 {
+        self.actions / (1 + self.timer_cells) as f32
+    }
+
+    /// Level from the actions per timer turn; rule book scenarios with a few extra tiles
+    /// come out between 30 and 90
+    pub fn level(&self) -> MallLevel {
+        match self.actions_per_timer() {
+            per_timer if per_timer < 50.0 => MallLevel::Easy,
+            per_timer if per_timer < 80.0 => MallLevel::Normal,
+            _ => MallLevel::Hard,
+        }
+    }
+}
+
+impl MallGenerator {
+    /// Limits of the bots on a shuffle, far beyond what they take to win rule book
+    /// scenarios
+    const MAX_ACTIONS: usize = 800;
+    const MAX_TIME: Duration = Duration::from_secs(10 * 60);
+
+    /// Cells a pile needs for `escape`
+    fn required_cells(escape: ScenarioEscape) -> Vec<TileCell> {
+        let exits = match escape {
+            ScenarioEscape::PurpleOnly => vec![TileCell::FinalExit(Pawn::Purple)],
+            ScenarioEscape::EachColor => Pawn::ALL.map(TileCell::FinalExit).to_vec(),
+        };
+        Pawn::ALL
+            .map(TileCell::Loot)
+            .into_iter()
+            .chain(exits)
+            .collect()
+    }
+
+    /// Winnable mall made from `tileset`, the same for the same `seed`
+    pub fn generate(&self, tileset: &[(String, Tile)], seed: u64) -> Result<Mall, MallError> {
+        let mut rng = GameRng::new(seed);
+        for _ in 0..self.max_attempts {
+            let (scenario, generated) = self.pile(tileset, &mut rng)?;
+            let mut mall_tileset = tileset.to_vec();
+            mall_tileset.extend(generated);
+
+            let first_seed = rng.u64();
+            let seeds: Vec<u64> = (0..self.shuffles as u64)
+                .map(|idx| first_seed.wrapping_add(idx))
+                .collect();
+            let Some(actions) = self.check(&scenario, &mall_tileset, &seeds)? else {
+                continue;
+            };
+
+            let timer_cells = std::iter::once(&scenario.start_tile_name)
+                .chain(scenario.explore_tile_names.iter())
+                .filter_map(|name| mall_tileset.iter().find(|(n, _)| n == name))
+                .flat_map(|(_, tile)| tile.cells_iter())
+                .filter(|cell| matches!(cell, TileCell::TimerFlip(_)))
+                .count();
+            return Ok(Mall {
+                scenario,
+                tileset: mall_tileset,
+                seeds,
+                rating: MallRating {
+                    actions,
+                    timer_cells,
+                },
+            });
+        }
+        Err(MallError::NotWinnable(self.max_attempts))
+    }
+
+    /// Random scenario, and the tiles generated for it
+    fn pile(
+        &self,
+        tileset: &[(String, Tile)],
+        rng: &mut GameRng,
+    ) -> Result<(Scenario, Vec<(String, Tile)>), MallError> {
+        // start tiles are the ones without an entrance
+        let (starts, mut candidates): (Vec<_>, Vec<_>) = tileset
+            .iter()
+            .partition(|(_, tile)| tile.entrance().is_none());
+        if starts.is_empty() {
+            return Err(MallError::NoStartTile);
+        }
+        let (start_name, _) = starts[rng.usize(..starts.len())];
+        rng.shuffle(&mut candidates);
+
+        let mut pile: Vec<String> = Vec::new();
+        for cell in Self::required_cells(self.escape) {
+            let has_cell = |tile: &Tile| tile.cells_iter().any(|c| *c == cell);
+            let in_pile = pile
+                .iter()
+                .any(|name| tileset.iter().any(|(n, tile)| n == name && has_cell(tile)));
+            if in_pile {
+                continue;
+            }
+            let (name, _) = candidates
+                .iter()
+                .find(|(_, tile)| has_cell(tile))
+                .ok_or(MallError::MissingTile(cell))?;
+            pile.push(name.clone());
+        }
+
+        let mut generated = Vec::new();
+        match &self.generated {
+            Some(constraints) => {
+                for idx in 0..self.extra_tiles {
+                    let name = format!("mall-{}", idx + 1);
+                    generated.push((name.clone(), constraints.generate(rng)?));
+                    pile.push(name);
+                }
+            }
+            None => {
+                let extra: Vec<String> = candidates
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .filter(|name| !pile.contains(name))
+                    .take(self.extra_tiles)
+                    .collect();
+                pile.extend(extra);
+            }
+        }
+
+        let scenario = Scenario {
+            escape: self.escape,
+            start_tile_name: start_name.clone(),
+            explore_tile_names: pile,
+        };
+        Ok((scenario, generated))
+    }
+
+    /// Average actions a win of the game of each of `seeds` took, or `None` if one was
+    /// not won
+    fn check(
+        &self,
+        scenario: &Scenario,
+        tileset: &[(String, Tile)],
+        seeds: &[u64],
+    ) -> Result<Option<f32>, MallError> {
+        let solver = Solver {
+            staged: true,
+            ..Default::default()
+        };
+        let bots = Simulation {
+            difficulty: self.bots,
+            timer_duration: None,
+            max_actions: Some(Self::MAX_ACTIONS),
+            max_time: Some(Self::MAX_TIME),
+            ..Default::default()
+        };
+        let mut actions = 0;
+        for seed in seeds.iter().copied() {
+            let state = GameState::new(self.num_players, scenario, tileset, seed)?;
+            let won_in = match solver.solve(&state) {
+                Ok(solution) => solution.len(),
+                Err(SolveError::LimitReached(_)) => {
+                    let result = bots.play(state);
+                    if !result.is_won() {
+                        debug!(
+                            "mall {scenario:?} not won with seed {}",
+                            GameRng::format_seed(seed)
+                        );
+                        return Ok(None);
+                    }
+                    result.actions
+                }
+                Err(err) => {
+                    debug!(
+                        "mall {scenario:?} with seed {}: {err}",
+                        GameRng::format_seed(seed)
+                    );
+                    return Ok(None);
+                }
+            };
+            actions += won_in;
+        }
+        Ok(Some(actions as f32 / seeds.len().max(1) as f32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generate() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let generator = MallGenerator {
+            extra_tiles: 0,
+            shuffles: 1,
+            ..Default::default()
+        };
+        let mall = generator.generate(&tileset, 11).unwrap();
+        assert_eq!(mall.tileset.len(), tileset.len());
+        assert_eq!(mall.seeds.len(), 1);
+        assert!(mall.rating.actions > 0.0);
+
+        // has every loot and the exit, and nothing twice
+        let pile: Vec<&Tile> = mall
+            .scenario
+            .explore_tile_names
+            .iter()
+            .map(|name| &tileset.iter().find(|(n, _)| n == name).unwrap().1)
+            .collect();
+        for cell in MallGenerator::required_cells(ScenarioEscape::PurpleOnly) {
+            assert!(pile
+                .iter()
+                .any(|tile| tile.cells_iter().any(|c| *c == cell)));
+        }
+        let mut names = mall.scenario.explore_tile_names.clone();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), mall.scenario.explore_tile_names.len());
+
+        let state = GameState::new(4, &mall.scenario, &mall.tileset, mall.seeds[0]).unwrap();
+        let solver = Solver {
+            staged: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            solver.solve(&state).unwrap().len() as f32,
+            mall.rating.actions
+        );
+
+        let pile = |seed| {
+            let (scenario, _) = generator.pile(&tileset, &mut GameRng::new(seed)).unwrap();
+            scenario.explore_tile_names
+        };
+        assert_eq!(pile(3), pile(3));
+    }
+
+    #[test]
+    fn generated_tiles() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let generator = MallGenerator {
+            extra_tiles: 1,
+            generated: Some(TileConstraints::default()),
+            shuffles: 1,
+            ..Default::default()
+        };
+        let mall = generator.generate(&tileset, 4).unwrap();
+        assert_eq!(mall.tileset.len(), tileset.len() + 1);
+        assert!(mall
+            .scenario
+            .explore_tile_names
+            .contains(&"mall-1".to_string()));
+
+        let no_green_loot: Vec<_> = tileset
+            .iter()
+            .filter(|(_, tile)| !tile.cells_iter().any(|c| *c == TileCell::Loot(Pawn::Green)))
+            .cloned()
+            .collect();
+        assert_eq!(
+            generator.generate(&no_green_loot, 4).unwrap_err(),
+            MallError::MissingTile(TileCell::Loot(Pawn::Green))
+        );
+    }
+
+    #[test]
+    fn rating() {
+        crate::init_logging();
+        let rating = MallRating {
+            actions: 120.0,
+            timer_cells: 2,
+        };
+        assert_eq!(rating.actions_per_timer(), 40.0);
+        assert_eq!(rating.level(), MallLevel::Easy);
+        let no_timer = MallRating {
+            timer_cells: 0,
+            ..rating
+        };
+        assert_eq!(no_timer.level(), MallLevel::Hard);
+    }
+}
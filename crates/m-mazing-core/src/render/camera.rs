@@ -1,46 +1,179 @@
-/*
 use crate::prelude::*;
 
-/// Zoom camera to fit rectangle while maintaining aspect ratio
+/// Camera translation and projection scale that show a rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraFit {
+    pub center: Vec2,
+
+    /// World units per logical pixel
+    pub scale: f32,
+}
+
+/// Zoom camera to fit rectangle while maintaining aspect ratio.
+///
+/// `viewport_size` is in logical pixels and the projection is expected to use
+/// `ScalingMode::WindowSize(1.0)`.
 #[must_use]
-pub fn camera_zoom_to_fit(fit: Rect) -> mq::Camera2D {
-    let desired_aspect_ratio = fit.w / fit.h;
-    let screen_width = screen_width();
-    let screen_height = screen_height();
-    let current_aspect_ratio = screen_width / screen_height;
-    debug!("Fitting camera to {:?}", fit);
-
-    let w;
-    let h;
-    let x;
-    let y;
-
-    if current_aspect_ratio >= desired_aspect_ratio {
+pub fn camera_zoom_to_fit(fit: Rect, viewport_size: Vec2) -> CameraFit {
+    let desired_aspect_ratio = fit.width() / fit.height();
+    let current_aspect_ratio = viewport_size.x / viewport_size.y;
+    trace!("Fitting camera to {:?}", fit);
+
+    let scale = if current_aspect_ratio >= desired_aspect_ratio {
         trace!(
             "    Window too wide; want {} but am currently {}",
             desired_aspect_ratio,
             current_aspect_ratio
         );
-        w = fit.w * current_aspect_ratio;
-        h = fit.h;
-        let excess_width = w - fit.w;
-        x = fit.x - excess_width * 0.5;
-        y = fit.y;
+        fit.height() / viewport_size.y
     } else {
         trace!(
             "    Window too tall; want {} but am currently {}",
             desired_aspect_ratio,
             current_aspect_ratio
         );
-        w = fit.w;
-        h = fit.w / current_aspect_ratio;
-        let excess_height = h - fit.h;
-        x = fit.x;
-        y = fit.y - excess_height * 0.5;
+        fit.width() / viewport_size.x
+    };
+
+    let camera_fit = CameraFit {
+        center: fit.center(),
+        scale,
+    };
+    trace!("    {:?}", camera_fit);
+    camera_fit
+}
+
+/// Camera that eases towards showing `target`
+#[derive(Debug, Clone, Component)]
+pub struct AutoFitCamera {
+    /// World rectangle to show
+    pub target: Rect,
+
+    /// Extra world space around `target`
+    pub margin: f32,
+
+    /// Larger is faster; `f32::INFINITY` jumps to the fit immediately
+    pub ease_rate: f32,
+
+    /// Cleared when the user moves the camera manually
+    pub enabled: bool,
+
+    /// Jump to the next fit instead of easing; cleared once applied
+    pub snap: bool,
+}
+
+impl Default for AutoFitCamera {
+    fn default() -> Self {
+        Self {
+            target: Rect::from_center_size(Vec2::ZERO, Vec2::splat(Tile::CELL_GRID_WIDTH as f32)),
+            margin: 0.5,
+            ease_rate: 4.0,
+            enabled: true,
+            snap: true,
+        }
+    }
+}
+
+impl AutoFitCamera {
+    /// Set a new target, re-enabling the auto fit if the target changed
+    pub fn set_target(&mut self, target: Rect) {
+        if target != self.target {
+            self.target = target;
+            self.enabled = true;
+        }
+    }
+
+    /// Fit of `target` plus margin in a viewport of `viewport_size`
+    pub fn fit(&self, viewport_size: Vec2) -> CameraFit {
+        let margin = Vec2::splat(self.margin);
+        let fit = Rect::from_corners(self.target.min - margin, self.target.max + margin);
+        camera_zoom_to_fit(fit, viewport_size)
     }
+}
 
-    trace!("    x={}, y={}, w={}, h={}", x, y, w, h);
-    Camera2D::from_display_rect(mq::Rect { x, y, w, h })
+/// Fraction of the remaining distance to cover after `dt` seconds
+fn ease_factor(ease_rate: f32, dt: f32) -> f32 {
+    1.0 - (-ease_rate * dt).exp()
 }
 
-*/
+pub fn camera_auto_fit_system(
+    time: Res<Time>,
+    mut cameras: Query<(
+        &Camera,
+        &mut AutoFitCamera,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    for (camera, mut auto_fit, mut transform, mut projection) in cameras.iter_mut() {
+        if !auto_fit.enabled {
+            continue;
+        }
+        // Re-evaluated every frame so the fit follows window resizes
+        let Some(viewport_size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        if viewport_size.x <= 0.0 || viewport_size.y <= 0.0 {
+            continue;
+        }
+
+        let fit = auto_fit.fit(viewport_size);
+        let t = if auto_fit.snap {
+            auto_fit.snap = false;
+            1.0
+        } else {
+            ease_factor(auto_fit.ease_rate, time.delta_seconds())
+        };
+
+        let center = transform.translation.truncate().lerp(fit.center, t);
+        let scale = projection.scale + (fit.scale - projection.scale) * t;
+
+        // Avoid triggering change detection once settled
+        if center != transform.translation.truncate() {
+            transform.translation = center.extend(transform.translation.z);
+        }
+        if scale != projection.scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zoom_to_fit() {
+        let fit = Rect::new(0.0, 0.0, 4.0, 2.0);
+
+        // same aspect ratio
+        assert_eq!(
+            camera_zoom_to_fit(fit, Vec2::new(400.0, 200.0)),
+            CameraFit {
+                center: Vec2::new(2.0, 1.0),
+                scale: 0.01,
+            }
+        );
+
+        // too wide: height limits
+        assert_eq!(
+            camera_zoom_to_fit(fit, Vec2::new(1000.0, 100.0)).scale,
+            0.02
+        );
+
+        // too tall: width limits
+        assert_eq!(
+            camera_zoom_to_fit(fit, Vec2::new(100.0, 1000.0)).scale,
+            0.04
+        );
+    }
+
+    #[test]
+    fn ease() {
+        assert_eq!(ease_factor(4.0, 0.0), 0.0);
+        assert_eq!(ease_factor(f32::INFINITY, 0.1), 1.0);
+        let half = ease_factor(4.0, 0.1);
+        assert!(0.0 < half && half < 1.0);
+        assert!(ease_factor(4.0, 0.2) > half);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod camera;
+pub mod render_board;
+pub mod render_glyph;
 pub mod render_tile;
 pub mod shape;
 pub mod theme;
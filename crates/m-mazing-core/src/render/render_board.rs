@@ -0,0 +1,165 @@
+use bevy_prototype_lyon::{
+    draw::{Fill, Stroke},
+    entity::ShapeBundle,
+    geometry::GeometryBuilder,
+    shapes::{self, RectangleOrigin},
+};
+
+use crate::prelude::*;
+
+use super::{render_glyph::spawn_pawn_glyph, render_tile::RenderLayerZ, RenderState};
+
+impl BoardCoord {
+    /// World position of the center of the cell
+    pub fn world_center(self) -> Vec2 {
+        Vec2::new(self.x as f32 + 0.5, -(self.y as f32) - 0.5)
+    }
+
+    /// Cell containing world position `pos`
+    pub fn from_world(pos: Vec2) -> Self {
+        Self::new(pos.x.floor() as i32, (-pos.y).floor() as i32)
+    }
+}
+
+impl Board {
+    /// World rectangle covered by placed tiles
+    pub fn world_rect(&self) -> Rect {
+        let (min, max) = self.bounds();
+        Rect::new(
+            min.x as f32,
+            -(max.y as f32) - 1.0,
+            max.x as f32 + 1.0,
+            -(min.y as f32),
+        )
+    }
+}
+
+impl PlacedTile {
+    /// World position of the center of the tile
+    pub fn world_center(&self) -> Vec2 {
+        let half_width = 0.5 * (Tile::CELL_GRID_WIDTH - 1) as f32;
+        self.origin.world_center() + Vec2::new(half_width, -half_width)
+    }
+
+    pub fn spawn(&self, render: &RenderState, commands: &mut Commands) -> Entity {
+        self.tile.spawn(self.world_center(), render, commands)
+    }
+}
+
+impl Pawn {
+    pub fn spawn(self, coord: BoardCoord, render: &RenderState, commands: &mut Commands) -> Entity {
+        let shape = shapes::Circle {
+            center: Vec2::ZERO,
+            radius: render.theme.pawn_radius,
+        };
+        let translation = coord.world_center().extend(RenderLayerZ::Pawn.z());
+        let id = commands
+            .spawn((
+                ShapeBundle {
+                    path: GeometryBuilder::build_as(&shape),
+                    spatial: SpatialBundle::from_transform(Transform::from_translation(
+                        translation,
+                    )),
+                    ..default()
+                },
+                Fill::color(self.as_color(render)),
+                Stroke::new(
+                    render.theme.pawn_outline_color,
+                    render.theme.pawn_outline_thickness,
+                ),
+            ))
+            .id();
+
+        let glyph_size = 1.3 * render.theme.pawn_radius;
+        spawn_pawn_glyph(render, self, Vec3::Z, glyph_size, commands, id);
+        id
+    }
+}
+
+/// Shade cell at `coord` to show it is a possible destination
+pub fn spawn_highlight(coord: BoardCoord, render: &RenderState, commands: &mut Commands) -> Entity {
+    spawn_cell_shade(coord, render.theme.highlight_color, commands)
+}
+
+/// Faintly shade cell at `coord` to show a hinted destination
+pub fn spawn_hint_highlight(
+    coord: BoardCoord,
+    render: &RenderState,
+    commands: &mut Commands,
+) -> Entity {
+    spawn_cell_shade(coord, render.theme.hint_color, commands)
+}
+
+fn spawn_cell_shade(coord: BoardCoord, color: Color, commands: &mut Commands) -> Entity {
+    let shape = shapes::Rectangle {
+        extents: Vec2::new(1., 1.),
+        origin: RectangleOrigin::Center,
+    };
+    let translation = coord.world_center().extend(RenderLayerZ::Highlight.z());
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shape),
+                spatial: SpatialBundle::from_transform(Transform::from_translation(translation)),
+                ..default()
+            },
+            Fill::color(color),
+        ))
+        .id()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn world_coords() {
+        for coord in [
+            BoardCoord::new(0, 0),
+            BoardCoord::new(3, -2),
+            BoardCoord::new(-5, 7),
+        ] {
+            assert_eq!(BoardCoord::from_world(coord.world_center()), coord);
+            assert_eq!(
+                BoardCoord::from_world(coord.world_center() + Vec2::new(0.49, -0.49)),
+                coord
+            );
+        }
+
+        let placed = PlacedTile {
+            name: "test".to_string(),
+            tile: Tile::default(),
+            orientation: Orientation::IDENTITY,
+            origin: BoardCoord::new(1, -4),
+        };
+        assert_eq!(placed.world_center(), Vec2::new(3.0, 2.0));
+
+        let board = Board::new("start".to_string(), Tile::default());
+        assert_eq!(board.world_rect(), Rect::new(0.0, -4.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn pawn_glyph() {
+        use bevy::ecs::system::CommandQueue;
+
+        let mut world = World::new();
+        let mut spawn = |pawn_glyphs: bool| {
+            let render = RenderState {
+                theme: Theme {
+                    pawn_glyphs,
+                    ..Default::default()
+                },
+            };
+            let mut queue = CommandQueue::default();
+            let mut commands = Commands::new(&mut queue, &world);
+            let id = Pawn::Purple.spawn(BoardCoord::new(1, 2), &render, &mut commands);
+            queue.apply(&mut world);
+            world
+                .get::<Children>(id)
+                .map_or(0, |children| children.len())
+        };
+
+        assert_eq!(spawn(false), 0);
+        assert_eq!(spawn(true), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy_prototype_lyon::{draw::Stroke, entity::ShapeBundle, geometry::GeometryBuilder, shapes};
+
+use crate::{prelude::*, render::draw_connected_line, render::polar_to_cartesian};
+
+use super::RenderState;
+
+impl Pawn {
+    /// Weapon of the pawn's hero, drawn `size` wide and centered on the origin.
+    ///
+    /// Lets pawns be told apart without relying on color.
+    fn glyph_builder(self, size: f32) -> GeometryBuilder {
+        let p = |x: f32, y: f32| Vec2::new(x, y) * size;
+        let line = |a: Vec2, b: Vec2| shapes::Line(a, b);
+        let builder = GeometryBuilder::new();
+
+        match self {
+            // bow with an arrow
+            Self::Green => {
+                const NUM_POINTS: u32 = 9;
+                const HALF_ANGLE: f32 = 1.2;
+                let bow = (0..NUM_POINTS).map(|idx| {
+                    let angle =
+                        -HALF_ANGLE + 2.0 * HALF_ANGLE * idx as f32 / (NUM_POINTS - 1) as f32;
+                    polar_to_cartesian(0.45 * size, angle) + p(-0.2, 0.0)
+                });
+                let bow_top = polar_to_cartesian(0.45 * size, HALF_ANGLE) + p(-0.2, 0.0);
+                let bow_bottom = polar_to_cartesian(0.45 * size, -HALF_ANGLE) + p(-0.2, 0.0);
+                draw_connected_line(bow, builder)
+                    .add(&line(bow_top, bow_bottom))
+                    .add(&line(p(-0.45, 0.0), p(0.45, 0.0)))
+            }
+            // axe: handle with a blade on one side
+            Self::Orange => {
+                let blade = [p(0.0, 0.4), p(0.35, 0.5), p(0.35, 0.0), p(0.0, 0.1)];
+                draw_connected_line(
+                    blade.iter().chain(blade.first()).copied(),
+                    builder.add(&line(p(0.0, -0.5), p(0.0, 0.5))),
+                )
+            }
+            // sword: blade, cross guard and grip
+            Self::Yellow => {
+                let grip = [
+                    p(-0.06, -0.2),
+                    p(-0.06, -0.45),
+                    p(0.06, -0.45),
+                    p(0.06, -0.2),
+                ];
+                draw_connected_line(
+                    grip.iter().copied(),
+                    builder
+                        .add(&line(p(0.0, -0.2), p(0.0, 0.5)))
+                        .add(&line(p(-0.25, -0.2), p(0.25, -0.2))),
+                )
+            }
+            // wand with a sparkle at the tip
+            Self::Purple => {
+                let tip = p(0.25, 0.25);
+                let mut builder = builder.add(&line(p(-0.45, -0.45), tip));
+                for idx in 0..4 {
+                    let angle = idx as f32 * std::f32::consts::FRAC_PI_4;
+                    let arm = polar_to_cartesian(0.18 * size, angle);
+                    builder = builder.add(&line(tip - arm, tip + arm));
+                }
+                builder
+            }
+        }
+    }
+}
+
+/// Draw the glyph of `pawn` as a child of `parent`, if enabled by the theme
+pub(crate) fn spawn_pawn_glyph(
+    render: &RenderState,
+    pawn: Pawn,
+    translation: Vec3,
+    size: f32,
+    commands: &mut Commands,
+    parent: Entity,
+) {
+    if !render.theme.pawn_glyphs {
+        return;
+    }
+
+    let glyph = commands
+        .spawn((
+            ShapeBundle {
+                path: pawn.glyph_builder(size).build(),
+                spatial: SpatialBundle::from_transform(Transform::from_translation(translation)),
+                ..default()
+            },
+            Stroke::new(
+                render.theme.pawn_glyph_color,
+                render.theme.pawn_glyph_thickness,
+            ),
+        ))
+        .id();
+    commands.entity(parent).push_children(&[glyph]);
+}
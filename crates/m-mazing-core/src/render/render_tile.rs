@@ -7,25 +7,32 @@ use bevy_prototype_lyon::{
 
 use crate::{prelude::*, render::draw_connected_line, render::polar_to_cartesian};
 
-use super::RenderState;
+use super::{render_glyph::spawn_pawn_glyph, RenderState};
 
 const GRID_WIDTH: f32 = Tile::CELL_GRID_WIDTH as f32;
 const GRID_HALF_WIDTH: f32 = 0.5 * GRID_WIDTH;
 const CELL_WIDTH: f32 = 1.0;
 const CELL_HALF_WIDTH: f32 = 0.5 * CELL_WIDTH;
 
+/// Pawn glyph size when drawn in the corner of a cell
+const CORNER_GLYPH_SIZE: f32 = 0.3;
+const CORNER_GLYPH_OFFSET: Vec2 = Vec2::new(0.22, -0.22);
+
 #[derive(Clone, Copy, Debug)]
 #[repr(u16)]
-enum RenderLayerZ {
+pub(crate) enum RenderLayerZ {
     CellBg,
     CellItem,
+    Glyph,
     CellMarker,
     Escalator,
+    Highlight,
+    Pawn,
 }
 
 impl RenderLayerZ {
     const HEIGHT: f32 = 100.0;
-    const fn z(self) -> f32 {
+    pub(crate) const fn z(self) -> f32 {
         ((self as u16) * (Self::HEIGHT as u16)) as f32
     }
 }
@@ -149,6 +156,15 @@ fn render_warp(
         ))
         .id();
     commands.entity(tile_entity).push_children(&[geo]);
+
+    spawn_pawn_glyph(
+        render,
+        pawn,
+        (location + CORNER_GLYPH_OFFSET).extend(RenderLayerZ::Glyph.z()),
+        CORNER_GLYPH_SIZE,
+        commands,
+        tile_entity,
+    );
 }
 
 fn render_loot(
@@ -176,6 +192,16 @@ fn render_loot(
         ))
         .id();
     commands.entity(tile_entity).push_children(&[entity]);
+
+    // inside the loot outline
+    spawn_pawn_glyph(
+        render,
+        pawn,
+        (location + Vec2::new(0.5, -0.5)).extend(RenderLayerZ::Glyph.z()),
+        CORNER_GLYPH_SIZE,
+        commands,
+        tile_entity,
+    );
 }
 
 fn render_camera(
@@ -367,6 +393,15 @@ fn render_final_exit(
         ))
         .id();
     commands.entity(tile_entity).push_children(&[arrow]);
+
+    spawn_pawn_glyph(
+        render,
+        pawn,
+        (location + CORNER_GLYPH_OFFSET).extend(RenderLayerZ::Glyph.z()),
+        CORNER_GLYPH_SIZE,
+        commands,
+        tile_entity,
+    );
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -416,6 +451,23 @@ fn render_wall(
         ))
         .id();
     commands.entity(tile_entity).push_children(&[geo]);
+
+    if let WallState::Explore(pawn) = wall {
+        // next to the middle of the wall, on the side towards the tile center
+        let mid = a.lerp(b, 0.5);
+        let mut inward = (b - a).perp().normalize_or_zero();
+        if inward.dot(-mid) < 0.0 {
+            inward = -inward;
+        }
+        spawn_pawn_glyph(
+            render,
+            pawn,
+            (mid + inward * CORNER_GLYPH_OFFSET.x).extend(RenderLayerZ::Glyph.z()),
+            CORNER_GLYPH_SIZE,
+            commands,
+            tile_entity,
+        );
+    }
 }
 
 #[derive(Component)]
@@ -437,7 +489,7 @@ impl TileChildZ {
 
 impl Tile {
     pub fn spawn(&self, pos: Vec2, render: &RenderState, commands: &mut Commands) -> Entity {
-        let is_reachable_coord = self.reachable_coords();
+        let reachable = self.reachable_coords();
 
         let tile_bg_color = if self.has_camera() {
             render.theme.tile_camera_bg_color
@@ -528,8 +580,8 @@ impl Tile {
                 let col_idx_float = col_idx as f32;
                 let x = -GRID_HALF_WIDTH + col_idx_float * CELL_WIDTH;
 
-                let is_reachable = is_reachable_coord[row_idx][col_idx];
-                if !is_reachable {
+                let coord = TileGridCoord::new(col_idx as u8, row_idx as u8).expect("grid coord");
+                if !reachable.contains(coord) {
                     let covered_cell = shapes::Rectangle {
                         extents: Vec2::new(1., 1.),
                         origin: RectangleOrigin::TopLeft,
@@ -596,3 +648,80 @@ impl Tile {
         tile_entity
     }
 }
+
+/// Cell or wall of a drawn `Tile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TilePart {
+    Cell(TileGridCoord),
+    Wall(TileGridCoord, CartesianDirection),
+}
+
+impl TilePart {
+    /// Part of a tile spawned at `tile_pos` under world position `pos`; walls are hit
+    /// within `wall_margin` of their line
+    pub fn from_world(pos: Vec2, tile_pos: Vec2, wall_margin: f32) -> Option<Self> {
+        // from the top left corner, with y pointing down
+        let local = Vec2::new(pos.x - tile_pos.x, tile_pos.y - pos.y) + GRID_HALF_WIDTH;
+        let outside = -wall_margin..GRID_WIDTH + wall_margin;
+        if !(outside.contains(&local.x) && outside.contains(&local.y)) {
+            return None;
+        }
+
+        let max_idx = GRID_WIDTH - CELL_WIDTH;
+        let cell = (local / CELL_WIDTH)
+            .floor()
+            .clamp(Vec2::ZERO, Vec2::splat(max_idx));
+        let coord = TileGridCoord::new(cell.x as u8, cell.y as u8)?;
+        let within = local - cell * CELL_WIDTH;
+        let (dist, dir) = [
+            (within.x, CartesianDirection::Left),
+            (CELL_WIDTH - within.x, CartesianDirection::Right),
+            (within.y, CartesianDirection::Up),
+            (CELL_WIDTH - within.y, CartesianDirection::Down),
+        ]
+        .into_iter()
+        .map(|(dist, dir)| (dist.abs(), dir))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))?;
+        Some(if dist < wall_margin {
+            Self::Wall(coord, dir)
+        } else {
+            Self::Cell(coord)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tile_part() {
+        let tile_pos = Vec2::new(10.0, -3.0);
+        let part = |x, y| TilePart::from_world(tile_pos + Vec2::new(x, y), tile_pos, 0.2);
+        let coord = |x, y| TileGridCoord::new(x, y).unwrap();
+
+        assert_eq!(part(-1.5, 1.5), Some(TilePart::Cell(coord(0, 0))));
+        assert_eq!(part(1.5, -1.5), Some(TilePart::Cell(coord(3, 3))));
+        assert_eq!(part(0.4, 0.6), Some(TilePart::Cell(coord(2, 1))));
+        assert_eq!(
+            part(0.05, 0.6),
+            Some(TilePart::Wall(coord(2, 1), CartesianDirection::Left))
+        );
+        assert_eq!(
+            part(0.4, 1.05),
+            Some(TilePart::Wall(coord(2, 0), CartesianDirection::Down))
+        );
+
+        // outer walls can be hit from just outside the tile
+        assert_eq!(
+            part(-2.1, -1.5),
+            Some(TilePart::Wall(coord(0, 3), CartesianDirection::Left))
+        );
+        assert_eq!(
+            part(1.5, -1.9),
+            Some(TilePart::Wall(coord(3, 3), CartesianDirection::Down))
+        );
+        assert_eq!(part(-2.3, 0.0), None);
+        assert_eq!(part(0.0, 2.5), None);
+    }
+}
@@ -1,33 +1,87 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
 use crate::*;
 
-#[derive(Clone, Debug)]
+use super::RenderState;
+
+/// Colors are written as `"#rrggbb"` or `"#rrggbbaa"` in theme files.
+///
+/// Keys missing from a theme file keep their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Theme {
+    #[serde(with = "hex_color")]
     pub bg_color: Color,
+    #[serde(with = "hex_color")]
     pub font_color: Color,
     pub font_size: f32,
+    #[serde(with = "hex_color")]
     pub tile_normal_bg_color: Color,
+    #[serde(with = "hex_color")]
     pub tile_camera_bg_color: Color,
+    #[serde(with = "hex_color")]
     pub wall_blocked_color: Color,
+    #[serde(with = "hex_color")]
     pub wall_open_color: Color,
+    #[serde(with = "hex_color")]
     pub wall_orange_only_color: Color,
     pub wall_orange_only_hole_width: f32,
+    #[serde(with = "hex_color")]
     pub wall_entrance_color: Color,
     pub wall_thickness: f32,
     pub warp_thickness: f32,
     pub loot_thickness: f32,
+    #[serde(with = "hex_color")]
     pub pawn_green_color: Color,
+    #[serde(with = "hex_color")]
     pub pawn_orange_color: Color,
+    #[serde(with = "hex_color")]
     pub pawn_yellow_color: Color,
+    #[serde(with = "hex_color")]
     pub pawn_purple_color: Color,
+    #[serde(with = "hex_color")]
     pub timer_color: Color,
+    #[serde(with = "hex_color")]
     pub final_exit_arrow_color: Color,
+    #[serde(with = "hex_color")]
     pub camera_color: Color,
+    #[serde(with = "hex_color")]
     pub crystal_ball_color: Color,
+    #[serde(with = "hex_color")]
     pub escalator_color: Color,
     pub escalator_thickness: f32,
     pub used_marker_thickness: f32,
+    #[serde(with = "hex_color")]
     pub used_marker_color: Color,
+    #[serde(with = "hex_color")]
     pub unreachable_cell_color: Color,
+    pub pawn_radius: f32,
+    #[serde(with = "hex_color")]
+    pub pawn_outline_color: Color,
+    pub pawn_outline_thickness: f32,
+    #[serde(with = "hex_color")]
+    pub highlight_color: Color,
+
+    /// Destinations of hinted actions in practice mode; fainter than `highlight_color`
+    #[serde(with = "hex_color")]
+    pub hint_color: Color,
+
+    /// "Do Something!" alert text
+    #[serde(with = "hex_color")]
+    pub do_something_color: Color,
+
+    /// Draw a glyph next to pawn-colored elements so colors are not needed
+    /// to tell pawns apart
+    pub pawn_glyphs: bool,
+    #[serde(with = "hex_color")]
+    pub pawn_glyph_color: Color,
+    pub pawn_glyph_thickness: f32,
 }
 
 impl Default for Theme {
@@ -60,6 +114,294 @@ impl Default for Theme {
             used_marker_thickness: 0.18,
             used_marker_color: Color::BLACK,
             unreachable_cell_color: Color::DARK_GRAY,
+            pawn_radius: 0.3,
+            pawn_outline_color: Color::BLACK,
+            pawn_outline_thickness: 0.04,
+            highlight_color: Color::rgba(0.2, 0.6, 1.0, 0.4),
+            hint_color: Color::rgba(0.2, 0.6, 1.0, 0.15),
+            do_something_color: Color::RED,
+            pawn_glyphs: false,
+            pawn_glyph_color: Color::BLACK,
+            pawn_glyph_thickness: 0.03,
+        }
+    }
+}
+
+/// Themes shipped with the game; the default theme is called "light"
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("dark", include_str!("../../themes/dark.ron")),
+    (
+        "high-contrast",
+        include_str!("../../themes/high-contrast.ron"),
+    ),
+    ("colorblind", include_str!("../../themes/colorblind.ron")),
+];
+
+const DEFAULT_THEME_NAME: &str = "light";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ThemeError {
+    #[error("Failed to parse theme: {0}")]
+    Parse(String),
+
+    #[error("Unknown theme file format {0:?}; expected .ron or .toml")]
+    UnknownFormat(PathBuf),
+
+    #[error("Failed to read theme from {path:?}: {msg}")]
+    Io { path: PathBuf, msg: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeFormat {
+    Ron,
+    Toml,
+}
+
+impl ThemeFormat {
+    pub fn from_path(path: &Path) -> Result<Self, ThemeError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => Ok(Self::Ron),
+            Some("toml") => Ok(Self::Toml),
+            _ => Err(ThemeError::UnknownFormat(path.to_owned())),
+        }
+    }
+}
+
+impl Theme {
+    /// Names accepted by [`Theme::builtin`]
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        std::iter::once(DEFAULT_THEME_NAME).chain(BUILTIN_THEMES.iter().map(|(name, _)| *name))
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        if name == DEFAULT_THEME_NAME {
+            return Some(Self::default());
+        }
+        let (_name, theme_str) = BUILTIN_THEMES.iter().find(|(n, _)| *n == name)?;
+        let theme = Self::from_str_format(theme_str, ThemeFormat::Ron)
+            .unwrap_or_else(|err| panic!("Invalid builtin theme {:?}: {}", name, err));
+        Some(theme)
+    }
+
+    /// Builtin theme after `current`, wrapping around; starts at the first
+    /// builtin theme if `current` is not a builtin theme
+    pub fn next_builtin(current: &Theme) -> (&'static str, Self) {
+        let themes: Vec<(&'static str, Self)> = Self::builtin_names()
+            .map(|name| (name, Self::builtin(name).expect("builtin theme")))
+            .collect();
+        let next_idx = themes
+            .iter()
+            .position(|(_name, theme)| theme == current)
+            .map_or(0, |idx| (idx + 1) % themes.len());
+        themes.into_iter().nth(next_idx).expect("builtin theme")
+    }
+
+    /// Parse a theme, warning about unknown keys
+    pub fn from_str_format(theme_str: &str, format: ThemeFormat) -> Result<Self, ThemeError> {
+        let (theme, unknown_keys) = Self::parse(theme_str, format)?;
+        for key in unknown_keys {
+            warn!("Ignoring unknown theme key {:?}", key);
         }
+        Ok(theme)
+    }
+
+    /// Parsed theme along with unknown keys
+    fn parse(theme_str: &str, format: ThemeFormat) -> Result<(Self, Vec<String>), ThemeError> {
+        let mut unknown_keys = Vec::new();
+        let on_unknown = |path: serde_ignored::Path| unknown_keys.push(path.to_string());
+        let theme = match format {
+            ThemeFormat::Ron => {
+                let mut de = ron::Deserializer::from_str(theme_str)
+                    .map_err(|err| ThemeError::Parse(err.to_string()))?;
+                serde_ignored::deserialize(&mut de, on_unknown)
+                    .map_err(|err| ThemeError::Parse(err.to_string()))?
+            }
+            ThemeFormat::Toml => {
+                let de = toml::Deserializer::new(theme_str);
+                serde_ignored::deserialize(de, on_unknown)
+                    .map_err(|err| ThemeError::Parse(err.to_string()))?
+            }
+        };
+        Ok((theme, unknown_keys))
+    }
+
+    /// Load theme file; format is picked by the file extension
+    pub fn load(path: &Path) -> Result<Self, ThemeError> {
+        info!("Loading theme from {:?}", path);
+        let format = ThemeFormat::from_path(path)?;
+        let theme_str = std::fs::read_to_string(path).map_err(|err| ThemeError::Io {
+            path: path.to_owned(),
+            msg: err.to_string(),
+        })?;
+        Self::from_str_format(&theme_str, format)
+    }
+
+    pub fn to_ron_string(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("Theme is always serializable")
+    }
+}
+
+mod hex_color {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::*;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color
+            .as_rgba_f32()
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let hex = if a == u8::MAX {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        Color::hex(digits).map_err(|err| D::Error::custom(format!("{:?}: {}", hex, err)))
+    }
+}
+
+/// Theme file to reload when it is modified
+#[derive(Debug, Resource)]
+pub struct ThemeFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    poll: Timer,
+}
+
+impl ThemeFile {
+    const POLL_SECS: f32 = 0.5;
+
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::modified_time(&path);
+        Self {
+            path,
+            modified,
+            poll: Timer::from_seconds(Self::POLL_SECS, TimerMode::Repeating),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+/// Hot reload the theme file, if any
+pub fn theme_reload_system(
+    time: Res<Time>,
+    file: Option<ResMut<ThemeFile>>,
+    mut render: ResMut<RenderState>,
+) {
+    let Some(mut file) = file else {
+        return;
+    };
+    if !file.poll.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = ThemeFile::modified_time(&file.path);
+    if modified == file.modified {
+        return;
+    }
+    file.modified = modified;
+
+    match Theme::load(&file.path) {
+        Ok(theme) => {
+            info!("Reloaded theme {:?}", file.path);
+            render.theme = theme;
+        }
+        Err(err) => error!("Failed to reload theme: {}", err),
+    }
+}
+
+/// Keep the window background in sync with the theme
+pub fn theme_clear_color_system(render: Res<RenderState>, mut clear_color: ResMut<ClearColor>) {
+    if render.is_changed() {
+        clear_color.0 = render.theme.bg_color;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        crate::init_logging();
+
+        let names: Vec<_> = Theme::builtin_names().collect();
+        assert_eq!(names, ["light", "dark", "high-contrast", "colorblind"]);
+        for name in names {
+            assert!(Theme::builtin(name).is_some(), "{}", name);
+        }
+        assert_eq!(Theme::builtin("light"), Some(Theme::default()));
+        assert_ne!(Theme::builtin("dark"), Some(Theme::default()));
+        assert!(Theme::builtin("missing").is_none());
+
+        let (name, dark) = Theme::next_builtin(&Theme::default());
+        assert_eq!(name, "dark");
+        assert_eq!(Theme::next_builtin(&dark).0, "high-contrast");
+        let custom = Theme {
+            wall_thickness: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(Theme::next_builtin(&custom).0, "light");
+    }
+
+    #[test]
+    fn unknown_and_missing_keys() {
+        crate::init_logging();
+
+        let (theme, unknown) = Theme::parse(
+            r##"(
+                bg_color: "#102030",
+                wall_thickness: 0.2,
+                sparkles: true,
+            )"##,
+            ThemeFormat::Ron,
+        )
+        .unwrap();
+        assert_eq!(unknown, ["sparkles"]);
+        assert_eq!(theme.bg_color, Color::rgb_u8(0x10, 0x20, 0x30));
+        assert_eq!(theme.wall_thickness, 0.2);
+        assert_eq!(theme.pawn_radius, Theme::default().pawn_radius);
+
+        let (theme, unknown) = Theme::parse(
+            r##"
+                highlight_color = "#ff000080"
+                glow = 3
+            "##,
+            ThemeFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(unknown, ["glow"]);
+        assert_eq!(
+            theme.highlight_color,
+            Color::rgba_u8(0xff, 0x00, 0x00, 0x80)
+        );
+
+        assert!(matches!(
+            Theme::parse(r#"(bg_color: "nope")"#, ThemeFormat::Ron),
+            Err(ThemeError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        crate::init_logging();
+
+        let theme = Theme::builtin("high-contrast").unwrap();
+        let (parsed, unknown) = Theme::parse(&theme.to_ron_string(), ThemeFormat::Ron).unwrap();
+        assert!(unknown.is_empty());
+        assert_eq!(parsed, theme);
     }
 }
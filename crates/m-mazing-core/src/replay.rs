@@ -0,0 +1,376 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("Failed to parse replay: {0}")]
+    Parse(String),
+
+    #[error("Failed to access replay {path:?}: {msg}")]
+    Io { path: PathBuf, msg: String },
+
+    #[error("Unsupported replay version {0}; expected {}", Replay::VERSION)]
+    Version(u32),
+
+    #[error("Unknown scenario {0}")]
+    UnknownScenario(u8),
+
+    #[error(transparent)]
+    Setup(#[from] GameSetupError),
+
+    #[error("Action {idx} by {player:?} is not legal: {err}")]
+    Action {
+        idx: usize,
+        player: PlayerId,
+        err: ActionError,
+    },
+}
+
+/// Action accepted during a recorded game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayAction {
+    /// Milliseconds the sand timer had been running when the action was applied
+    pub time_ms: u64,
+    pub player: PlayerId,
+    pub action: Action,
+}
+
+impl ReplayAction {
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+}
+
+/// Everything needed to play a game again exactly as it happened.
+///
+/// Times only advance while the sand timer runs, so pauses are left out and the timer
+/// can be simulated from the action times alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+
+    /// Rule book scenario number; see [`Scenario::builtin`]
+    pub scenario: u8,
+    pub num_players: u8,
+
+    /// Seed the game was set up with
+    pub seed: u64,
+    pub timer_ms: u64,
+
+    /// In the order they were applied
+    pub actions: Vec<ReplayAction>,
+
+    /// Milliseconds the game lasted
+    pub length_ms: u64,
+}
+
+impl Replay {
+    /// Bumped on every incompatible change to the replay format or game rules
+    pub const VERSION: u32 = 2;
+
+    pub fn new(scenario: u8, num_players: u8, seed: u64, timer_duration: Duration) -> Self {
+        Self {
+            version: Self::VERSION,
+            scenario,
+            num_players,
+            seed,
+            timer_ms: timer_duration.as_millis() as u64,
+            actions: Vec::new(),
+            length_ms: 0,
+        }
+    }
+
+    pub fn timer_duration(&self) -> Duration {
+        Duration::from_millis(self.timer_ms)
+    }
+
+    pub fn length(&self) -> Duration {
+        Duration::from_millis(self.length_ms)
+    }
+
+    /// Record an action that was applied at `time`
+    pub fn record(&mut self, time: Duration, player: PlayerId, action: Action) {
+        let time_ms = time.as_millis() as u64;
+        self.length_ms = self.length_ms.max(time_ms);
+        self.actions.push(ReplayAction {
+            time_ms,
+            player,
+            action,
+        });
+    }
+
+    /// Game ended, or recording stopped, at `time`
+    pub fn finish(&mut self, time: Duration) {
+        self.length_ms = self.length_ms.max(time.as_millis() as u64);
+    }
+
+    pub fn from_ron_str(replay_str: &str) -> Result<Self, ReplayError> {
+        let replay: Self =
+            ron::from_str(replay_str).map_err(|err| ReplayError::Parse(err.to_string()))?;
+        if replay.version != Self::VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+        Ok(replay)
+    }
+
+    pub fn to_ron_string(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("Replay is always serializable")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let replay_str = std::fs::read_to_string(path).map_err(|err| ReplayError::Io {
+            path: path.to_owned(),
+            msg: err.to_string(),
+        })?;
+        Self::from_ron_str(&replay_str)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        std::fs::write(path, self.to_ron_string()).map_err(|err| ReplayError::Io {
+            path: path.to_owned(),
+            msg: err.to_string(),
+        })
+    }
+
+    /// Game state before the first action
+    pub fn initial_state(&self, tileset: &[(String, Tile)]) -> Result<GameState, ReplayError> {
+        let scenario =
+            Scenario::builtin(self.scenario).ok_or(ReplayError::UnknownScenario(self.scenario))?;
+        Ok(GameState::new(
+            self.num_players,
+            &scenario,
+            tileset,
+            self.seed,
+        )?)
+    }
+}
+
+/// Replay being watched; can be moved to any point in time
+#[derive(Debug, Clone)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    initial: GameState,
+    state: GameState,
+    timer: SandTimer,
+    time: Duration,
+
+    /// Index of the next action to apply
+    next_action: usize,
+}
+
+impl ReplayPlayback {
+    /// Playback at the start of `replay`; fails if any recorded action is not legal
+    pub fn new(replay: Replay, tileset: &[(String, Tile)]) -> Result<Self, ReplayError> {
+        let initial = replay.initial_state(tileset)?;
+        let mut timer = SandTimer::new(replay.timer_duration());
+        timer.start();
+        let playback = Self {
+            state: initial.clone(),
+            initial,
+            timer,
+            time: Duration::ZERO,
+            next_action: 0,
+            replay,
+        };
+
+        let mut check = playback.clone();
+        check.advance_to(check.length())?;
+        Ok(playback)
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn timer(&self) -> &SandTimer {
+        &self.timer
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn length(&self) -> Duration {
+        self.replay.length()
+    }
+
+    /// Number of actions applied so far
+    pub fn num_applied(&self) -> usize {
+        self.next_action
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.length()
+    }
+
+    /// Move to `time`, clamped to the length of the replay; returns true if the game state
+    /// changed
+    pub fn seek(&mut self, time: Duration) -> bool {
+        let time = time.min(self.length());
+        let rewind = time < self.time;
+        if rewind {
+            self.state = self.initial.clone();
+            self.timer = SandTimer::new(self.replay.timer_duration());
+            self.timer.start();
+            self.time = Duration::ZERO;
+            self.next_action = 0;
+        }
+        let events = self.advance_to(time).expect("actions checked on creation");
+        rewind || !events.is_empty()
+    }
+
+    /// Apply actions up to and including `time`; returns the resulting events
+    fn advance_to(&mut self, time: Duration) -> Result<Vec<GameEvent>, ReplayError> {
+        let mut events = Vec::new();
+        while let Some(entry) = self.replay.actions.get(self.next_action).cloned() {
+            if entry.time() > time {
+                break;
+            }
+            self.run_timer(entry.time(), &mut events);
+            let applied = self
+                .state
+                .apply_action(entry.player, &entry.action)
+                .map_err(|err| ReplayError::Action {
+                    idx: self.next_action,
+                    player: entry.player,
+                    err,
+                })?;
+            if applied
+                .iter()
+                .any(|event| matches!(event, GameEvent::TimerFlipped(_)))
+            {
+                self.timer.flip();
+            }
+            if self.state.phase().is_over() {
+                self.timer.pause();
+            }
+            events.extend(applied);
+            self.next_action += 1;
+        }
+        self.run_timer(time, &mut events);
+        Ok(events)
+    }
+
+    fn run_timer(&mut self, time: Duration, events: &mut Vec<GameEvent>) {
+        if self.timer.tick(time.saturating_sub(self.time)) {
+            let event = GameEvent::TimeUp;
+            self.state.apply_event(&event);
+            self.timer.pause();
+            events.push(event);
+        }
+        self.time = self.time.max(time);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TIMER: Duration = Duration::from_secs(10);
+
+    /// Play the first legal action of each player in turn, one second apart
+    fn record(num_actions: usize) -> (Replay, GameState) {
+        let tileset = game_tileset().unwrap();
+        let mut replay = Replay::new(1, 2, 0, TIMER);
+        let mut state = replay.initial_state(&tileset).unwrap();
+        for idx in 0..num_actions {
+            let player = PlayerId((idx % 2) as u8);
+            let action = Pawn::ALL
+                .iter()
+                .flat_map(|pawn| state.legal_actions(player, *pawn))
+                .next()
+                .map(Action::BoardAction)
+                .unwrap();
+            state.apply_action(player, &action).unwrap();
+            replay.record(Duration::from_secs(idx as u64 + 1), player, action);
+        }
+        (replay, state)
+    }
+
+    #[test]
+    fn round_trip() {
+        crate::init_logging();
+        let (mut replay, state) = record(4);
+        replay.finish(Duration::from_secs(5));
+
+        let parsed = Replay::from_ron_str(&replay.to_ron_string()).unwrap();
+        assert_eq!(parsed, replay);
+
+        let tileset = game_tileset().unwrap();
+        let mut playback = ReplayPlayback::new(parsed, &tileset).unwrap();
+        assert!(playback.seek(Duration::from_secs(60)));
+        assert!(playback.is_finished());
+        assert_eq!(playback.time(), Duration::from_secs(5));
+        assert_eq!(playback.state(), &state);
+        assert_eq!(playback.timer().remaining(), TIMER - Duration::from_secs(5));
+    }
+
+    #[test]
+    fn scrub() {
+        crate::init_logging();
+        let (replay, _) = record(4);
+        let tileset = game_tileset().unwrap();
+        let mut playback = ReplayPlayback::new(replay, &tileset).unwrap();
+
+        assert!(!playback.seek(Duration::from_millis(500)));
+        assert_eq!(playback.num_applied(), 0);
+        assert!(playback.seek(Duration::from_secs(2)));
+        assert_eq!(playback.num_applied(), 2);
+        let at_two = playback.state().clone();
+
+        playback.seek(Duration::from_secs(4));
+        assert_eq!(playback.num_applied(), 4);
+        assert!(playback.seek(Duration::from_secs(2)));
+        assert_eq!(playback.num_applied(), 2);
+        assert_eq!(playback.state(), &at_two);
+    }
+
+    #[test]
+    fn time_up() {
+        crate::init_logging();
+        let (mut replay, _) = record(1);
+        replay.finish(TIMER * 2);
+        let tileset = game_tileset().unwrap();
+        let mut playback = ReplayPlayback::new(replay, &tileset).unwrap();
+
+        playback.seek(TIMER - Duration::from_millis(1));
+        assert_eq!(playback.state().phase(), GamePhase::Explore);
+        playback.seek(TIMER);
+        assert_eq!(playback.state().phase(), GamePhase::Lost);
+        assert!(!playback.timer().is_running());
+    }
+
+    #[test]
+    fn invalid() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let (mut replay, _) = record(2);
+        replay.actions[1].player = PlayerId(2);
+        assert_eq!(
+            ReplayPlayback::new(replay.clone(), &tileset).unwrap_err(),
+            ReplayError::Action {
+                idx: 1,
+                player: PlayerId(2),
+                err: ActionError::UnknownPlayer(PlayerId(2))
+            }
+        );
+
+        replay.version += 1;
+        assert_eq!(
+            Replay::from_ron_str(&replay.to_ron_string()),
+            Err(ReplayError::Version(Replay::VERSION + 1))
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{hash::Hasher, ops::RangeBounds};
+
+use serde::{Deserialize, Serialize};
+
+/// Source of all randomness in a game.
+///
+/// Serialized as its current state, so a saved or sent game continues with the same
+/// numbers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u64", into = "u64")]
+pub struct GameRng(fastrand::Rng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self(fastrand::Rng::with_seed(seed))
+    }
+
+    /// Seed for a game nobody asked to reproduce
+    pub fn random_seed() -> u64 {
+        fastrand::u64(..)
+    }
+
+    /// Seed from a phrase players can share, e.g. the date of a daily challenge.
+    ///
+    /// Uses 64 bit FNV-1a, so the same phrase gives the same game on every platform and
+    /// version.
+    pub fn seed_from_phrase(phrase: &str) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write(phrase.as_bytes());
+        hasher.finish()
+    }
+
+    /// Seed as written by [`format_seed`](Self::format_seed), or else a phrase
+    pub fn parse_seed(seed: &str) -> u64 {
+        match seed.len() {
+            1..=16 => {
+                u64::from_str_radix(seed, 16).unwrap_or_else(|_| Self::seed_from_phrase(seed))
+            }
+            _ => Self::seed_from_phrase(seed),
+        }
+    }
+
+    pub fn format_seed(seed: u64) -> String {
+        format!("{seed:016x}")
+    }
+
+    pub fn usize(&mut self, range: impl RangeBounds<usize>) -> usize {
+        self.0.usize(range)
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        self.0.u64(..)
+    }
+
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        self.0.shuffle(slice)
+    }
+}
+
+impl From<u64> for GameRng {
+    fn from(state: u64) -> Self {
+        Self::new(state)
+    }
+}
+
+impl From<GameRng> for u64 {
+    fn from(rng: GameRng) -> Self {
+        rng.0.get_seed()
+    }
+}
+
+/// 64 bit FNV-1a, which unlike `DefaultHasher` does not change between Rust releases
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        crate::init_logging();
+        let shuffled = |rng: &mut GameRng| {
+            let mut items: Vec<usize> = (0..20).collect();
+            rng.shuffle(&mut items);
+            items
+        };
+        let mut rng = GameRng::new(7);
+        let first = shuffled(&mut rng);
+        assert_eq!(first, shuffled(&mut GameRng::new(7)));
+        assert_ne!(first, shuffled(&mut GameRng::new(8)));
+
+        // continues where it left off after a round trip
+        let mut copy: GameRng = ron::from_str(&ron::to_string(&rng).unwrap()).unwrap();
+        assert_eq!(copy, rng);
+        assert_eq!(shuffled(&mut copy), shuffled(&mut rng));
+    }
+
+    #[test]
+    fn seeds() {
+        crate::init_logging();
+        assert_eq!(GameRng::seed_from_phrase(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(GameRng::seed_from_phrase("a"), 0xaf63_dc4c_8601_ec8c);
+
+        let seed = 0x0123_4567_89ab_cdef;
+        assert_eq!(GameRng::parse_seed(&GameRng::format_seed(seed)), seed);
+        assert_eq!(GameRng::parse_seed("ff"), 0xff);
+        assert_eq!(
+            GameRng::parse_seed("2026-10-18"),
+            GameRng::seed_from_phrase("2026-10-18")
+        );
+    }
+}
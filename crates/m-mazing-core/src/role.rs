@@ -2,6 +2,10 @@ use crate::prelude::*;
 
 use BoardAction::*;
 
+/// Largest supported number of players
+pub const MAX_PLAYERS: u8 = (ALLOWED_ACTIONS.len() - 1) as u8;
+
+/// Actions allowed for each player, indexed by `PlayerId`
 pub fn game_roles(num_players: u8) -> Option<&'static [&'static [BoardAction]]> {
     if num_players == 0 {
         return None;
@@ -36,4 +40,91 @@ const ALLOWED_ACTIONS: &[&[&[BoardAction]]] = &[
             Slide(CartesianDirection::Right),
         ],
     ],
+    // 3 players
+    &[
+        &[Escalator, Explore, Slide(CartesianDirection::Down)],
+        &[Warp, Slide(CartesianDirection::Left)],
+        &[
+            Slide(CartesianDirection::Up),
+            Slide(CartesianDirection::Right),
+        ],
+    ],
+    // 4 players
+    &[
+        &[Explore, Slide(CartesianDirection::Down)],
+        &[Escalator, Slide(CartesianDirection::Left)],
+        &[Warp, Slide(CartesianDirection::Up)],
+        &[Slide(CartesianDirection::Right)],
+    ],
+    // 5 players
+    &[
+        &[Explore, Slide(CartesianDirection::Down)],
+        &[Escalator, Slide(CartesianDirection::Left)],
+        &[Slide(CartesianDirection::Up)],
+        &[Slide(CartesianDirection::Right)],
+        &[Warp],
+    ],
+    // 6 players
+    &[
+        &[Slide(CartesianDirection::Down)],
+        &[Slide(CartesianDirection::Left)],
+        &[Slide(CartesianDirection::Up)],
+        &[Slide(CartesianDirection::Right)],
+        &[Explore],
+        &[Escalator, Warp],
+    ],
+    // 7 players
+    &[
+        &[Slide(CartesianDirection::Down)],
+        &[Slide(CartesianDirection::Left)],
+        &[Slide(CartesianDirection::Up)],
+        &[Slide(CartesianDirection::Right)],
+        &[Explore],
+        &[Escalator],
+        &[Warp],
+    ],
+    // 8 players; more players than actions, so exploring is shared
+    &[
+        &[Slide(CartesianDirection::Down)],
+        &[Slide(CartesianDirection::Left)],
+        &[Slide(CartesianDirection::Up)],
+        &[Slide(CartesianDirection::Right)],
+        &[Explore],
+        &[Escalator],
+        &[Warp],
+        &[Explore],
+    ],
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn all_actions_covered() {
+        crate::init_logging();
+        let all_actions = [
+            Warp,
+            Explore,
+            Escalator,
+            Slide(CartesianDirection::Left),
+            Slide(CartesianDirection::Up),
+            Slide(CartesianDirection::Down),
+            Slide(CartesianDirection::Right),
+        ];
+
+        assert!(game_roles(0).is_none());
+        assert!(game_roles(MAX_PLAYERS + 1).is_none());
+        for num_players in 1..=MAX_PLAYERS {
+            let roles = game_roles(num_players).unwrap();
+            assert_eq!(roles.len(), num_players as usize);
+            assert!(roles.iter().all(|role| !role.is_empty()));
+            for action in all_actions.iter() {
+                assert!(
+                    roles.iter().any(|role| role.contains(action)),
+                    "{action:?} missing for {num_players} players"
+                );
+            }
+        }
+    }
+}
@@ -1,11 +1,40 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
+#[derive(Clone, Debug)]
 pub struct Scenario {
     pub escape: ScenarioEscape,
-    pub start_tile: Tile,
+    pub start_tile_name: String,
     pub explore_tile_names: Vec<String>,
 }
 
+impl Scenario {
+    /// Number of scenarios from the rule book that are supported, starting at 1
+    pub const NUM_BUILTIN: u8 = 2;
+
+    /// First scenario from the rule book: tiles 2 through 9
+    pub fn first() -> Self {
+        Self::builtin(1).expect("first scenario")
+    }
+
+    /// Scenario `number` from the rule book
+    pub fn builtin(number: u8) -> Option<Self> {
+        let (escape, last_tile) = match number {
+            1 => (ScenarioEscape::PurpleOnly, 9),
+            // tiles 10 through 12 add the other colors' exits
+            2 => (ScenarioEscape::EachColor, 12),
+            _ => return None,
+        };
+        Some(Self {
+            escape,
+            start_tile_name: "1a".to_string(),
+            explore_tile_names: (2..=last_tile).map(|idx| idx.to_string()).collect(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScenarioEscape {
     /// All pawns must escape via the the Purple exit
     PurpleOnly,
@@ -13,3 +42,53 @@ pub enum ScenarioEscape {
     /// Each pawn must escape through its own color
     EachColor,
 }
+
+impl ScenarioEscape {
+    /// Whether `pawn` escapes when standing on `cell`
+    pub fn is_exit(self, pawn: Pawn, cell: TileCell) -> bool {
+        match (self, cell) {
+            (Self::PurpleOnly, TileCell::FinalExit(exit)) => exit == Pawn::Purple,
+            (Self::EachColor, TileCell::FinalExit(exit)) => exit == pawn,
+            _ => false,
+        }
+    }
+}
+
+/// Game of `num_players` on the start tile plus `explore`, in draw order
+#[cfg(test)]
+pub(crate) fn small_game(num_players: u8, escape: ScenarioEscape, explore: &[&str]) -> GameState {
+    let scenario = Scenario {
+        escape,
+        start_tile_name: "1a".to_string(),
+        explore_tile_names: explore.iter().map(|name| name.to_string()).collect(),
+    };
+    let tileset = game_tileset().unwrap();
+    GameState::new(num_players, &scenario, &tileset, 0).unwrap()
+}
+
+/// Game with the loot already stolen that is won by exploring tile 2, which has the
+/// purple exit
+#[cfg(test)]
+pub(crate) fn escape_game(num_players: u8) -> GameState {
+    let mut state = small_game(num_players, ScenarioEscape::PurpleOnly, &["2"]);
+    state.apply_event(&GameEvent::LootStolen);
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        assert!(Scenario::builtin(0).is_none());
+        assert!(Scenario::builtin(Scenario::NUM_BUILTIN + 1).is_none());
+        for number in 1..=Scenario::NUM_BUILTIN {
+            let scenario = Scenario::builtin(number).unwrap();
+            let game = GameState::new(1, &scenario, &tileset, 0).unwrap();
+            assert_eq!(game.pile_len(), scenario.explore_tile_names.len());
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::prelude::*;
+
+/// Why a simulated game was lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LossReason {
+    /// Time ran out before every loot was explored
+    LootNotFound,
+
+    /// Time ran out with every loot on the board, but not every pawn on it
+    LootNotReached,
+
+    /// Time ran out after the loot was stolen
+    NotEscaped,
+
+    /// The game went on past the limits of the simulation
+    GaveUp,
+}
+
+impl LossReason {
+    /// Reason a game in `state` is lost if time runs out now
+    fn of(state: &GameState) -> Self {
+        if state.phase() == GamePhase::Escape {
+            return Self::NotEscaped;
+        }
+        let board = state.board();
+        let all_found = Pawn::ALL.iter().all(|pawn| {
+            board
+                .tiles()
+                .iter()
+                .flat_map(|placed| placed.board_coords())
+                .any(|coord| board.cell(coord) == Some(TileCell::Loot(*pawn)))
+        });
+        if all_found {
+            Self::LootNotReached
+        } else {
+            Self::LootNotFound
+        }
+    }
+}
+
+/// How a simulated game went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimResult {
+    /// `None` if the game was won
+    pub loss: Option<LossReason>,
+
+    /// Sand left in the timer when the game ended
+    pub time_left: Duration,
+
+    /// Time the game took
+    pub time_played: Duration,
+
+    pub timer_flips: u32,
+    pub tiles_explored: usize,
+    pub actions: usize,
+}
+
+impl SimResult {
+    pub fn is_won(&self) -> bool {
+        self.loss.is_none()
+    }
+}
+
+/// Plays games with a bot in every seat, without rendering and faster than real time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Simulation {
+    pub difficulty: BotDifficulty,
+
+    /// `None` to play without the sand timer, e.g. to find out whether the bots can win
+    /// at all
+    pub timer_duration: Option<Duration>,
+
+    /// Simulated time between bot updates
+    pub step: Duration,
+
+    /// Give up on a game after this many actions or this much time, which is what ends
+    /// games without a timer that the bots cannot win
+    pub max_actions: Option<usize>,
+    pub max_time: Option<Duration>,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            difficulty: BotDifficulty::default(),
+            timer_duration: Some(SandTimer::DEFAULT_DURATION),
+            step: Duration::from_millis(100),
+            max_actions: None,
+            max_time: None,
+        }
+    }
+}
+
+impl Simulation {
+    /// Play `state`, which is not over yet, to the end; the timer or the limits end any
+    /// game the bots cannot win
+    pub fn play(&self, mut state: GameState) -> SimResult {
+        let tiles_before = state.board().tiles().len();
+        let mut bots: Vec<Bot> = (0..state.num_players())
+            .map(|player| Bot::new(PlayerId(player), self.difficulty))
+            .collect();
+        let mut timer = SandTimer::new(self.timer_duration.unwrap_or_default());
+        if self.timer_duration.is_some() {
+            timer.start();
+        }
+
+        let mut loss = None;
+        let mut time_played = Duration::ZERO;
+        let mut timer_flips = 0;
+        let mut actions = 0;
+        while !state.phase().is_over() {
+            let over_limit = self.max_actions.is_some_and(|max| actions >= max)
+                || self.max_time.is_some_and(|max| time_played >= max);
+            if over_limit {
+                loss = Some(LossReason::GaveUp);
+                break;
+            }
+            time_played += self.step;
+            if timer.tick(self.step) {
+                loss = Some(LossReason::of(&state));
+                state.apply_event(&GameEvent::TimeUp);
+                break;
+            }
+
+            let chosen: Vec<_> = bots
+                .iter_mut()
+                .filter_map(|bot| Some((bot.player(), bot.tick(&state, self.step)?)))
+                .collect();
+            // bots that acted on the same state may get in each other's way
+            for (player, action) in chosen {
+                let Ok(events) = state.apply_action(player, &action) else {
+                    continue;
+                };
+                actions += 1;
+                if events
+                    .iter()
+                    .any(|event| matches!(event, GameEvent::TimerFlipped(_)))
+                {
+                    timer.flip();
+                    timer_flips += 1;
+                }
+            }
+        }
+
+        SimResult {
+            loss,
+            time_left: timer.remaining(),
+            time_played,
+            timer_flips,
+            tiles_explored: state.board().tiles().len() - tiles_before,
+            actions,
+        }
+    }
+}
+
+/// Totals over many simulated games
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimStats {
+    pub games: usize,
+    pub wins: usize,
+
+    /// Summed over won games only, as lost games have no time left
+    pub time_left: Duration,
+    pub timer_flips: u64,
+    pub tiles_explored: u64,
+    pub losses: HashMap<LossReason, usize>,
+}
+
+impl SimStats {
+    pub fn add(&mut self, result: &SimResult) {
+        self.games += 1;
+        match result.loss {
+            None => {
+                self.wins += 1;
+                self.time_left += result.time_left;
+            }
+            Some(reason) => *self.losses.entry(reason).or_default() += 1,
+        }
+        self.timer_flips += u64::from(result.timer_flips);
+        self.tiles_explored += result.tiles_explored as u64;
+    }
+
+    /// Combine with the totals of other games, e.g. from another thread
+    pub fn merge(&mut self, other: &SimStats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.time_left += other.time_left;
+        self.timer_flips += other.timer_flips;
+        self.tiles_explored += other.tiles_explored;
+        for (reason, count) in other.losses.iter() {
+            *self.losses.entry(*reason).or_default() += count;
+        }
+    }
+
+    /// Fraction of games won, from 0 to 1
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.wins as f64, self.games)
+    }
+
+    /// Average sand left when a game is won
+    pub fn avg_time_left(&self) -> Duration {
+        self.time_left
+            .checked_div(self.wins as u32)
+            .unwrap_or_default()
+    }
+
+    pub fn avg_timer_flips(&self) -> f64 {
+        ratio(self.timer_flips as f64, self.games)
+    }
+
+    pub fn avg_tiles_explored(&self) -> f64 {
+        ratio(self.tiles_explored as f64, self.games)
+    }
+
+    /// Reason most games were lost for, with the number of those games
+    pub fn most_common_loss(&self) -> Option<(LossReason, usize)> {
+        self.losses
+            .iter()
+            .map(|(reason, count)| (*reason, *count))
+            // the first reason wins a tie, so the result does not depend on hash order
+            .max_by_key(|(reason, count)| (*count, std::cmp::Reverse(*reason)))
+    }
+}
+
+fn ratio(total: f64, games: usize) -> f64 {
+    if games == 0 {
+        0.0
+    } else {
+        total / games as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn play() {
+        crate::init_logging();
+        let state = crate::scenario::escape_game(2);
+
+        let sim = Simulation::default();
+        let won = sim.play(state.clone());
+        assert!(won.is_won(), "{won:?}");
+        assert!(won.actions > 0);
+        assert_eq!(won.tiles_explored, 1);
+        assert!(won.time_left > Duration::ZERO);
+        assert_eq!(
+            won.time_played + won.time_left,
+            SandTimer::DEFAULT_DURATION,
+            "no timer flip on tile 2"
+        );
+
+        let rushed = Simulation {
+            timer_duration: Some(Duration::from_secs(1)),
+            ..sim
+        }
+        .play(state);
+        assert_eq!(rushed.loss, Some(LossReason::NotEscaped));
+        assert_eq!(rushed.time_left, Duration::ZERO);
+
+        let mut stats = SimStats::default();
+        stats.add(&won);
+        stats.add(&rushed);
+        stats.add(&rushed);
+        assert_eq!(stats.win_rate(), 1.0 / 3.0);
+        assert_eq!(stats.avg_time_left(), won.time_left);
+        assert_eq!(stats.most_common_loss(), Some((LossReason::NotEscaped, 2)));
+
+        let mut merged = SimStats::default();
+        merged.merge(&stats);
+        merged.merge(&SimStats::default());
+        assert_eq!(merged, stats);
+    }
+
+    #[test]
+    fn loss_reason() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let state = GameState::new(1, &Scenario::first(), &tileset, 0).unwrap();
+        // the start tile has no loot
+        assert_eq!(LossReason::of(&state), LossReason::LootNotFound);
+
+        // too short to react to anything
+        let result = Simulation {
+            timer_duration: Some(Duration::from_secs(1)),
+            ..Default::default()
+        }
+        .play(state.clone());
+        assert_eq!(result.loss, Some(LossReason::LootNotFound));
+        assert_eq!(result.actions, 0);
+
+        // without a timer only the limits end the game
+        let untimed = Simulation {
+            timer_duration: None,
+            max_actions: Some(3),
+            ..Default::default()
+        };
+        let result = untimed.play(state);
+        assert_eq!(result.loss, Some(LossReason::GaveUp));
+        assert_eq!(result.actions, 3);
+        let won = Simulation {
+            max_actions: Some(100),
+            ..untimed
+        }
+        .play(crate::scenario::escape_game(1));
+        assert!(won.is_won(), "{won:?}");
+    }
+}
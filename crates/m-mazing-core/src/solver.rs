@@ -0,0 +1,697 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, VecDeque},
+};
+
+use thiserror::Error;
+
+use crate::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    #[error("Game is already over")]
+    GameOver,
+
+    #[error("No sequence of actions wins the game")]
+    Unsolvable,
+
+    #[error("Gave up after expanding {0} positions")]
+    LimitReached(usize),
+}
+
+type Pawns = [Option<BoardCoord>; Pawn::ALL.len()];
+
+/// Search position; the board is shared by all positions with the same explored tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position {
+    pawns: Pawns,
+    phase: GamePhase,
+    board: usize,
+}
+
+/// State with one set of explored tiles whose pawns are moved around during a search,
+/// with a `BitBoard` of it to move them quickly
+pub(crate) struct SearchBoard {
+    pub state: GameState,
+
+    /// `None` if the board is too large
+    pub bits: Option<BitBoard>,
+}
+
+impl SearchBoard {
+    pub fn new(state: GameState) -> Self {
+        let bits = BitBoard::new(state.board());
+        Self { state, bits }
+    }
+
+    /// Every way to perform `actions` with the pawns at `pawns` in `phase`; the state is
+    /// left there for [`GameState::legal_pawn_action_events`]
+    pub fn pawn_actions(
+        &mut self,
+        pawns: Pawns,
+        phase: GamePhase,
+        actions: &[BoardAction],
+    ) -> Vec<PawnAction> {
+        self.state.set_pawns(pawns, phase);
+        let occupied: Vec<BoardCoord> = pawns.iter().flatten().copied().collect();
+        let mask = self.bits.as_ref().map(|bits| bits.occupied(&occupied));
+        let bits = self.bits.as_ref().zip(mask.as_ref());
+        let mut pawn_actions = Vec::new();
+        for pawn in Pawn::ALL {
+            for action in actions {
+                pawn_actions.extend(self.state.pawn_actions_with(bits, pawn, *action));
+            }
+        }
+        pawn_actions
+    }
+}
+
+/// Finds the fewest actions that win a game, with the whole team acting as one player.
+///
+/// Tiles are explored in pile order, so the solution holds for one shuffle only. The
+/// sand timer is ignored.
+///
+/// The search is exact, and grows quickly with the distance to the win: escapes and
+/// small custom scenarios solve in well under a second, while whole rule book games
+/// hit the limit unless the search is `staged`.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    /// Give up after expanding this many positions, in each stage
+    pub max_positions: usize,
+
+    /// Split the game into stages and search for the fewest actions of each in turn,
+    /// instead of for the fewest overall: explore one tile at a time, the closest one,
+    /// until the goals of the phase are on the board, then bring one pawn at a time to
+    /// its loot, and after the theft do the same with the exits.
+    ///
+    /// Each stage is much shorter than the whole game, which keeps most rule book games
+    /// within the limit. The solution usually takes more actions than needed, and in
+    /// rare games a stage leaves the pawns where the next one is unsolvable.
+    pub staged: bool,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self {
+            max_positions: 200_000,
+            staged: false,
+        }
+    }
+}
+
+impl Solver {
+    /// Sequence of actions from `state` to `GamePhase::Won`, the shortest one unless
+    /// `staged`
+    pub fn solve(&self, state: &GameState) -> Result<Vec<PawnAction>, SolveError> {
+        if state.phase().is_over() {
+            return Err(SolveError::GameOver);
+        }
+        if !self.staged {
+            return Search::new(state, SearchGoal::Win).run(self.max_positions);
+        }
+
+        let mut state = state.clone();
+        let mut actions = Vec::new();
+        while state.phase() != GamePhase::Won {
+            let phase = state.phase();
+            let explores = Pawn::ALL
+                .iter()
+                .filter(|pawn| state.pawn_coord(**pawn).is_some())
+                .map(|pawn| pile_depth(&state, |cell| is_goal(&state, *pawn, phase, cell)))
+                .try_fold(0, |max, depth| Some(max.max(depth?)))
+                .ok_or(SolveError::Unsolvable)?;
+            let goal = if explores > 0 {
+                SearchGoal::Explore
+            } else {
+                SearchGoal::Progress
+            };
+            let stage = Search::new(&state, goal).run(self.max_positions)?;
+            for action in stage.iter() {
+                let events = state.pawn_action_events(action).expect("solution is legal");
+                for event in events.iter() {
+                    state.apply_event(event);
+                }
+            }
+            actions.extend(stage);
+        }
+        Ok(actions)
+    }
+}
+
+/// Where a search ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchGoal {
+    Win,
+
+    /// One more pawn on its loot or escaped, or the phase over
+    Progress,
+
+    /// Any tile explored
+    Explore,
+}
+
+/// Whether `cell` is a goal of `pawn` in `phase`
+fn is_goal(state: &GameState, pawn: Pawn, phase: GamePhase, cell: TileCell) -> bool {
+    match phase {
+        GamePhase::Explore => cell == TileCell::Loot(pawn),
+        GamePhase::Escape => state.escape().is_exit(pawn, cell),
+        GamePhase::Won | GamePhase::Lost => false,
+    }
+}
+
+/// Node of the search tree; `action` leads from `parent` to this node
+struct Node {
+    position: Position,
+    parent: usize,
+    action: Option<PawnAction>,
+}
+
+/// A* search over positions.
+///
+/// See [`PawnBound`] for the estimate of the remaining actions.
+struct Search {
+    goal: SearchGoal,
+
+    /// Phase and [`Search::progress`] of the start
+    start: (GamePhase, usize),
+
+    /// Each set of explored tiles, indexed by `Position::board`
+    boards: Vec<SearchBoard>,
+    board_ids: HashMap<Vec<(BoardCoord, Option<CartesianDirection>)>, usize>,
+    bounds: HashMap<(usize, Pawn, GamePhase), Option<PawnBound>>,
+    nodes: Vec<Node>,
+    best: HashMap<Position, u32>,
+
+    /// Node indices ordered by estimated total, preferring deeper nodes
+    open: BinaryHeap<Reverse<(u32, Reverse<u32>, usize)>>,
+}
+
+impl Search {
+    fn new(state: &GameState, goal: SearchGoal) -> Self {
+        let mut search = Self {
+            goal,
+            start: (state.phase(), 0),
+            boards: Vec::new(),
+            board_ids: HashMap::new(),
+            bounds: HashMap::new(),
+            nodes: Vec::new(),
+            best: HashMap::new(),
+            open: BinaryHeap::new(),
+        };
+        let board = search.board_id(state);
+        let start = Position {
+            pawns: state.pawns(),
+            phase: state.phase(),
+            board,
+        };
+        search.start = (start.phase, search.progress(&start));
+        search.push(start, 0, None, 0);
+        search
+    }
+
+    /// Pawns on their loot, or escaped
+    fn progress(&self, position: &Position) -> usize {
+        let board = self.boards[position.board].state.board();
+        Pawn::ALL
+            .iter()
+            .filter(|pawn| match (position.phase, position.pawns[pawn.idx()]) {
+                (GamePhase::Explore, Some(coord)) => {
+                    board.cell(coord) == Some(TileCell::Loot(**pawn))
+                }
+                (_, coord) => coord.is_none(),
+            })
+            .count()
+    }
+
+    fn board_key(state: &GameState) -> Vec<(BoardCoord, Option<CartesianDirection>)> {
+        state
+            .board()
+            .tiles()
+            .iter()
+            .map(|placed| (placed.origin, placed.tile.entrance().map(|(_, dir)| dir)))
+            .collect()
+    }
+
+    fn board_id(&mut self, state: &GameState) -> usize {
+        let key = Self::board_key(state);
+        if let Some(id) = self.board_ids.get(&key) {
+            return *id;
+        }
+        let id = self.boards.len();
+        self.boards.push(SearchBoard::new(state.clone()));
+        self.board_ids.insert(key, id);
+        id
+    }
+
+    fn push(&mut self, position: Position, cost: u32, action: Option<PawnAction>, parent: usize) {
+        if self.best.get(&position).is_some_and(|best| *best <= cost) {
+            return;
+        }
+        let Some(estimate) = self.estimate(&position) else {
+            return;
+        };
+        self.best.insert(position, cost);
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            position,
+            parent,
+            action,
+        });
+        self.open
+            .push(Reverse((cost + estimate, Reverse(cost), node)));
+    }
+
+    fn run(mut self, max_positions: usize) -> Result<Vec<PawnAction>, SolveError> {
+        let mut expanded = 0;
+        while let Some(Reverse((_, Reverse(cost), node))) = self.open.pop() {
+            let position = self.nodes[node].position;
+            if self.best[&position] < cost {
+                continue;
+            }
+            if self.reached(&position) {
+                return Ok(self.path(node));
+            }
+            if expanded == max_positions {
+                return Err(SolveError::LimitReached(expanded));
+            }
+            expanded += 1;
+            self.expand(position, cost, node);
+        }
+        Err(SolveError::Unsolvable)
+    }
+
+    fn expand(&mut self, position: Position, cost: u32, node: usize) {
+        let board = &mut self.boards[position.board];
+        let successors: Vec<_> = board
+            .pawn_actions(position.pawns, position.phase, &BoardAction::ALL)
+            .into_iter()
+            .filter_map(|pawn_action| {
+                let events = board.state.legal_pawn_action_events(&pawn_action).ok()?;
+                Some((pawn_action, events))
+            })
+            .collect();
+        for (pawn_action, events) in successors {
+            let next = self.next_position(position, &events);
+            self.push(next, cost + 1, Some(pawn_action), node);
+        }
+    }
+
+    /// Position after `events` happened at `position`
+    fn next_position(&mut self, position: Position, events: &[GameEvent]) -> Position {
+        let mut next = position;
+        for event in events {
+            match event {
+                GameEvent::PawnMoved { pawn, to, .. } => next.pawns[pawn.idx()] = Some(*to),
+                GameEvent::PawnEscaped(pawn) => next.pawns[pawn.idx()] = None,
+                GameEvent::LootStolen => next.phase = GamePhase::Escape,
+                GameEvent::GameWon => next.phase = GamePhase::Won,
+                GameEvent::TileExplored(_) => {
+                    let mut explored = self.boards[position.board].state.clone();
+                    explored.apply_event(event);
+                    next.board = self.board_id(&explored);
+                }
+                GameEvent::TimerFlipped(_)
+                | GameEvent::TimeUp
+                | GameEvent::DoSomething { .. }
+                | GameEvent::DoSomethingAnswered(_) => (),
+            }
+        }
+        next
+    }
+
+    fn path(&self, mut node: usize) -> Vec<PawnAction> {
+        let mut actions = Vec::new();
+        while let Some(action) = self.nodes[node].action {
+            actions.push(action);
+            node = self.nodes[node].parent;
+        }
+        actions.reverse();
+        actions
+    }
+
+    fn reached(&self, position: &Position) -> bool {
+        match self.goal {
+            SearchGoal::Win => position.phase == GamePhase::Won,
+            SearchGoal::Progress => {
+                let (phase, progress) = self.start;
+                position.phase != phase || self.progress(position) > progress
+            }
+            // the start board is the first one
+            SearchGoal::Explore => position.board != 0,
+        }
+    }
+
+    /// Lower bound of the actions left to reach the goal, or `None` if that is impossible
+    fn estimate(&mut self, position: &Position) -> Option<u32> {
+        if self.reached(position) {
+            return Some(0);
+        }
+        if self.goal == SearchGoal::Explore {
+            // the closest pawn walks to an explore wall and explores
+            let mut closest: Option<u32> = None;
+            for pawn in Pawn::ALL {
+                let Some(coord) = position.pawns[pawn.idx()] else {
+                    continue;
+                };
+                if let Some(moves) = self
+                    .bound(position.board, pawn, position.phase)?
+                    .moves(coord)
+                {
+                    closest = Some(closest.map_or(moves, |closest| closest.min(moves)));
+                }
+            }
+            return closest;
+        }
+
+        // for progress this is the rest of the phase, which is more than needed, but
+        // keeps the pawns that are done from wandering off
+        let mut moves = 0;
+        let mut explores = 0;
+        for pawn in Pawn::ALL {
+            let Some(coord) = position.pawns[pawn.idx()] else {
+                continue;
+            };
+            let bound = self.bound(position.board, pawn, position.phase)?;
+            moves += bound.moves(coord)?;
+            explores = explores.max(bound.explores);
+        }
+        Some(moves + explores)
+    }
+
+    /// `None` if `pawn` cannot reach the goal on `board`
+    fn bound(&mut self, board: usize, pawn: Pawn, phase: GamePhase) -> Option<&PawnBound> {
+        let search_board = &self.boards[board];
+        let goal = self.goal;
+        self.bounds
+            .entry((board, pawn, phase))
+            .or_insert_with(|| PawnBound::new(search_board, pawn, phase, goal))
+            .as_ref()
+    }
+}
+
+/// Lower bound of the actions `pawn` needs to reach the goal of the search, ignoring
+/// other pawns.
+///
+/// Every action either moves one pawn or explores a tile, so the moves of all pawns plus
+/// the explores any of them needs never overestimate the actions left.
+struct PawnBound {
+    /// Moves to the goal of the current phase on the explored board
+    distances: HashMap<BoardCoord, u32>,
+    through_pile: ThroughPile,
+
+    /// Moves from the goal of the phase to the goal of the search; when searching for
+    /// the win, the pawn still has to get from its loot to an exit after the theft
+    remaining: u32,
+
+    /// Tiles to explore before the goals of the pawn are on the board
+    explores: u32,
+}
+
+/// Lower bound of the moves to the goal through tiles that are yet to be explored
+enum ThroughPile {
+    /// Pile is empty, or no explore walls are left
+    Impossible,
+
+    /// Same from every cell
+    Constant(u32),
+
+    /// Moves to the closest explore wall plus the step through it
+    Frontier(HashMap<BoardCoord, u32>),
+}
+
+impl PawnBound {
+    fn new(
+        search_board: &SearchBoard,
+        pawn: Pawn,
+        phase: GamePhase,
+        goal: SearchGoal,
+    ) -> Option<Self> {
+        let SearchBoard { state, bits } = search_board;
+        let bits = bits.as_ref();
+        let board = state.board();
+        if goal == SearchGoal::Explore {
+            // explore walls where the next tile does not fit are no way out
+            let next = state.pile().last();
+            let distances = goal_distances(state, bits, pawn, phase, |coord| {
+                next.is_some_and(|(name, tile)| {
+                    board
+                        .explore_destinations(coord, pawn)
+                        .into_iter()
+                        .any(|dest| {
+                            board
+                                .tile_placement(coord, dest, name.clone(), tile.clone())
+                                .is_ok()
+                        })
+                })
+            });
+            return Some(Self {
+                distances,
+                through_pile: ThroughPile::Impossible,
+                remaining: 1,
+                explores: 0,
+            });
+        }
+
+        let is_goal = |cell: TileCell| is_goal(state, pawn, phase, cell);
+        let distances = goal_distances(state, bits, pawn, phase, |coord| {
+            board.cell(coord).is_some_and(is_goal)
+        });
+
+        let mut explores = pile_depth(state, is_goal)?;
+        let mut remaining = 0;
+        if phase == GamePhase::Explore && goal == SearchGoal::Win {
+            let escape = Self::new(search_board, pawn, GamePhase::Escape, goal)?;
+            explores = explores.max(escape.explores);
+            let loot = distances
+                .iter()
+                .find(|(_, distance)| **distance == 0)
+                .map(|(coord, _)| *coord);
+            if let Some(loot) = loot {
+                remaining = escape.moves(loot)?;
+            }
+        }
+
+        // paths through tiles that are yet to be explored enter the board through an
+        // explore wall
+        let frontier: BoardCellSet = board
+            .tiles()
+            .iter()
+            .flat_map(|placed| placed.board_coords())
+            .filter(|coord| {
+                CartesianDirection::ALL_DIRECTIONS.iter().any(|dir| {
+                    matches!(board.wall(*coord, *dir), Some(WallState::Explore(_)))
+                        && !board.contains(coord.neighbor(*dir))
+                })
+            })
+            .collect();
+        let through_pile = if state.pile_len() == 0 || frontier.is_empty() {
+            ThroughPile::Impossible
+        } else if !distances.is_empty() {
+            frontier
+                .iter()
+                .filter_map(|coord| distances.get(&coord))
+                .min()
+                .map_or(ThroughPile::Impossible, |distance| {
+                    ThroughPile::Constant(*distance)
+                })
+        } else if phase == GamePhase::Explore {
+            // could warp straight onto the tile with the goal
+            ThroughPile::Constant(1)
+        } else {
+            let mut to_frontier =
+                goal_distances(state, bits, pawn, phase, |coord| frontier.contains(coord));
+            for distance in to_frontier.values_mut() {
+                *distance += 1;
+            }
+            ThroughPile::Frontier(to_frontier)
+        };
+
+        Some(Self {
+            distances,
+            through_pile,
+            remaining,
+            explores,
+        })
+    }
+
+    /// Moves from `coord`, or `None` if the pawn cannot win from there
+    fn moves(&self, coord: BoardCoord) -> Option<u32> {
+        let through_pile = match &self.through_pile {
+            ThroughPile::Impossible => None,
+            ThroughPile::Constant(distance) => Some(*distance),
+            ThroughPile::Frontier(distances) => distances.get(&coord).copied(),
+        };
+        let distance = match (self.distances.get(&coord).copied(), through_pile) {
+            (Some(distance), Some(through_pile)) => distance.min(through_pile),
+            (distance, through_pile) => distance.or(through_pile)?,
+        };
+        Some(distance + self.remaining)
+    }
+}
+
+/// Tiles to draw until a cell matching `is_goal` is on the board, or `None` if there is
+/// no such cell
+fn pile_depth(state: &GameState, is_goal: impl Fn(TileCell) -> bool) -> Option<u32> {
+    let board = state.board();
+    let on_board = board
+        .tiles()
+        .iter()
+        .any(|placed| placed.tile.cells_iter().any(|cell| is_goal(*cell)));
+    if on_board {
+        return Some(0);
+    }
+    state
+        .pile()
+        .iter()
+        .rev()
+        .position(|(_, tile)| tile.cells_iter().any(|cell| is_goal(*cell)))
+        .map(|idx| idx as u32 + 1)
+}
+
+/// Backwards breadth first search from cells matching `is_goal`, on `bits` if the board
+/// fits in a `BitBoard`.
+///
+/// Slides may stop on any cell, as another pawn could block them there, which makes
+/// slides and escalators symmetric. Warps are one way: a warp cell can be reached from
+/// anywhere.
+pub(crate) fn goal_distances(
+    state: &GameState,
+    bits: Option<&BitBoard>,
+    pawn: Pawn,
+    phase: GamePhase,
+    is_goal: impl Fn(BoardCoord) -> bool,
+) -> HashMap<BoardCoord, u32> {
+    let board = state.board();
+    let coords: Vec<BoardCoord> = board
+        .tiles()
+        .iter()
+        .flat_map(|placed| placed.board_coords())
+        .collect();
+
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    for coord in coords.iter().copied() {
+        if is_goal(coord) {
+            distances.insert(coord, 0);
+            queue.push_back(coord);
+        }
+    }
+
+    // the walk over the board is only needed for boards too large for bits
+    let nobody = bits.map(|bits| bits.occupied(&[]));
+    let mut warped = false;
+    while let Some(coord) = queue.pop_front() {
+        let next = distances[&coord] + 1;
+        let mut sources = Vec::new();
+        match (bits, &nobody) {
+            (Some(bits), Some(nobody)) => {
+                for dir in CartesianDirection::ALL_DIRECTIONS {
+                    sources.extend(bits.slide_destinations(coord, dir, pawn, nobody));
+                }
+                sources.extend(bits.escalator_destinations(coord, nobody));
+            }
+            _ => {
+                for dir in CartesianDirection::ALL_DIRECTIONS {
+                    sources.extend(board.slide_destinations(coord, dir, pawn, &[]));
+                }
+                sources.extend(board.escalator_destinations(coord, &[]));
+            }
+        }
+        let is_warp = board.cell(coord) == Some(TileCell::Warp(pawn));
+        if is_warp && phase == GamePhase::Explore && !warped {
+            warped = true;
+            sources.extend(coords.iter().copied());
+        }
+        for source in sources {
+            if let Entry::Vacant(entry) = distances.entry(source) {
+                entry.insert(next);
+                queue.push_back(source);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Apply `actions` as the only player, who may perform every action
+    fn play(mut state: GameState, actions: &[PawnAction]) -> GameState {
+        for action in actions {
+            state
+                .apply_action(PlayerId(0), &Action::BoardAction(*action))
+                .unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn escape() {
+        crate::init_logging();
+        let state = crate::scenario::escape_game(1);
+
+        let actions = Solver::default().solve(&state).unwrap();
+        assert_eq!(
+            actions
+                .iter()
+                .filter(|action| action.action == BoardAction::Explore)
+                .count(),
+            1
+        );
+        assert_eq!(play(state.clone(), &actions).phase(), GamePhase::Won);
+        assert_eq!(actions.len(), 24);
+
+        // tile 3 has no exit
+        let mut state = crate::scenario::small_game(1, ScenarioEscape::PurpleOnly, &["3"]);
+        state.apply_event(&GameEvent::LootStolen);
+        assert_eq!(Solver::default().solve(&state), Err(SolveError::Unsolvable));
+    }
+
+    #[test]
+    fn staged() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let solver = Solver {
+            staged: true,
+            ..Default::default()
+        };
+        for number in 1..=Scenario::NUM_BUILTIN {
+            let state =
+                GameState::new(1, &Scenario::builtin(number).unwrap(), &tileset, 0).unwrap();
+            let actions = solver.solve(&state).unwrap();
+            let explores = actions
+                .iter()
+                .filter(|action| action.action == BoardAction::Explore)
+                .count();
+            assert!(explores > 0, "scenario {number}");
+            assert_eq!(
+                play(state, &actions).phase(),
+                GamePhase::Won,
+                "scenario {number}"
+            );
+        }
+
+        // an escape is one stage per pawn
+        let state = crate::scenario::escape_game(1);
+        let actions = solver.solve(&state).unwrap();
+        assert!(actions.len() >= 24);
+        assert_eq!(play(state, &actions).phase(), GamePhase::Won);
+    }
+
+    #[test]
+    fn limit() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let state = GameState::new(1, &Scenario::first(), &tileset, 0).unwrap();
+        let solver = Solver {
+            max_positions: 10,
+            ..Default::default()
+        };
+        assert_eq!(solver.solve(&state), Err(SolveError::LimitReached(10)));
+
+        let mut over = state.clone();
+        over.apply_event(&GameEvent::TimeUp);
+        assert_eq!(Solver::default().solve(&over), Err(SolveError::GameOver));
+    }
+}
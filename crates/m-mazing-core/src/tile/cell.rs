@@ -1,7 +1,8 @@
 use super::TileTokenParse;
 use crate::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum TileCell {
     /// Pawn walk freely through
     Empty,
@@ -86,7 +87,7 @@ impl TileTokenParse for TileCell {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum CellItemAvailability {
     Available,
     Used,
@@ -0,0 +1,350 @@
+use std::{
+    collections::BTreeMap,
+    ops::{BitAnd, BitOr},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Set of cells in a `Tile`, one bit per cell.
+///
+/// Iterates row by row, like [`TileGridCoord::all`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CellSet(u16);
+
+impl CellSet {
+    pub const EMPTY: Self = Self(0);
+    pub const ALL: Self = Self(u16::MAX);
+
+    fn bit(coord: TileGridCoord) -> u16 {
+        1 << (coord.y * Tile::CELL_GRID_WIDTH + coord.x)
+    }
+
+    pub fn contains(self, coord: TileGridCoord) -> bool {
+        self.0 & Self::bit(coord) != 0
+    }
+
+    /// Returns true if `coord` was not in the set yet
+    pub fn insert(&mut self, coord: TileGridCoord) -> bool {
+        let added = !self.contains(coord);
+        self.0 |= Self::bit(coord);
+        added
+    }
+
+    /// Returns true if `coord` was in the set
+    pub fn remove(&mut self, coord: TileGridCoord) -> bool {
+        let removed = self.contains(coord);
+        self.0 &= !Self::bit(coord);
+        removed
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Cells not in `other`
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Same cells after the tile is rotated by `spin`
+    pub fn rotated(self, spin: SpinDirection) -> Self {
+        self.iter().map(|coord| coord.as_rotated(spin)).collect()
+    }
+
+    pub fn iter(self) -> CellSetIter {
+        CellSetIter(self.0)
+    }
+}
+
+impl BitOr for CellSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitAnd for CellSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl FromIterator<TileGridCoord> for CellSet {
+    fn from_iter<I: IntoIterator<Item = TileGridCoord>>(iter: I) -> Self {
+        let mut set = Self::EMPTY;
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<TileGridCoord> for CellSet {
+    fn extend<I: IntoIterator<Item = TileGridCoord>>(&mut self, iter: I) {
+        for coord in iter {
+            self.insert(coord);
+        }
+    }
+}
+
+impl IntoIterator for CellSet {
+    type Item = TileGridCoord;
+    type IntoIter = CellSetIter;
+
+    fn into_iter(self) -> CellSetIter {
+        self.iter()
+    }
+}
+
+/// Cells of a [`CellSet`], row by row
+#[derive(Debug, Clone)]
+pub struct CellSetIter(u16);
+
+impl Iterator for CellSetIter {
+    type Item = TileGridCoord;
+
+    fn next(&mut self) -> Option<TileGridCoord> {
+        if self.0 == 0 {
+            return None;
+        }
+        let idx = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(TileGridCoord {
+            x: idx % Tile::CELL_GRID_WIDTH,
+            y: idx / Tile::CELL_GRID_WIDTH,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+/// Set of cells anywhere on a `Board`.
+///
+/// Stored as a [`CellSet`] per tile-sized block of the board, aligned to multiples of
+/// the tile width, so it grows with the board without knowing where tiles are placed.
+/// Iterates block by block, and row by row within a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoardCellSet {
+    /// Keyed by the board coordinate of the block's top-left cell
+    blocks: BTreeMap<BoardCoord, CellSet>,
+}
+
+impl BoardCellSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block containing `coord`, and the coordinate inside it
+    fn split(coord: BoardCoord) -> (BoardCoord, TileGridCoord) {
+        let width = Tile::CELL_GRID_WIDTH as i32;
+        let block = BoardCoord::new(
+            coord.x.div_euclid(width) * width,
+            coord.y.div_euclid(width) * width,
+        );
+        let local = coord - block;
+        (
+            block,
+            TileGridCoord {
+                x: local.x as u8,
+                y: local.y as u8,
+            },
+        )
+    }
+
+    pub fn contains(&self, coord: BoardCoord) -> bool {
+        let (block, local) = Self::split(coord);
+        self.blocks
+            .get(&block)
+            .is_some_and(|cells| cells.contains(local))
+    }
+
+    /// Returns true if `coord` was not in the set yet
+    pub fn insert(&mut self, coord: BoardCoord) -> bool {
+        let (block, local) = Self::split(coord);
+        self.blocks.entry(block).or_default().insert(local)
+    }
+
+    /// Returns true if `coord` was in the set
+    pub fn remove(&mut self, coord: BoardCoord) -> bool {
+        let (block, local) = Self::split(coord);
+        let Some(cells) = self.blocks.get_mut(&block) else {
+            return false;
+        };
+        let removed = cells.remove(local);
+        if cells.is_empty() {
+            self.blocks.remove(&block);
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.values().map(|cells| cells.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for (block, cells) in other.blocks.iter() {
+            let merged = union.blocks.entry(*block).or_default();
+            *merged = merged.union(*cells);
+        }
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let blocks = self
+            .blocks
+            .iter()
+            .filter_map(|(block, cells)| {
+                let common = cells.intersection(*other.blocks.get(block)?);
+                (!common.is_empty()).then_some((*block, common))
+            })
+            .collect();
+        Self { blocks }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BoardCoord> + '_ {
+        self.blocks
+            .iter()
+            .flat_map(|(block, cells)| cells.iter().map(|coord| *block + coord.into()))
+    }
+}
+
+impl FromIterator<BoardCoord> for BoardCellSet {
+    fn from_iter<I: IntoIterator<Item = BoardCoord>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<BoardCoord> for BoardCellSet {
+    fn extend<I: IntoIterator<Item = BoardCoord>>(&mut self, iter: I) {
+        for coord in iter {
+            self.insert(coord);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn coord(x: u8, y: u8) -> TileGridCoord {
+        TileGridCoord::new(x, y).unwrap()
+    }
+
+    #[test]
+    fn cell_set() {
+        crate::init_logging();
+        let mut set = CellSet::EMPTY;
+        assert!(set.insert(coord(1, 0)));
+        assert!(!set.insert(coord(1, 0)));
+        set.insert(coord(0, 2));
+        set.insert(coord(3, 3));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(coord(0, 2)));
+        assert!(!set.contains(coord(2, 0)));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [coord(1, 0), coord(0, 2), coord(3, 3)]
+        );
+
+        let other: CellSet = [coord(1, 0), coord(2, 2)].into_iter().collect();
+        assert_eq!((set & other).iter().collect::<Vec<_>>(), [coord(1, 0)]);
+        assert_eq!((set | other).len(), 4);
+        assert_eq!(set.difference(other).len(), 2);
+
+        assert!(set.remove(coord(1, 0)));
+        assert!(!set.remove(coord(1, 0)));
+        assert_eq!(CellSet::ALL.len(), TileGridCoord::all().count());
+        assert!(CellSet::EMPTY.is_empty());
+    }
+
+    #[test]
+    fn rotated() {
+        crate::init_logging();
+        let set: CellSet = [coord(0, 0), coord(1, 0), coord(3, 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            set.rotated(SpinDirection::Clockwise),
+            [coord(3, 0), coord(3, 1), coord(1, 3)]
+                .into_iter()
+                .collect()
+        );
+
+        let mut turned = set;
+        for _ in 0..4 {
+            turned = turned.rotated(SpinDirection::CounterClockwise);
+        }
+        assert_eq!(turned, set);
+        assert_eq!(
+            set.rotated(SpinDirection::Clockwise)
+                .rotated(SpinDirection::CounterClockwise),
+            set
+        );
+    }
+
+    #[test]
+    fn board_cell_set() {
+        crate::init_logging();
+        let coords = [
+            BoardCoord::new(0, 0),
+            BoardCoord::new(5, 1),
+            BoardCoord::new(-1, -1),
+            BoardCoord::new(-4, 7),
+        ];
+        let mut set: BoardCellSet = coords.into_iter().collect();
+        assert_eq!(set.len(), coords.len());
+        for coord in coords {
+            assert!(set.contains(coord));
+        }
+        assert!(!set.contains(BoardCoord::new(1, 0)));
+        assert!(!set.insert(BoardCoord::new(-1, -1)));
+
+        let mut iterated: Vec<_> = set.iter().collect();
+        iterated.sort();
+        let mut sorted = coords.to_vec();
+        sorted.sort();
+        assert_eq!(iterated, sorted);
+
+        let other: BoardCellSet = [BoardCoord::new(5, 1), BoardCoord::new(9, 9)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            set.intersection(&other).iter().collect::<Vec<_>>(),
+            [BoardCoord::new(5, 1)]
+        );
+        assert_eq!(set.union(&other).len(), coords.len() + 1);
+
+        assert!(set.remove(BoardCoord::new(-4, 7)));
+        assert!(!set.remove(BoardCoord::new(-4, 7)));
+        assert_eq!(set.len(), coords.len() - 1);
+        for coord in coords {
+            set.remove(coord);
+        }
+        assert!(set.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CartesianDirection {
     Right,
     Up,
@@ -20,6 +22,15 @@ impl CartesianDirection {
         }
     }
 
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Right => Self::Left,
+            Self::Up => Self::Down,
+            Self::Left => Self::Right,
+            Self::Down => Self::Up,
+        }
+    }
+
     /// CartesianDirection converted to angle (in radians)
     pub fn as_angle(self) -> f32 {
         match self {
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+/// Change to a `Tile` made in the tile editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileEdit {
+    /// Cycle the wall on side `1` of `0` through the states it can have there
+    Wall(TileGridCoord, CartesianDirection),
+
+    /// Cycle the content of the cell
+    Cell(TileGridCoord),
+
+    /// Add an escalator between the cells, or remove it if there already is one
+    Escalator(EscalatorLocation),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TileEditError {
+    #[error(
+        "A tile has room for at most {} escalators",
+        Tile::MAX_ESCALATORS_PER_TILE
+    )]
+    TooManyEscalators,
+
+    #[error("An escalator needs two different cells, not {0:?} twice")]
+    EscalatorToSelf(TileGridCoord),
+}
+
+impl TileEdit {
+    /// Same edit on the tile turned by `orientation`
+    pub fn as_oriented(self, orientation: Orientation) -> Self {
+        match self {
+            Self::Wall(coord, dir) => {
+                Self::Wall(coord.as_oriented(orientation), dir.as_oriented(orientation))
+            }
+            Self::Cell(coord) => Self::Cell(coord.as_oriented(orientation)),
+            Self::Escalator(mut esc) => {
+                esc.orient(orientation);
+                Self::Escalator(esc)
+            }
+        }
+    }
+}
+
+impl WallState {
+    /// Walls between two cells
+    const INNER_CYCLE: [Self; 3] = [Self::Open, Self::Blocked, Self::OrangeOnly];
+
+    /// Walls on the edge of a tile
+    const OUTER_CYCLE: [Self; 7] = [
+        Self::Blocked,
+        Self::Open,
+        Self::Explore(Pawn::Green),
+        Self::Explore(Pawn::Orange),
+        Self::Explore(Pawn::Yellow),
+        Self::Explore(Pawn::Purple),
+        Self::Entrance,
+    ];
+
+    /// State after `self` when clicked in the editor; walls on the edge of a tile cycle
+    /// through exits instead of orange only walls
+    pub fn next_edit(self, outer: bool) -> Self {
+        let cycle: &[Self] = if outer {
+            &Self::OUTER_CYCLE
+        } else {
+            &Self::INNER_CYCLE
+        };
+        next_in_cycle(cycle, self)
+    }
+}
+
+impl TileCell {
+    const EDIT_CYCLE: [Self; 16] = {
+        use CellItemAvailability::Available;
+        use Pawn::*;
+        [
+            Self::Empty,
+            Self::Warp(Green),
+            Self::Warp(Orange),
+            Self::Warp(Yellow),
+            Self::Warp(Purple),
+            Self::Loot(Green),
+            Self::Loot(Orange),
+            Self::Loot(Yellow),
+            Self::Loot(Purple),
+            Self::FinalExit(Green),
+            Self::FinalExit(Orange),
+            Self::FinalExit(Yellow),
+            Self::FinalExit(Purple),
+            Self::TimerFlip(Available),
+            Self::Camera(Available),
+            Self::CrystalBall(Available),
+        ]
+    };
+
+    /// Content after `self` when clicked in the editor, with cell items available
+    pub fn next_edit(self) -> Self {
+        let mut cell = self;
+        cell.set_availability(CellItemAvailability::Available);
+        next_in_cycle(&Self::EDIT_CYCLE, cell)
+    }
+}
+
+/// Item after `current` in `cycle`, or the first one if `current` is last or missing
+fn next_in_cycle<T: Copy + PartialEq>(cycle: &[T], current: T) -> T {
+    let idx = cycle
+        .iter()
+        .position(|item| *item == current)
+        .map_or(0, |idx| (idx + 1) % cycle.len());
+    cycle[idx]
+}
+
+impl Tile {
+    pub fn apply_edit(&mut self, edit: TileEdit) -> Result<(), TileEditError> {
+        match edit {
+            TileEdit::Wall(coord, dir) => {
+                let outer = self.cell_cardinal_neighbor_coords(coord, dir).is_none();
+                let wall = self.cell_wall(coord, dir).next_edit(outer);
+                self.set_cell_wall(coord, dir, wall);
+            }
+            TileEdit::Cell(coord) => {
+                let cell = self.cell_value(coord).next_edit();
+                self.set_cell_value(coord, cell);
+            }
+            TileEdit::Escalator(EscalatorLocation([a, b])) => {
+                if a == b {
+                    return Err(TileEditError::EscalatorToSelf(a));
+                }
+                let existing = self
+                    .escalators
+                    .iter()
+                    .position(|esc| esc.coord_neighbor(a) == Some(b));
+                match existing {
+                    Some(idx) => {
+                        self.escalators.remove(idx);
+                    }
+                    None => self
+                        .escalators
+                        .try_push(EscalatorLocation([a, b]))
+                        .map_err(|_| TileEditError::TooManyEscalators)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Undo and redo for the edits of one tile
+#[derive(Debug, Clone, Default)]
+pub struct TileEditHistory {
+    undo: Vec<Tile>,
+    redo: Vec<Tile>,
+}
+
+impl TileEditHistory {
+    /// Apply `edit` to `tile` so that it can be undone
+    pub fn apply(&mut self, tile: &mut Tile, edit: TileEdit) -> Result<(), TileEditError> {
+        let before = tile.clone();
+        tile.apply_edit(edit)?;
+        self.undo.push(before);
+        self.redo.clear();
+        Ok(())
+    }
+
+    /// Undo the last edit of `tile`; false if there is none
+    pub fn undo(&mut self, tile: &mut Tile) -> bool {
+        let Some(before) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(std::mem::replace(tile, before));
+        true
+    }
+
+    /// Redo the last undone edit of `tile`; false if there is none
+    pub fn redo(&mut self, tile: &mut Tile) -> bool {
+        let Some(after) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(tile, after));
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cycle() {
+        crate::init_logging();
+        let mut tile = Tile::default();
+        let corner = TileGridCoord::new(0, 0).unwrap();
+
+        // inner walls never become exits
+        let mut seen = vec![];
+        for _ in 0..WallState::INNER_CYCLE.len() {
+            tile.apply_edit(TileEdit::Wall(corner, CartesianDirection::Right))
+                .unwrap();
+            seen.push(tile.cell_wall(corner, CartesianDirection::Right));
+        }
+        assert_eq!(
+            seen,
+            [WallState::Blocked, WallState::OrangeOnly, WallState::Open]
+        );
+        let neighbor = TileGridCoord::new(1, 0).unwrap();
+        tile.apply_edit(TileEdit::Wall(neighbor, CartesianDirection::Left))
+            .unwrap();
+        assert_eq!(
+            tile.cell_wall(corner, CartesianDirection::Right),
+            WallState::Blocked
+        );
+
+        // outer walls cycle through exits instead
+        let mut seen = vec![];
+        for _ in 0..WallState::OUTER_CYCLE.len() {
+            tile.apply_edit(TileEdit::Wall(corner, CartesianDirection::Up))
+                .unwrap();
+            seen.push(tile.cell_wall(corner, CartesianDirection::Up));
+        }
+        assert_eq!(seen[0], WallState::Explore(Pawn::Green));
+        assert_eq!(seen[4], WallState::Entrance);
+        assert_eq!(seen[5], WallState::Blocked);
+        assert_eq!(seen[6], WallState::Open);
+
+        // every cell content comes around once, and used items become available
+        let mut cells = vec![];
+        for _ in 0..TileCell::EDIT_CYCLE.len() {
+            tile.apply_edit(TileEdit::Cell(corner)).unwrap();
+            cells.push(tile.cell_value(corner));
+        }
+        assert_eq!(cells.last(), Some(&TileCell::Empty));
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), TileCell::EDIT_CYCLE.len());
+        assert_eq!(
+            TileCell::Camera(CellItemAvailability::Used).next_edit(),
+            TileCell::CrystalBall(CellItemAvailability::Available)
+        );
+    }
+
+    #[test]
+    fn escalators() {
+        crate::init_logging();
+        let mut tile = Tile::default();
+        let coord = |x, y| TileGridCoord::new(x, y).unwrap();
+        let esc = EscalatorLocation([coord(0, 0), coord(1, 1)]);
+        tile.apply_edit(TileEdit::Escalator(esc)).unwrap();
+        assert_eq!(tile.escalators().as_slice(), [esc]);
+        assert!(tile
+            .cell_immediate_neighbor_coords(coord(0, 0))
+            .contains(coord(1, 1)));
+
+        // dragging the other way removes it
+        let back = EscalatorLocation([coord(1, 1), coord(0, 0)]);
+        tile.apply_edit(TileEdit::Escalator(back)).unwrap();
+        assert!(tile.escalators().is_empty());
+
+        for x in 0..4 {
+            let esc = EscalatorLocation([coord(x, 0), coord(x, 3)]);
+            tile.apply_edit(TileEdit::Escalator(esc)).unwrap();
+        }
+        assert_eq!(
+            tile.apply_edit(TileEdit::Escalator(esc)),
+            Err(TileEditError::TooManyEscalators)
+        );
+        assert_eq!(
+            tile.apply_edit(TileEdit::Escalator(EscalatorLocation([
+                coord(2, 2),
+                coord(2, 2)
+            ]))),
+            Err(TileEditError::EscalatorToSelf(coord(2, 2)))
+        );
+        assert_eq!(tile.escalators().len(), 4);
+    }
+
+    #[test]
+    fn oriented() {
+        crate::init_logging();
+        let tile = game_tileset().unwrap()[1].1.clone();
+        let coord = TileGridCoord::new(1, 0).unwrap();
+        let edits = [
+            TileEdit::Wall(coord, CartesianDirection::Up),
+            TileEdit::Wall(coord, CartesianDirection::Right),
+            TileEdit::Cell(coord),
+            TileEdit::Escalator(EscalatorLocation([
+                coord,
+                TileGridCoord::new(2, 3).unwrap(),
+            ])),
+        ];
+        for orientation in Orientation::ALL {
+            for edit in edits {
+                // editing the turned tile is editing the tile, turned
+                let mut turned = tile.as_oriented(orientation);
+                turned.apply_edit(edit).unwrap();
+                let mut edited = tile.clone();
+                edited
+                    .apply_edit(edit.as_oriented(orientation.inverse()))
+                    .unwrap();
+                assert_eq!(edited.as_oriented(orientation), turned, "{orientation}");
+            }
+        }
+    }
+
+    #[test]
+    fn history() {
+        crate::init_logging();
+        let original = game_tileset().unwrap()[0].1.clone();
+        let mut tile = original.clone();
+        let mut history = TileEditHistory::default();
+        assert!(!history.undo(&mut tile));
+
+        let coord = TileGridCoord::new(1, 1).unwrap();
+        history.apply(&mut tile, TileEdit::Cell(coord)).unwrap();
+        let once = tile.clone();
+        history.apply(&mut tile, TileEdit::Cell(coord)).unwrap();
+        assert_ne!(tile, once);
+
+        assert!(history.undo(&mut tile));
+        assert_eq!(tile, once);
+        assert!(history.undo(&mut tile));
+        assert_eq!(tile, original);
+        assert!(!history.undo(&mut tile));
+
+        assert!(history.redo(&mut tile));
+        assert_eq!(tile, once);
+
+        // a new edit drops what was undone
+        history
+            .apply(&mut tile, TileEdit::Wall(coord, CartesianDirection::Up))
+            .unwrap();
+        assert!(!history.redo(&mut tile));
+
+        // a failed edit is not recorded
+        let same = EscalatorLocation([coord, coord]);
+        assert!(history.apply(&mut tile, TileEdit::Escalator(same)).is_err());
+        assert!(history.undo(&mut tile));
+        assert_eq!(tile, once);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::rng::Fnv1a;
+
+/// Identifies a `Tile` regardless of how it is rotated.
+///
+/// Tiles with the same fingerprint are almost surely the same up to rotation; compare
+/// [`Tile::canonical`] to be sure. Stable between runs of the same build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TileFingerprint(pub u64);
+
+impl fmt::Display for TileFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl Tile {
+    /// Same tile in the rotation that sorts first, with cell items available and
+    /// escalators in a fixed order, and the rotation that turns `self` into it.
+    ///
+    /// All rotations of a tile have the same canonical tile.
+    pub fn canonical(&self) -> (Tile, Orientation) {
+        let mut tile = self.clone();
+        for cell in tile.cells_iter_mut() {
+            cell.set_availability(CellItemAvailability::Available);
+        }
+        (0..4)
+            .map(Orientation::rotation)
+            .map(|orientation| {
+                let mut turned = tile.as_oriented(orientation);
+                for esc in turned.escalators.iter_mut() {
+                    esc.0.sort();
+                }
+                turned.escalators.sort();
+                (turned, orientation)
+            })
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .expect("four rotations")
+    }
+
+    pub fn fingerprint(&self) -> TileFingerprint {
+        let mut hasher = Fnv1a::default();
+        self.canonical().0.hash(&mut hasher);
+        TileFingerprint(hasher.finish())
+    }
+}
+
+/// Indices of tiles in `tiles` that are the same up to rotation, in groups of at least
+/// two, in the order of their first tile
+pub fn duplicate_tiles<'a, I>(tiles: I) -> Vec<Vec<usize>>
+where
+    I: IntoIterator<Item = &'a Tile>,
+{
+    let mut groups: BTreeMap<Tile, Vec<usize>> = BTreeMap::new();
+    for (idx, tile) in tiles.into_iter().enumerate() {
+        groups.entry(tile.canonical().0).or_default().push(idx);
+    }
+    let mut duplicates: Vec<Vec<usize>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    duplicates.sort();
+    duplicates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fingerprint() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        for (name, tile) in tileset.iter() {
+            let (canonical, orientation) = tile.canonical();
+            // escalators may be listed in another order
+            let turned = tile.as_oriented(orientation);
+            assert_eq!(turned.cell_grid(), canonical.cell_grid(), "{name}");
+            assert_eq!(turned.horz_walls(), canonical.horz_walls(), "{name}");
+            assert_eq!(turned.vert_walls(), canonical.vert_walls(), "{name}");
+            for turns in 0..4 {
+                let turned = tile.as_oriented(Orientation::rotation(turns));
+                assert_eq!(turned.canonical().0, canonical, "{name}");
+                assert_eq!(turned.fingerprint(), tile.fingerprint(), "{name}");
+            }
+        }
+
+        let mut used = tileset[0].1.clone();
+        for cell in used.cells_iter_mut() {
+            cell.set_availability(CellItemAvailability::Used);
+        }
+        assert_eq!(used.fingerprint(), tileset[0].1.fingerprint());
+
+        let mut fingerprints: Vec<_> = tileset.iter().map(|(_, tile)| tile.fingerprint()).collect();
+        fingerprints.sort();
+        fingerprints.dedup();
+        assert_eq!(fingerprints.len(), tileset.len());
+    }
+
+    #[test]
+    fn duplicates() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        assert!(duplicate_tiles(tileset.iter().map(|(_, tile)| tile)).is_empty());
+
+        let tiles = [
+            tileset[3].1.clone(),
+            tileset[1].1.clone(),
+            tileset[3].1.as_oriented(Orientation::rotation(1)),
+            tileset[2].1.clone(),
+            tileset[1].1.as_oriented(Orientation::rotation(2)),
+            tileset[3].1.clone(),
+        ];
+        assert_eq!(duplicate_tiles(tiles.iter()), [vec![0, 2, 5], vec![1, 4]]);
+
+        // a mirror image is a different tile
+        let mirrored = tileset[3].1.as_oriented(Orientation::MIRROR);
+        assert!(duplicate_tiles([&tileset[3].1, &mirrored]).is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prelude::*;
+
+/// What a tile made by [`TileConstraints::generate`] has.
+///
+/// Generated tiles always have an entrance where game tiles have theirs, and every cell
+/// can be reached from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileConstraints {
+    /// Explore walls on the other edges; at most 3
+    pub explore_exits: u8,
+
+    /// A warp cell of each of these colors
+    pub warps: Vec<Pawn>,
+
+    pub camera: bool,
+    pub timer_flip: bool,
+    pub crystal_ball: bool,
+
+    /// At most 4
+    pub escalators: u8,
+
+    /// Inner walls to open on top of the ones needed to reach every cell, which makes
+    /// loops
+    pub extra_openings: u8,
+}
+
+impl Default for TileConstraints {
+    fn default() -> Self {
+        Self {
+            explore_exits: 2,
+            warps: vec![],
+            camera: false,
+            timer_flip: false,
+            crystal_ball: false,
+            escalators: 0,
+            extra_openings: 3,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TileGenError {
+    #[error("A tile has room for at most 3 explore exits, not {0}")]
+    TooManyExploreExits(u8),
+
+    #[error(
+        "A tile has room for at most {} escalators, not {0}",
+        Tile::MAX_ESCALATORS_PER_TILE
+    )]
+    TooManyEscalators(u8),
+
+    #[error("{items} cell items do not fit in the {free} cells away from the edge exits")]
+    TooManyItems { items: usize, free: usize },
+}
+
+impl TileConstraints {
+    /// Edge cells that game tiles have their entrance and explore exits at
+    const EDGE_EXITS: usize = 4;
+
+    fn items(&self) -> Vec<TileCell> {
+        let avail = CellItemAvailability::Available;
+        let mut items: Vec<TileCell> = self.warps.iter().copied().map(TileCell::Warp).collect();
+        items.extend(
+            [
+                (self.camera, TileCell::Camera(avail)),
+                (self.timer_flip, TileCell::TimerFlip(avail)),
+                (self.crystal_ball, TileCell::CrystalBall(avail)),
+            ]
+            .into_iter()
+            .filter_map(|(wanted, cell)| wanted.then_some(cell)),
+        );
+        items
+    }
+
+    pub fn check(&self) -> Result<(), TileGenError> {
+        if self.explore_exits as usize >= Self::EDGE_EXITS {
+            return Err(TileGenError::TooManyExploreExits(self.explore_exits));
+        }
+        if self.escalators > Tile::MAX_ESCALATORS_PER_TILE {
+            return Err(TileGenError::TooManyEscalators(self.escalators));
+        }
+        let items = self.items().len();
+        let free = TileGridCoord::all().count() - 1 - self.explore_exits as usize;
+        if items > free {
+            return Err(TileGenError::TooManyItems { items, free });
+        }
+        Ok(())
+    }
+
+    /// Random tile with everything asked for
+    pub fn generate(&self, rng: &mut GameRng) -> Result<Tile, TileGenError> {
+        self.check()?;
+        let mut tile = Tile::default();
+        for wall in tile.horz_walls.iter_mut().flatten() {
+            *wall = WallState::Blocked;
+        }
+        for wall in tile.vert_walls.iter_mut().flatten() {
+            *wall = WallState::Blocked;
+        }
+
+        let mut edges = Tile::POSSIBLE_ENTRANCE_COORDS[..Self::EDGE_EXITS].to_vec();
+        rng.shuffle(&mut edges);
+        let exits = &edges[..=self.explore_exits as usize];
+        for (idx, coord) in exits.iter().copied().enumerate() {
+            let dir = tile.cell_outer_edge_directions(coord)[0];
+            let wall = if idx == 0 {
+                WallState::Entrance
+            } else {
+                WallState::Explore(Pawn::ALL[rng.usize(..Pawn::ALL.len())])
+            };
+            tile.set_cell_wall(coord, dir, wall);
+        }
+
+        carve(&mut tile, exits[0], rng);
+        let mut closed: Vec<(TileGridCoord, CartesianDirection)> = inner_walls()
+            .filter(|(coord, dir)| tile.cell_wall(*coord, *dir) == WallState::Blocked)
+            .collect();
+        rng.shuffle(&mut closed);
+        for (coord, dir) in closed.into_iter().take(self.extra_openings as usize) {
+            tile.set_cell_wall(coord, dir, WallState::Open);
+        }
+
+        let mut pairs: Vec<EscalatorLocation> = TileGridCoord::all()
+            .flat_map(|a| TileGridCoord::all().map(move |b| EscalatorLocation([a, b])))
+            // escalators run diagonally, like in the game
+            .filter(|EscalatorLocation([a, b])| a < b && a.x != b.x && a.y != b.y)
+            .collect();
+        rng.shuffle(&mut pairs);
+        let mut used = CellSet::EMPTY;
+        for esc in pairs {
+            if tile.escalators.len() == self.escalators as usize {
+                break;
+            }
+            if esc.0.iter().all(|coord| !used.contains(*coord)) {
+                used.extend(esc.0);
+                tile.escalators.push(esc);
+            }
+        }
+
+        let mut free: Vec<TileGridCoord> = TileGridCoord::all()
+            .filter(|coord| !exits.contains(coord))
+            .collect();
+        rng.shuffle(&mut free);
+        for (coord, item) in free.into_iter().zip(self.items()) {
+            tile.set_cell_value(coord, item);
+        }
+
+        debug_assert_eq!(tile.reachable_coords(), CellSet::ALL);
+        Ok(tile)
+    }
+}
+
+/// Walls between two cells of a tile, once each
+fn inner_walls() -> impl Iterator<Item = (TileGridCoord, CartesianDirection)> {
+    TileGridCoord::all().flat_map(|coord| {
+        [CartesianDirection::Right, CartesianDirection::Down]
+            .into_iter()
+            .filter(move |dir| coord.added(dir.neighbor_transform()).is_some())
+            .map(move |dir| (coord, dir))
+    })
+}
+
+/// Open just enough inner walls for every cell to be reached from `start`, as a random
+/// depth-first maze
+fn carve(tile: &mut Tile, start: TileGridCoord, rng: &mut GameRng) {
+    let mut visited: CellSet = [start].into_iter().collect();
+    let mut stack = vec![start];
+    while let Some(coord) = stack.last().copied() {
+        let unvisited: Vec<(TileGridCoord, CartesianDirection)> =
+            CartesianDirection::ALL_DIRECTIONS
+                .iter()
+                .copied()
+                .filter_map(|dir| Some((tile.cell_cardinal_neighbor_coords(coord, dir)?, dir)))
+                .filter(|(neighbor, _dir)| !visited.contains(*neighbor))
+                .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (neighbor, dir) = unvisited[rng.usize(..unvisited.len())];
+        tile.set_cell_wall(coord, dir, WallState::Open);
+        visited.insert(neighbor);
+        stack.push(neighbor);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generate() {
+        crate::init_logging();
+        let tileset = game_tileset().unwrap();
+        let mut rng = GameRng::new(3);
+        for seed in 0..200 {
+            let constraints = TileConstraints {
+                explore_exits: rng.usize(..=3) as u8,
+                warps: Pawn::ALL[..rng.usize(..=4)].to_vec(),
+                camera: rng.usize(..2) == 0,
+                timer_flip: rng.usize(..2) == 0,
+                crystal_ball: rng.usize(..2) == 0,
+                escalators: rng.usize(..=4) as u8,
+                extra_openings: rng.usize(..6) as u8,
+            };
+            let tile = constraints.generate(&mut GameRng::new(seed)).unwrap();
+            assert_eq!(
+                constraints.generate(&mut GameRng::new(seed)).as_ref(),
+                Ok(&tile)
+            );
+
+            assert_eq!(tile.reachable_coords(), CellSet::ALL, "{tile}");
+            let (entrance, _dir) = tile.entrance().unwrap();
+            assert!(Tile::POSSIBLE_ENTRANCE_COORDS[..4].contains(&entrance));
+            let tile_ref = &tile;
+            let explore_exits = TileGridCoord::all()
+                .flat_map(|coord| {
+                    tile_ref
+                        .cell_outer_edge_directions(coord)
+                        .into_iter()
+                        .map(move |dir| tile_ref.cell_wall(coord, dir))
+                })
+                .filter(|wall| matches!(wall, WallState::Explore(_)))
+                .count();
+            assert_eq!(explore_exits, constraints.explore_exits as usize);
+            assert_eq!(tile.escalators().len(), constraints.escalators as usize);
+
+            let mut items: Vec<TileCell> = tile
+                .cells_iter()
+                .copied()
+                .filter(|cell| *cell != TileCell::Empty)
+                .collect();
+            let mut expected = constraints.items();
+            items.sort();
+            expected.sort();
+            assert_eq!(items, expected);
+
+            assert_eq!(tile.to_string().parse::<Tile>().as_ref(), Ok(&tile));
+
+            // fits on a board like a game tile
+            let mut board = Board::new(tileset[0].0.clone(), tileset[0].1.clone());
+            let from = BoardCoord::new(2, 0);
+            let entrance_coord = board.explore_destinations(from, Pawn::Orange)[0];
+            board
+                .place_tile(from, entrance_coord, "gen".to_string(), tile)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn impossible() {
+        crate::init_logging();
+        let mut rng = GameRng::new(0);
+        let too_many_exits = TileConstraints {
+            explore_exits: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            too_many_exits.generate(&mut rng),
+            Err(TileGenError::TooManyExploreExits(4))
+        );
+
+        let too_many_escalators = TileConstraints {
+            escalators: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            too_many_escalators.check(),
+            Err(TileGenError::TooManyEscalators(5))
+        );
+
+        let too_many_items = TileConstraints {
+            explore_exits: 3,
+            warps: [Pawn::ALL; 3].concat(),
+            camera: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            too_many_items.check(),
+            Err(TileGenError::TooManyItems {
+                items: 13,
+                free: 12
+            })
+        );
+    }
+}
@@ -1,13 +1,16 @@
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
 /// Index (x, y) into a `Tile` grid.
 ///
 /// - `x`: goes from left to right
 /// - `y`: goes from top to bottom
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "(u8, u8)", into = "(u8, u8)")]
 pub struct TileGridCoord {
-    pub(super) x: u8,
-    pub(super) y: u8,
+    pub(crate) x: u8,
+    pub(crate) y: u8,
 }
 
 impl TileGridCoord {
@@ -19,6 +22,12 @@ impl TileGridCoord {
         }
     }
 
+    /// All coordinates in a tile, row by row
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..Tile::CELL_GRID_WIDTH)
+            .flat_map(|y| (0..Tile::CELL_GRID_WIDTH).map(move |x| Self { x, y }))
+    }
+
     pub fn added(self, add: (i8, i8)) -> Option<Self> {
         let new_x = (self.x as i8 + add.0).try_into().ok()?;
         let new_y = (self.y as i8 + add.1).try_into().ok()?;
@@ -52,3 +61,17 @@ impl TileGridCoord {
         }
     }
 }
+
+impl TryFrom<(u8, u8)> for TileGridCoord {
+    type Error = String;
+
+    fn try_from((x, y): (u8, u8)) -> Result<Self, Self::Error> {
+        Self::new(x, y).ok_or_else(|| format!("Tile grid coordinate ({}, {}) out of range", x, y))
+    }
+}
+
+impl From<TileGridCoord> for (u8, u8) {
+    fn from(coord: TileGridCoord) -> Self {
+        (coord.x, coord.y)
+    }
+}
@@ -1,11 +1,18 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
 pub mod cell;
+pub mod cell_set;
 pub mod direction;
+pub mod edit;
 pub mod escalator;
+pub mod fingerprint;
+pub mod generator;
 pub mod grid_coord;
+pub mod orientation;
 pub mod tileset;
 pub mod wall;
 
@@ -18,12 +25,12 @@ where
     fn parse(value: u8) -> Option<Self>;
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Tile {
-    cell_grid: [[TileCell; Self::CELL_GRID_WIDTH as usize]; Self::CELL_GRID_WIDTH as usize],
-    horz_walls: [[WallState; Self::CELL_GRID_WIDTH as usize]; (Self::CELL_GRID_WIDTH + 1) as usize],
-    vert_walls: [[WallState; (Self::CELL_GRID_WIDTH + 1) as usize]; Self::CELL_GRID_WIDTH as usize],
-    escalators: arrayvec::ArrayVec<EscalatorLocation, { Self::MAX_ESCALATORS_PER_TILE as usize }>,
+    cell_grid: [[TileCell; Tile::CELL_GRID_WIDTH as usize]; Tile::CELL_GRID_WIDTH as usize],
+    horz_walls: [[WallState; Tile::CELL_GRID_WIDTH as usize]; (Tile::CELL_GRID_WIDTH + 1) as usize],
+    vert_walls: [[WallState; (Tile::CELL_GRID_WIDTH + 1) as usize]; Tile::CELL_GRID_WIDTH as usize],
+    escalators: arrayvec::ArrayVec<EscalatorLocation, { Tile::MAX_ESCALATORS_PER_TILE as usize }>,
 }
 
 impl Tile {
@@ -56,6 +63,10 @@ impl Tile {
         self.cell_grid[coord.y() as usize][coord.x() as usize]
     }
 
+    pub fn set_cell_availability(&mut self, coord: TileGridCoord, avail: CellItemAvailability) {
+        self.cell_grid[coord.y() as usize][coord.x() as usize].set_availability(avail);
+    }
+
     /// Neighbor coords accessible via cardinal direction walk
     pub fn cell_cardinal_neighbor_coords(
         &self,
@@ -77,7 +88,7 @@ impl Tile {
 
     /// Coordinates of "neighbors" in current tile that are "one step" away
     /// (either by cardinal direction walk or escalator).
-    pub fn cell_immediate_neighbor_coords(&self, coord: TileGridCoord) -> Vec<TileGridCoord> {
+    pub fn cell_immediate_neighbor_coords(&self, coord: TileGridCoord) -> CellSet {
         let cardinal_neighbors = CartesianDirection::ALL_DIRECTIONS
             .iter()
             .copied()
@@ -91,23 +102,23 @@ impl Tile {
             .iter()
             .filter_map(|esc_loc| esc_loc.coord_neighbor(coord));
 
-        let mut neighbors: Vec<_> = cardinal_neighbors.chain(escalator_neighbors).collect();
-
-        // we could have duplicates
-        neighbors.sort_unstable();
-        neighbors.dedup();
-
-        neighbors
+        cardinal_neighbors.chain(escalator_neighbors).collect()
     }
 
-    const POSSIBLE_ENTRANCE_COORDS: [TileGridCoord; 4] = [
+    /// Edge cells that may connect to another tile; game tiles only use the first four,
+    /// mirrored tiles the last four
+    const POSSIBLE_ENTRANCE_COORDS: [TileGridCoord; 8] = [
         TileGridCoord { x: 0, y: 1 },
         TileGridCoord { x: 1, y: 3 },
         TileGridCoord { x: 2, y: 0 },
         TileGridCoord { x: 3, y: 2 },
+        TileGridCoord { x: 3, y: 1 },
+        TileGridCoord { x: 2, y: 3 },
+        TileGridCoord { x: 1, y: 0 },
+        TileGridCoord { x: 0, y: 2 },
     ];
 
-    fn reachable_coords_starting(&self) -> Vec<TileGridCoord> {
+    fn reachable_coords_starting(&self) -> CellSet {
         Self::POSSIBLE_ENTRANCE_COORDS
             .iter()
             .copied()
@@ -129,26 +140,31 @@ impl Tile {
             .collect()
     }
 
-    pub fn reachable_coords(
-        &self,
-    ) -> [[bool; Tile::CELL_GRID_WIDTH as usize]; Tile::CELL_GRID_WIDTH as usize] {
-        let mut explore_coords = self.reachable_coords_starting();
-        let mut visited_coords = HashSet::new();
-        let mut is_reachable_coord: [[bool; Tile::CELL_GRID_WIDTH as usize];
-            Tile::CELL_GRID_WIDTH as usize] = Default::default();
-
-        while let Some(coord) = explore_coords.pop() {
-            visited_coords.insert(coord);
-            is_reachable_coord[coord.y as usize][coord.x as usize] = true;
-
-            for neighbor in self.cell_immediate_neighbor_coords(coord) {
-                if !(visited_coords.contains(&neighbor) || explore_coords.contains(&neighbor)) {
-                    explore_coords.push(neighbor);
-                }
-            }
-        }
+    /// Entrance coordinate and the direction of its entrance wall
+    pub fn entrance(&self) -> Option<(TileGridCoord, CartesianDirection)> {
+        Self::POSSIBLE_ENTRANCE_COORDS
+            .iter()
+            .copied()
+            .flat_map(|coord| {
+                self.cell_outer_edge_directions(coord)
+                    .into_iter()
+                    .map(move |dir| (coord, dir))
+            })
+            .find(|(coord, dir)| self.cell_wall(*coord, *dir) == WallState::Entrance)
+    }
 
-        is_reachable_coord
+    /// Cells that can be reached from the edges of the tile
+    pub fn reachable_coords(&self) -> CellSet {
+        let mut reachable = CellSet::EMPTY;
+        let mut frontier = self.reachable_coords_starting();
+        while !frontier.is_empty() {
+            reachable = reachable.union(frontier);
+            let neighbors = frontier.iter().fold(CellSet::EMPTY, |neighbors, coord| {
+                neighbors.union(self.cell_immediate_neighbor_coords(coord))
+            });
+            frontier = neighbors.difference(reachable);
+        }
+        reachable
     }
 
     pub fn cell_wall(&self, coord: TileGridCoord, direction: CartesianDirection) -> WallState {
@@ -162,6 +178,28 @@ impl Tile {
         }
     }
 
+    /// Set the wall on side `direction` of `coord`, which is also the wall on the
+    /// opposite side of the neighbor
+    pub fn set_cell_wall(
+        &mut self,
+        coord: TileGridCoord,
+        direction: CartesianDirection,
+        wall: WallState,
+    ) {
+        let x = coord.x() as usize;
+        let y = coord.y() as usize;
+        match direction {
+            CartesianDirection::Up => self.horz_walls[y][x] = wall,
+            CartesianDirection::Down => self.horz_walls[y + 1][x] = wall,
+            CartesianDirection::Left => self.vert_walls[y][x] = wall,
+            CartesianDirection::Right => self.vert_walls[y][x + 1] = wall,
+        }
+    }
+
+    pub fn set_cell_value(&mut self, coord: TileGridCoord, cell: TileCell) {
+        self.cell_grid[coord.y() as usize][coord.x() as usize] = cell;
+    }
+
     pub fn cell_exit_direction(&self, coord: TileGridCoord) -> CartesianDirection {
         let open_exit_dirs: Vec<CartesianDirection> = self
             .cell_outer_edge_directions(coord)
@@ -252,6 +290,12 @@ pub fn rotate_2d_array<T: Copy, const WIDTH: usize, const HEIGHT: usize>(
     }
 }
 
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tileset::tile_to_string(self))
+    }
+}
+
 impl FromStr for Tile {
     type Err = tileset::TileParsingError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -497,71 +541,78 @@ mod test {
         }
     }
 
+    fn cells<const N: usize>(coords: [(u8, u8); N]) -> CellSet {
+        coords
+            .into_iter()
+            .map(|(x, y)| TileGridCoord { x, y })
+            .collect()
+    }
+
     #[test]
     fn neighbor() {
         assert_eq!(
             TILE_1A.cell_immediate_neighbor_coords(TileGridCoord { x: 0, y: 0 }),
-            [TileGridCoord { x: 1, y: 0 }]
+            cells([(1, 0)])
         );
         assert_eq!(
             TILE_1A.cell_immediate_neighbor_coords(TileGridCoord { x: 1, y: 0 }),
-            [
-                TileGridCoord { x: 0, y: 0 },
-                TileGridCoord { x: 1, y: 1 },
-                TileGridCoord { x: 2, y: 0 },
-            ]
+            cells([(0, 0), (1, 1), (2, 0)])
         );
         assert_eq!(
             TILE_1A.cell_immediate_neighbor_coords(TileGridCoord { x: 3, y: 3 }),
-            []
+            CellSet::EMPTY
         );
         assert_eq!(
             TILE_1A.cell_immediate_neighbor_coords(TileGridCoord { x: 2, y: 3 }),
-            [
-                TileGridCoord { x: 1, y: 3 },
-                TileGridCoord { x: 2, y: 2 },
-                TileGridCoord { x: 3, y: 2 },
-            ]
+            cells([(1, 3), (2, 2), (3, 2)])
         );
         assert_eq!(
             TILE_1A.cell_immediate_neighbor_coords(TileGridCoord { x: 3, y: 2 }),
-            [TileGridCoord { x: 2, y: 3 },]
+            cells([(2, 3)])
         );
     }
 
     #[test]
-    fn start_coords() {
+    fn entrance() {
+        assert_eq!(TILE_1A.entrance(), None);
         assert_eq!(
-            TILE_1A.reachable_coords_starting(),
-            Tile::POSSIBLE_ENTRANCE_COORDS
+            TILE_2.entrance(),
+            Some((TileGridCoord { x: 3, y: 2 }, CartesianDirection::Right))
         );
+    }
 
+    #[test]
+    fn start_coords() {
         assert_eq!(
-            TILE_2.reachable_coords_starting(),
-            [TileGridCoord { x: 1, y: 3 }, TileGridCoord { x: 3, y: 2 },]
+            TILE_1A.reachable_coords_starting(),
+            Tile::POSSIBLE_ENTRANCE_COORDS[..4]
+                .iter()
+                .copied()
+                .collect()
         );
+
+        assert_eq!(TILE_2.reachable_coords_starting(), cells([(1, 3), (3, 2)]));
     }
 
     #[test]
     fn reachable_coords() {
         assert_eq!(
             TILE_1A.reachable_coords(),
-            [
-                [true, true, true, true],
-                [true, true, true, true],
-                [true, true, true, true],
-                [true, true, true, false],
-            ]
+            CellSet::ALL.difference(cells([(3, 3)]))
         );
 
         assert_eq!(
             TILE_2.reachable_coords(),
-            [
-                [true, false, false, false],
-                [true, false, false, true],
-                [false, false, true, true],
-                [false, true, true, true],
-            ]
+            cells([
+                (0, 0),
+                (0, 1),
+                (3, 1),
+                (2, 2),
+                (3, 2),
+                (1, 3),
+                (2, 3),
+                (3, 3),
+            ])
         );
     }
 }
@@ -0,0 +1,343 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How a `Tile` is turned: mirrored left to right first if `mirrored`, then rotated
+/// counterclockwise by `left_turns` quarter turns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "(u8, bool)", into = "(u8, bool)")]
+pub struct Orientation {
+    left_turns: u8,
+    mirrored: bool,
+}
+
+impl Orientation {
+    pub const IDENTITY: Self = Self {
+        left_turns: 0,
+        mirrored: false,
+    };
+
+    /// Flip left to right, without rotating
+    pub const MIRROR: Self = Self {
+        left_turns: 0,
+        mirrored: true,
+    };
+
+    const NUM_TURNS: u8 = 4;
+
+    /// All eight orientations, the unmirrored ones first
+    pub const ALL: [Self; 8] = {
+        let mut all = [Self::IDENTITY; 8];
+        let mut idx = 0;
+        while idx < all.len() {
+            all[idx] = Self {
+                left_turns: idx as u8 % Self::NUM_TURNS,
+                mirrored: idx as u8 >= Self::NUM_TURNS,
+            };
+            idx += 1;
+        }
+        all
+    };
+
+    /// Counterclockwise rotation by `left_turns` quarter turns
+    pub const fn rotation(left_turns: u8) -> Self {
+        Self {
+            left_turns: left_turns % Self::NUM_TURNS,
+            mirrored: false,
+        }
+    }
+
+    pub fn left_turns(self) -> u8 {
+        self.left_turns
+    }
+
+    pub fn is_mirrored(self) -> bool {
+        self.mirrored
+    }
+
+    /// Rotation in degrees, counterclockwise
+    pub fn degrees(self) -> u16 {
+        self.left_turns as u16 * 90
+    }
+
+    /// `self` followed by `next`
+    pub fn then(self, next: Self) -> Self {
+        // a mirror turns the rotation before it the other way
+        let turns = if next.mirrored {
+            Self::NUM_TURNS - self.left_turns
+        } else {
+            self.left_turns
+        };
+        Self {
+            left_turns: (next.left_turns + turns) % Self::NUM_TURNS,
+            mirrored: self.mirrored != next.mirrored,
+        }
+    }
+
+    /// Orientation that undoes `self`
+    pub fn inverse(self) -> Self {
+        let left_turns = if self.mirrored {
+            self.left_turns
+        } else {
+            (Self::NUM_TURNS - self.left_turns) % Self::NUM_TURNS
+        };
+        Self {
+            left_turns,
+            mirrored: self.mirrored,
+        }
+    }
+
+    /// `self` followed by a quarter turn
+    pub fn rotated(self, spin: SpinDirection) -> Self {
+        let turn = match spin {
+            SpinDirection::CounterClockwise => 1,
+            SpinDirection::Clockwise => Self::NUM_TURNS - 1,
+        };
+        self.then(Self::rotation(turn))
+    }
+
+    /// `self` followed by a mirror
+    pub fn mirrored(self) -> Self {
+        self.then(Self::MIRROR)
+    }
+
+    /// Quarter turns to apply after mirroring
+    fn turns(self) -> impl Iterator<Item = SpinDirection> {
+        (0..self.left_turns).map(|_| SpinDirection::CounterClockwise)
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°", self.degrees())?;
+        if self.mirrored {
+            write!(f, " mirrored")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<(u8, bool)> for Orientation {
+    type Error = String;
+
+    fn try_from((left_turns, mirrored): (u8, bool)) -> Result<Self, Self::Error> {
+        if left_turns < Self::NUM_TURNS {
+            Ok(Self {
+                left_turns,
+                mirrored,
+            })
+        } else {
+            Err(format!(
+                "Orientation with {left_turns} left turns out of range"
+            ))
+        }
+    }
+}
+
+impl From<Orientation> for (u8, bool) {
+    fn from(orientation: Orientation) -> Self {
+        (orientation.left_turns, orientation.mirrored)
+    }
+}
+
+impl Tile {
+    /// Flip the tile left to right
+    pub fn mirror(&mut self) {
+        mirror_2d_array(&mut self.cell_grid);
+        mirror_2d_array(&mut self.horz_walls);
+        mirror_2d_array(&mut self.vert_walls);
+        for esc in self.escalators.iter_mut() {
+            esc.mirror();
+        }
+    }
+
+    pub fn orient(&mut self, orientation: Orientation) {
+        if orientation.mirrored {
+            self.mirror();
+        }
+        for spin in orientation.turns() {
+            self.rotate(spin);
+        }
+    }
+
+    pub fn as_oriented(&self, orientation: Orientation) -> Self {
+        let mut tile = self.clone();
+        tile.orient(orientation);
+        tile
+    }
+}
+
+/// Reverse each row of `arr`
+pub fn mirror_2d_array<T, const WIDTH: usize, const HEIGHT: usize>(arr: &mut [[T; WIDTH]; HEIGHT]) {
+    for row in arr.iter_mut() {
+        row.reverse();
+    }
+}
+
+impl TileGridCoord {
+    pub fn as_mirrored(self) -> Self {
+        Self {
+            x: Tile::CELL_GRID_WIDTH - 1 - self.x,
+            y: self.y,
+        }
+    }
+
+    pub fn orient(&mut self, orientation: Orientation) {
+        *self = self.as_oriented(orientation);
+    }
+
+    pub fn as_oriented(self, orientation: Orientation) -> Self {
+        let start = if orientation.mirrored {
+            self.as_mirrored()
+        } else {
+            self
+        };
+        orientation
+            .turns()
+            .fold(start, |coord, spin| coord.as_rotated(spin))
+    }
+}
+
+impl CartesianDirection {
+    pub fn as_rotated(self, spin: SpinDirection) -> Self {
+        match (self, spin) {
+            (Self::Right, SpinDirection::CounterClockwise) => Self::Up,
+            (Self::Up, SpinDirection::CounterClockwise) => Self::Left,
+            (Self::Left, SpinDirection::CounterClockwise) => Self::Down,
+            (Self::Down, SpinDirection::CounterClockwise) => Self::Right,
+            (dir, SpinDirection::Clockwise) => {
+                dir.opposite().as_rotated(SpinDirection::CounterClockwise)
+            }
+        }
+    }
+
+    pub fn as_mirrored(self) -> Self {
+        match self {
+            Self::Right | Self::Left => self.opposite(),
+            Self::Up | Self::Down => self,
+        }
+    }
+
+    pub fn as_oriented(self, orientation: Orientation) -> Self {
+        let start = if orientation.mirrored {
+            self.as_mirrored()
+        } else {
+            self
+        };
+        orientation
+            .turns()
+            .fold(start, |dir, spin| dir.as_rotated(spin))
+    }
+}
+
+impl EscalatorLocation {
+    pub fn mirror(&mut self) {
+        for point in self.0.iter_mut() {
+            *point = point.as_mirrored();
+        }
+    }
+
+    pub fn orient(&mut self, orientation: Orientation) {
+        for point in self.0.iter_mut() {
+            point.orient(orientation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compose() {
+        crate::init_logging();
+        let coord = TileGridCoord::new(0, 1).unwrap();
+        for a in Orientation::ALL {
+            assert_eq!(a.then(a.inverse()), Orientation::IDENTITY);
+            assert_eq!(a.inverse().then(a), Orientation::IDENTITY);
+            assert_eq!(a.then(Orientation::IDENTITY), a);
+            for b in Orientation::ALL {
+                assert_eq!(
+                    coord.as_oriented(a.then(b)),
+                    coord.as_oriented(a).as_oriented(b)
+                );
+                for dir in CartesianDirection::ALL_DIRECTIONS {
+                    assert_eq!(
+                        dir.as_oriented(a.then(b)),
+                        dir.as_oriented(a).as_oriented(b)
+                    );
+                }
+            }
+        }
+
+        let turned = Orientation::IDENTITY.rotated(SpinDirection::Clockwise);
+        assert_eq!(turned, Orientation::rotation(3));
+        assert_eq!(turned.to_string(), "270°");
+        assert_eq!(turned.mirrored().to_string(), "90° mirrored");
+        assert_eq!(Orientation::rotation(5), Orientation::rotation(1));
+
+        // no two orientations move every cell the same way
+        let all: Vec<Vec<TileGridCoord>> = Orientation::ALL
+            .iter()
+            .map(|o| TileGridCoord::all().map(|c| c.as_oriented(*o)).collect())
+            .collect();
+        for (idx, moved) in all.iter().enumerate() {
+            assert!(!all[idx + 1..].contains(moved));
+        }
+    }
+
+    #[test]
+    fn orient_tile() {
+        crate::init_logging();
+        for (name, tile) in game_tileset().unwrap() {
+            for orientation in Orientation::ALL {
+                let oriented = tile.as_oriented(orientation);
+                for coord in TileGridCoord::all() {
+                    let moved = coord.as_oriented(orientation);
+                    assert_eq!(
+                        oriented.cell_value(moved),
+                        tile.cell_value(coord),
+                        "{name} {orientation}"
+                    );
+                    for dir in CartesianDirection::ALL_DIRECTIONS {
+                        assert_eq!(
+                            oriented.cell_wall(moved, dir.as_oriented(orientation)),
+                            tile.cell_wall(coord, dir),
+                            "{name} {orientation}"
+                        );
+                    }
+                }
+                let mut escalators = tile.escalators().clone();
+                for esc in escalators.iter_mut() {
+                    esc.orient(orientation);
+                }
+                assert_eq!(oriented.escalators(), &escalators);
+                assert_eq!(
+                    oriented.entrance(),
+                    tile.entrance().map(|(coord, dir)| (
+                        coord.as_oriented(orientation),
+                        dir.as_oriented(orientation)
+                    ))
+                );
+
+                assert_eq!(oriented.as_oriented(orientation.inverse()), tile);
+                assert_eq!(
+                    oriented.reachable_coords().len(),
+                    tile.reachable_coords().len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn serde() {
+        crate::init_logging();
+        for orientation in Orientation::ALL {
+            let s = ron::to_string(&orientation).unwrap();
+            assert_eq!(ron::from_str::<Orientation>(&s).unwrap(), orientation);
+        }
+        assert!(ron::from_str::<Orientation>("(4, false)").is_err());
+    }
+}
@@ -93,6 +93,13 @@ impl InvalidEscalator {
     }
 }
 
+/// Tiles from the physical game
+pub const GAME_TILES_STR: &str = include_str!("../../game-tiles.txt");
+
+pub fn game_tileset() -> Result<Vec<(String, Tile)>, TileParsingError> {
+    tileset_from_str(GAME_TILES_STR)
+}
+
 pub fn tileset_from_str(s: &str) -> Result<Vec<(String, Tile)>, TileParsingError> {
     info!("Parsing tileset");
     tileset_from_lines(s.lines())
@@ -183,6 +190,120 @@ pub(super) fn tile_from_str(s: &str) -> Result<Tile, TileParsingError> {
     tile_from_lines(&mut lines)
 }
 
+/// Tiles in the format read by [`tileset_from_str`], each followed by an empty line
+pub fn tileset_to_string<'a, I>(tileset: I) -> String
+where
+    I: IntoIterator<Item = &'a (String, Tile)>,
+{
+    tileset
+        .into_iter()
+        .map(|(name, tile)| format!("@{name}\n{}\n", tile_to_string(tile)))
+        .collect()
+}
+
+/// Like [`tileset_to_string`], but with the comments of `original`, the tile file being
+/// rewritten: the comments before a tile stay before the tile of the same name, and the
+/// ones after the last tile stay at the end
+pub fn tileset_to_string_with_comments(tileset: &[(String, Tile)], original: &str) -> String {
+    let mut comments: Vec<(Option<&str>, String)> = Vec::new();
+    let mut block = String::new();
+    for line in original.lines() {
+        if skip_tile_line(line.as_bytes()) {
+            // blank lines only count between comments
+            if !block.is_empty() || !line.is_empty() {
+                block.push_str(line);
+                block.push('\n');
+            }
+        } else if let Some(name) = line.strip_prefix('@') {
+            comments.push((Some(name), std::mem::take(&mut block)));
+        } else {
+            block.clear();
+        }
+    }
+    comments.push((None, block.trim_end().to_string()));
+
+    let comments_before = |name: Option<&str>| {
+        comments
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or("", |(_, block)| block.as_str())
+    };
+    let mut s = String::new();
+    for (name, tile) in tileset {
+        s.push_str(comments_before(Some(name)));
+        s.push_str(&format!("@{name}\n{}\n", tile_to_string(tile)));
+    }
+    let trailer = comments_before(None);
+    if !trailer.is_empty() {
+        s.push_str(trailer);
+        s.push('\n');
+    }
+    s
+}
+
+/// Tile in the format read by `Tile::from_str`; used cell items are written as available
+pub fn tile_to_string(tile: &Tile) -> String {
+    let mut s = String::new();
+    for (row_idx, walls) in tile.horz_walls.iter().enumerate() {
+        s.push('+');
+        for wall in walls {
+            s.push(wall_token(*wall, RowType::Wall));
+            s.push('+');
+        }
+        s.push('\n');
+
+        let (Some(walls), Some(cells)) =
+            (tile.vert_walls.get(row_idx), tile.cell_grid.get(row_idx))
+        else {
+            continue;
+        };
+        s.push(wall_token(walls[0], RowType::Cell));
+        for (cell, wall) in cells.iter().zip(walls[1..].iter()) {
+            s.push(cell_token(*cell));
+            s.push(wall_token(*wall, RowType::Cell));
+        }
+        s.push('\n');
+    }
+
+    if !tile.escalators.is_empty() {
+        let escalators: Vec<String> = tile
+            .escalators
+            .iter()
+            .map(|EscalatorLocation([a, b])| format!("{}{}-{}{}", a.x, a.y, b.x, b.y))
+            .collect();
+        s.push_str(&format!("E: {}\n", escalators.join(", ")));
+    }
+    s
+}
+
+fn pawn_digit(pawn: Pawn, first: u8) -> char {
+    (first + pawn.idx() as u8) as char
+}
+
+fn cell_token(cell: TileCell) -> char {
+    match cell {
+        TileCell::Empty => ' ',
+        TileCell::Warp(pawn) => pawn_digit(pawn, b'1'),
+        TileCell::Loot(pawn) => ['g', 'o', 'y', 'p'][pawn.idx()],
+        TileCell::FinalExit(pawn) => ['G', 'O', 'Y', 'P'][pawn.idx()],
+        TileCell::TimerFlip(_) => 't',
+        TileCell::Camera(_) => 'c',
+        TileCell::CrystalBall(_) => 'b',
+    }
+}
+
+/// `row_type` is the kind of row the wall is written in
+fn wall_token(wall: WallState, row_type: RowType) -> char {
+    match (wall, row_type) {
+        (WallState::Blocked, RowType::Wall) => '-',
+        (WallState::Blocked, RowType::Cell) => '|',
+        (WallState::Open, _) => ' ',
+        (WallState::OrangeOnly, _) => '$',
+        (WallState::Explore(pawn), _) => pawn_digit(pawn, b'5'),
+        (WallState::Entrance, _) => '^',
+    }
+}
+
 fn skip_tile_line(line: &[u8]) -> bool {
     matches!(line.first(), None | Some(b'#'))
 }
@@ -535,6 +656,53 @@ E: 01-23, 00-33, 33-02
         );
     }
 
+    #[test]
+    fn game_tiles() {
+        crate::init_logging();
+
+        let tileset = game_tileset().unwrap();
+        assert_eq!(tileset.len(), 25);
+        assert_eq!(tileset[0].0, "1a");
+    }
+
+    #[test]
+    fn round_trip() {
+        crate::init_logging();
+
+        for (tile_str, tile) in [(TILE1_STR, &TILE1), (TILE2_STR, &TILE2)] {
+            assert_eq!(tile_to_string(tile), tile_str.trim_start());
+        }
+        assert_eq!(tile_to_string(&TILE3).parse::<Tile>().as_ref(), Ok(&*TILE3));
+
+        let tileset = game_tileset().unwrap();
+        assert_eq!(tileset_from_str(&tileset_to_string(&tileset)), Ok(tileset));
+    }
+
+    #[test]
+    fn keep_comments() {
+        crate::init_logging();
+        let comment_lines = |s: &str| -> Vec<String> {
+            s.lines()
+                .filter(|line| line.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        };
+
+        let tileset = game_tileset().unwrap();
+        let s = tileset_to_string_with_comments(&tileset, GAME_TILES_STR);
+        assert_eq!(tileset_from_str(&s).as_ref(), Ok(&tileset));
+        assert_eq!(comment_lines(&s), comment_lines(GAME_TILES_STR));
+        assert_eq!(tileset_to_string_with_comments(&tileset, &s), s);
+
+        // comments move with their tile
+        let original = format!("# one\n@tile1\n{TILE1_STR}\n# two\n\n@tile2\n{TILE2_STR}# end\n");
+        let swapped = [("tile2".to_string(), TILE2), ("tile1".to_string(), TILE1)];
+        let s = tileset_to_string_with_comments(&swapped, &original);
+        assert_eq!(comment_lines(&s), ["# two", "# one", "# end"]);
+        assert!(s.starts_with("# two\n\n@tile2\n"), "{s}");
+        assert_eq!(tileset_from_str(&s), Ok(swapped.to_vec()));
+    }
+
     #[test]
     fn tile_negative() {
         crate::init_logging();
@@ -1,8 +1,10 @@
 use super::TileTokenParse;
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
 /// Whether a wall exists or not
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum WallState {
     Open,
     Blocked,
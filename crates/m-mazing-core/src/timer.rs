@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// Sand timer; flipping it swaps the sand that has run out with the sand that is left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandTimer {
+    duration: Duration,
+    remaining: Duration,
+    running: bool,
+}
+
+impl SandTimer {
+    /// Length of the timer in the board game
+    pub const DEFAULT_DURATION: Duration = Duration::from_secs(3 * 60);
+
+    /// Full timer that is not running yet
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            remaining: duration,
+            running: false,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining.is_zero()
+    }
+
+    pub fn start(&mut self) {
+        self.running = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.running = false;
+    }
+
+    pub fn flip(&mut self) {
+        self.remaining = self.duration.saturating_sub(self.remaining);
+    }
+
+    /// Let `elapsed` time pass; returns true if the timer just ran out
+    pub fn tick(&mut self, elapsed: Duration) -> bool {
+        if !self.running || self.is_expired() {
+            return false;
+        }
+        self.remaining = self.remaining.saturating_sub(elapsed);
+        self.is_expired()
+    }
+}
+
+impl Default for SandTimer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tick_and_flip() {
+        crate::init_logging();
+        let mut timer = SandTimer::new(Duration::from_secs(10));
+
+        assert!(!timer.tick(Duration::from_secs(3)));
+        assert_eq!(timer.remaining(), Duration::from_secs(10));
+
+        timer.start();
+        assert!(!timer.tick(Duration::from_secs(3)));
+        assert_eq!(timer.remaining(), Duration::from_secs(7));
+
+        timer.flip();
+        assert_eq!(timer.remaining(), Duration::from_secs(3));
+
+        timer.pause();
+        assert!(!timer.tick(Duration::from_secs(5)));
+        timer.start();
+        assert!(timer.tick(Duration::from_secs(5)));
+        assert!(timer.is_expired());
+
+        // only reports running out once
+        assert!(!timer.tick(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,285 @@
+use std::io::{Read, Write};
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::PROTOCOL_VERSION;
+
+/// First bytes sent by both peers
+pub const MAGIC: [u8; 4] = *b"MMAZ";
+
+/// Largest allowed message payload in bytes
+pub const MAX_FRAME_LEN: u32 = 1 << 20;
+
+const LEN_PREFIX_SIZE: usize = std::mem::size_of::<u32>();
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to encode or decode message: {0}")]
+    Codec(#[from] bincode::Error),
+
+    #[error("Frame of {len} bytes exceeds limit of {max} bytes")]
+    FrameTooLarge { len: u32, max: u32 },
+
+    #[error("Peer does not speak the m-mazing protocol; got magic {0:?}")]
+    BadMagic([u8; 4]),
+
+    #[error("Protocol version mismatch: ours is {ours}, peer's is {theirs}")]
+    VersionMismatch { ours: u16, theirs: u16 },
+}
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(MAX_FRAME_LEN.into())
+}
+
+/// Magic and protocol version; exchanged before any message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handshake {
+    pub version: u16,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+        }
+    }
+}
+
+impl Handshake {
+    pub const SIZE: usize = MAGIC.len() + std::mem::size_of::<u16>();
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        bytes[MAGIC.len()..].copy_from_slice(&self.version.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Result<Self, ProtocolError> {
+        let (magic, version) = bytes.split_at(MAGIC.len());
+        let magic: [u8; 4] = magic.try_into().expect("magic length");
+        if magic != MAGIC {
+            return Err(ProtocolError::BadMagic(magic));
+        }
+        let version = u16::from_be_bytes(version.try_into().expect("version length"));
+        Ok(Self { version })
+    }
+
+    /// Check that the peer's handshake is compatible with ours
+    pub fn check(self) -> Result<(), ProtocolError> {
+        if self.version != PROTOCOL_VERSION {
+            return Err(ProtocolError::VersionMismatch {
+                ours: PROTOCOL_VERSION,
+                theirs: self.version,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W) -> Result<(), ProtocolError> {
+        writer.write_all(&self.to_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, ProtocolError> {
+        let mut bytes = [0; Self::SIZE];
+        reader.read_exact(&mut bytes)?;
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Send our handshake, then read and check the peer's
+pub fn handshake<S: Read + Write>(stream: &mut S) -> Result<(), ProtocolError> {
+    Handshake::default().write(stream)?;
+    Handshake::read(stream)?.check()
+}
+
+/// Message payload prefixed by its big-endian `u32` length
+pub fn encode<M: Serialize>(msg: &M) -> Result<Vec<u8>, ProtocolError> {
+    let payload = bincode_options().serialize(msg)?;
+    let len = payload.len() as u32;
+    if len > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge {
+            len,
+            max: MAX_FRAME_LEN,
+        });
+    }
+
+    let mut frame = Vec::with_capacity(LEN_PREFIX_SIZE + payload.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+fn decode_payload<M: DeserializeOwned>(payload: &[u8]) -> Result<M, ProtocolError> {
+    Ok(bincode_options().deserialize(payload)?)
+}
+
+fn check_len(len: u32) -> Result<usize, ProtocolError> {
+    if len > MAX_FRAME_LEN {
+        return Err(ProtocolError::FrameTooLarge {
+            len,
+            max: MAX_FRAME_LEN,
+        });
+    }
+    Ok(len as usize)
+}
+
+pub fn write_message<W: Write, M: Serialize>(writer: &mut W, msg: &M) -> Result<(), ProtocolError> {
+    writer.write_all(&encode(msg)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Block until a whole message has been read
+pub fn read_message<R: Read, M: DeserializeOwned>(reader: &mut R) -> Result<M, ProtocolError> {
+    let mut len_bytes = [0; LEN_PREFIX_SIZE];
+    reader.read_exact(&mut len_bytes)?;
+    let len = check_len(u32::from_be_bytes(len_bytes))?;
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    decode_payload(&payload)
+}
+
+/// Decodes messages from bytes as they arrive, for non-blocking transports
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Next complete message, if one has been fully received
+    pub fn next_message<M: DeserializeOwned>(&mut self) -> Result<Option<M>, ProtocolError> {
+        let Some(len_bytes) = self.buf.get(..LEN_PREFIX_SIZE) else {
+            return Ok(None);
+        };
+        let len = check_len(u32::from_be_bytes(
+            len_bytes.try_into().expect("prefix length"),
+        ))?;
+        let frame_len = LEN_PREFIX_SIZE + len;
+        if self.buf.len() < frame_len {
+            return Ok(None);
+        }
+
+        let msg = decode_payload(&self.buf[LEN_PREFIX_SIZE..frame_len]);
+        self.buf.drain(..frame_len);
+        msg.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn handshake_ok() {
+        let bytes = Handshake::default().to_bytes();
+        assert_eq!(&bytes[..4], b"MMAZ");
+        let parsed = Handshake::from_bytes(bytes).unwrap();
+        assert_eq!(parsed, Handshake::default());
+        parsed.check().unwrap();
+    }
+
+    #[test]
+    fn handshake_bad_magic() {
+        let mut bytes = Handshake::default().to_bytes();
+        bytes[0] = b'X';
+        assert!(matches!(
+            Handshake::from_bytes(bytes),
+            Err(ProtocolError::BadMagic(_))
+        ));
+    }
+
+    #[test]
+    fn handshake_version_mismatch() {
+        let theirs = PROTOCOL_VERSION + 1;
+        let mut stream = Cursor::new(Handshake { version: theirs }.to_bytes().to_vec());
+        let err = Handshake::read(&mut stream).unwrap().check().unwrap_err();
+        assert!(matches!(
+            err,
+            ProtocolError::VersionMismatch { ours, theirs: t }
+                if ours == PROTOCOL_VERSION && t == theirs
+        ));
+    }
+
+    #[test]
+    fn stream_round_trip() {
+        let msgs = [
+            ClientMessage::Ping { nonce: 1 },
+            ClientMessage::CreateRoom {
+                name: "alice".to_string(),
+            },
+        ];
+        let mut buf = Vec::new();
+        for msg in msgs.iter() {
+            write_message(&mut buf, msg).unwrap();
+        }
+
+        let mut reader = Cursor::new(buf);
+        for msg in msgs.iter() {
+            let read: ClientMessage = read_message(&mut reader).unwrap();
+            assert_eq!(&read, msg);
+        }
+        assert!(matches!(
+            read_message::<_, ClientMessage>(&mut reader),
+            Err(ProtocolError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn decoder_partial_frames() {
+        let frame = encode(&ServerMessage::Pong { nonce: 42 }).unwrap();
+        let mut decoder = FrameDecoder::default();
+
+        // byte at a time
+        for byte in frame.iter() {
+            assert_eq!(decoder.next_message::<ServerMessage>().unwrap(), None);
+            decoder.extend(&[*byte]);
+        }
+        assert_eq!(
+            decoder.next_message::<ServerMessage>().unwrap(),
+            Some(ServerMessage::Pong { nonce: 42 })
+        );
+
+        // two frames at once
+        decoder.extend(&[frame.clone(), frame].concat());
+        for _ in 0..2 {
+            assert!(decoder.next_message::<ServerMessage>().unwrap().is_some());
+        }
+        assert_eq!(decoder.next_message::<ServerMessage>().unwrap(), None);
+    }
+
+    #[test]
+    fn frame_too_large() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&(MAX_FRAME_LEN + 1).to_be_bytes());
+        assert!(matches!(
+            decoder.next_message::<ServerMessage>(),
+            Err(ProtocolError::FrameTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_payload() {
+        let mut frame = 2u32.to_be_bytes().to_vec();
+        frame.extend_from_slice(&[0xff, 0xff]);
+        assert!(matches!(
+            read_message::<_, ServerMessage>(&mut Cursor::new(frame)),
+            Err(ProtocolError::Codec(_))
+        ));
+    }
+}
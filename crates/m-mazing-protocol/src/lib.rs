@@ -0,0 +1,19 @@
+//! Messages exchanged between game clients and the server.
+//!
+//! A connection starts with both peers sending a [`codec::Handshake`]. After
+//! that, every message is a length-prefixed bincode frame: clients send
+//! [`message::ClientMessage`]s and the server sends [`message::ServerMessage`]s.
+
+pub mod codec;
+pub mod message;
+pub mod prediction;
+
+pub mod prelude {
+    pub use crate::codec::*;
+    pub use crate::message::*;
+    pub use crate::prediction::*;
+    pub use crate::PROTOCOL_VERSION;
+}
+
+/// Bumped on every incompatible change to the handshake, framing or messages
+pub const PROTOCOL_VERSION: u16 = 10;
@@ -0,0 +1,568 @@
+use serde::{Deserialize, Serialize};
+
+use m_mazing_core::prelude::*;
+
+/// Client to server message.
+///
+/// Any change to a message must bump [`crate::PROTOCOL_VERSION`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Create a room and become its host; answered by [`ServerMessage::RoomJoined`]
+    CreateRoom {
+        name: String,
+    },
+
+    /// Join an existing room; answered by [`ServerMessage::RoomJoined`]
+    JoinRoom {
+        code: RoomCode,
+        name: String,
+    },
+
+    /// Take back the seat of a dropped connection; answered by [`ServerMessage::RoomJoined`].
+    ///
+    /// `next_event` is the number of game events the client already has.
+    Reconnect {
+        code: RoomCode,
+        token: SessionToken,
+        next_event: u64,
+    },
+
+    LeaveRoom,
+
+    /// Host only; resets everyone's ready flag
+    Configure(RoomConfig),
+
+    SetReady(bool),
+
+    /// Host only; everyone must be ready and every seat taken
+    StartGame,
+
+    /// Host only; remove a member from the room
+    Kick(MemberId),
+
+    /// Host only; make another member the host
+    TransferHost(MemberId),
+
+    /// Submit an action; `seq` is echoed back if the action is rejected
+    SubmitAction {
+        seq: u32,
+        action: Action,
+    },
+
+    /// Answered by [`ServerMessage::Pong`] with the same `nonce`
+    Ping {
+        nonce: u64,
+    },
+}
+
+/// Server to client message.
+///
+/// Any change to a message must bump [`crate::PROTOCOL_VERSION`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Recipient is now in `room` as member `you`.
+    ///
+    /// `token` lets the recipient reconnect if its connection drops during a game.
+    RoomJoined {
+        you: MemberId,
+        room: RoomInfo,
+        token: SessionToken,
+    },
+
+    /// Room changed; sent to every member
+    RoomUpdate(RoomInfo),
+
+    /// Recipient is no longer in a room
+    RoomLeft {
+        kicked: bool,
+    },
+
+    /// Seat and allowed actions of the player once the game starts
+    RoleAssignment {
+        player: PlayerId,
+        num_players: u8,
+
+        /// Rule book scenario number; see [`Scenario::builtin`]
+        scenario: u8,
+
+        /// Seed to set up the game with, so every client gets the same pile and roles
+        seed: u64,
+        actions: Vec<BoardAction>,
+    },
+
+    /// Authoritative events in the order they must be applied.
+    ///
+    /// `first_idx` is the index of the first event since the start of the game.
+    Events {
+        first_idx: u64,
+        events: Vec<GameEvent>,
+
+        /// `seq` of the recipient's own action that caused the events
+        acked: Option<u32>,
+    },
+
+    /// Game state after the first `num_events` events; sent on reconnect.
+    ///
+    /// Replaces any state built from earlier events.
+    Snapshot {
+        state: Box<GameState>,
+        num_events: u64,
+
+        /// `seq` of the recipient's last action in the snapshot; the ones before it are
+        /// in it as well
+        acked: Option<u32>,
+    },
+
+    TimerSync(TimerSync),
+
+    Pong {
+        nonce: u64,
+    },
+
+    Error(ServerError),
+}
+
+/// Identifies a connected client within the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MemberId(pub u64);
+
+/// Secret that identifies a member across connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionToken(pub u64);
+
+/// Short code that players share to join the same room
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RoomCode(String);
+
+impl RoomCode {
+    pub const LEN: usize = 4;
+
+    /// Letters used in codes; I and O are left out since they look like digits
+    pub const ALPHABET: &'static [u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+    /// Code typed by a player; case and surrounding whitespace are ignored
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        let valid = code.len() == Self::LEN && code.bytes().all(|c| Self::ALPHABET.contains(&c));
+        valid.then_some(Self(code))
+    }
+
+    /// Code made of `ALPHABET` letters picked by `pick`, which gets the alphabet length
+    pub fn generate(mut pick: impl FnMut(usize) -> usize) -> Self {
+        Self(
+            (0..Self::LEN)
+                .map(|_| Self::ALPHABET[pick(Self::ALPHABET.len())] as char)
+                .collect(),
+        )
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RoomCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for RoomCode {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Self::parse(&code).ok_or_else(|| format!("Invalid room code {code:?}"))
+    }
+}
+
+impl From<RoomCode> for String {
+    fn from(code: RoomCode) -> Self {
+        code.0
+    }
+}
+
+/// Game settings chosen by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomConfig {
+    /// Rule book scenario number; see [`Scenario::builtin`]
+    pub scenario: u8,
+    pub num_players: u8,
+
+    /// Stop the sand timer while a seat has no connected player
+    pub pause_when_vacant: bool,
+
+    /// Let players alert each other with the "Do Something!" pawn
+    pub do_something: bool,
+
+    /// Bots of this difficulty play the seats nobody takes, including seats left during
+    /// the game; without bots, every seat needs a member to start
+    pub bots: Option<BotDifficulty>,
+}
+
+impl Default for RoomConfig {
+    fn default() -> Self {
+        Self {
+            scenario: 1,
+            num_players: 1,
+            pause_when_vacant: false,
+            do_something: true,
+            bots: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomMember {
+    pub id: MemberId,
+    pub name: String,
+    pub ready: bool,
+
+    /// Unset while the member's seat is held for them to reconnect
+    pub connected: bool,
+}
+
+/// Everything members see about a room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub code: RoomCode,
+    pub host: MemberId,
+
+    /// In order of joining
+    pub members: Vec<RoomMember>,
+    pub config: RoomConfig,
+
+    /// A game is being played; set until it is won or lost
+    pub in_game: bool,
+}
+
+/// State of the sand timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerSync {
+    pub remaining_ms: u32,
+    pub running: bool,
+}
+
+impl From<&SandTimer> for TimerSync {
+    fn from(timer: &SandTimer) -> Self {
+        Self {
+            remaining_ms: timer.remaining().as_millis().try_into().unwrap_or(u32::MAX),
+            running: timer.is_running(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerErrorKind {
+    /// Message is only allowed in a room
+    NotInRoom,
+
+    /// Leave the current room first
+    AlreadyInRoom,
+
+    /// No room with the given code
+    UnknownRoom,
+
+    /// No free seat
+    RoomFull,
+
+    /// Message is only allowed for the host
+    NotHost,
+
+    /// No member with the given id in the room
+    UnknownMember,
+
+    /// Reconnect token does not match a dropped member of the room
+    UnknownSession,
+
+    /// Unknown scenario or unsupported number of players
+    InvalidConfig,
+
+    /// Not every seat is taken by a ready member
+    NotReady,
+
+    /// Not allowed while a game is in progress
+    GameInProgress,
+
+    /// Action with sequence number `seq` was not applied
+    ActionRejected { seq: u32 },
+
+    /// Message could not be decoded
+    Malformed,
+}
+
+/// Error response with a human readable message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerError {
+    pub kind: ServerErrorKind,
+    pub msg: String,
+}
+
+impl ServerError {
+    pub fn new(kind: ServerErrorKind, msg: impl Into<String>) -> Self {
+        Self {
+            kind,
+            msg: msg.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::*;
+
+    /// Fails to compile when a message is added without a round-trip test
+    fn client_variant_idx(msg: &ClientMessage) -> usize {
+        match msg {
+            ClientMessage::CreateRoom { .. } => 0,
+            ClientMessage::JoinRoom { .. } => 1,
+            ClientMessage::Reconnect { .. } => 2,
+            ClientMessage::LeaveRoom => 3,
+            ClientMessage::Configure(_) => 4,
+            ClientMessage::SetReady(_) => 5,
+            ClientMessage::StartGame => 6,
+            ClientMessage::Kick(_) => 7,
+            ClientMessage::TransferHost(_) => 8,
+            ClientMessage::SubmitAction { .. } => 9,
+            ClientMessage::Ping { .. } => 10,
+        }
+    }
+
+    /// Fails to compile when a message is added without a round-trip test
+    fn server_variant_idx(msg: &ServerMessage) -> usize {
+        match msg {
+            ServerMessage::RoomJoined { .. } => 0,
+            ServerMessage::RoomUpdate(_) => 1,
+            ServerMessage::RoomLeft { .. } => 2,
+            ServerMessage::RoleAssignment { .. } => 3,
+            ServerMessage::Events { .. } => 4,
+            ServerMessage::Snapshot { .. } => 5,
+            ServerMessage::TimerSync(_) => 6,
+            ServerMessage::Pong { .. } => 7,
+            ServerMessage::Error(_) => 8,
+        }
+    }
+
+    fn round_trip<M>(msg: &M)
+    where
+        M: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let frame = encode(msg).unwrap();
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&frame);
+        assert_eq!(decoder.next_message::<M>().unwrap().as_ref(), Some(msg));
+    }
+
+    fn placed_tile() -> PlacedTile {
+        let tileset = game_tileset().unwrap();
+        let (name, tile) = tileset[1].clone();
+        PlacedTile {
+            name,
+            tile,
+            orientation: Orientation::IDENTITY,
+            origin: BoardCoord::new(1, -4),
+        }
+    }
+
+    fn game_state() -> GameState {
+        let tileset = game_tileset().unwrap();
+        let mut state = GameState::new(3, &Scenario::builtin(2).unwrap(), &tileset, 5).unwrap();
+        state.apply_event(&GameEvent::TileExplored(placed_tile()));
+        state.apply_event(&GameEvent::LootStolen);
+        state
+    }
+
+    fn code() -> RoomCode {
+        RoomCode::parse("ABCD").unwrap()
+    }
+
+    fn room_info() -> RoomInfo {
+        RoomInfo {
+            code: code(),
+            host: MemberId(3),
+            members: vec![
+                RoomMember {
+                    id: MemberId(3),
+                    name: "host".to_string(),
+                    ready: true,
+                    connected: true,
+                },
+                RoomMember {
+                    id: MemberId(8),
+                    name: "guest".to_string(),
+                    ready: false,
+                    connected: false,
+                },
+            ],
+            config: RoomConfig {
+                scenario: 2,
+                num_players: 4,
+                pause_when_vacant: true,
+                do_something: false,
+                bots: None,
+            },
+            in_game: false,
+        }
+    }
+
+    #[test]
+    fn client_round_trip() {
+        let msgs = [
+            ClientMessage::CreateRoom {
+                name: "Ørjan 🧙".to_string(),
+            },
+            ClientMessage::JoinRoom {
+                code: code(),
+                name: "guest".to_string(),
+            },
+            ClientMessage::Reconnect {
+                code: code(),
+                token: SessionToken(u64::MAX - 1),
+                next_event: 5,
+            },
+            ClientMessage::LeaveRoom,
+            ClientMessage::Configure(RoomConfig {
+                scenario: 1,
+                num_players: 8,
+                pause_when_vacant: false,
+                do_something: true,
+                bots: Some(BotDifficulty::default()),
+            }),
+            ClientMessage::SetReady(true),
+            ClientMessage::StartGame,
+            ClientMessage::Kick(MemberId(1)),
+            ClientMessage::TransferHost(MemberId(2)),
+            ClientMessage::SubmitAction {
+                seq: 7,
+                action: Action::BoardAction(PawnAction {
+                    pawn: Pawn::Yellow,
+                    action: BoardAction::Slide(CartesianDirection::Left),
+                    dest: BoardCoord::new(-3, 2),
+                }),
+            },
+            ClientMessage::SubmitAction {
+                seq: 8,
+                action: Action::DoSomething(PlayerId(2)),
+            },
+            ClientMessage::Ping { nonce: u64::MAX },
+        ];
+
+        let mut covered = [false; 11];
+        for msg in msgs.iter() {
+            covered[client_variant_idx(msg)] = true;
+            round_trip(msg);
+        }
+        assert!(covered.iter().all(|c| *c));
+    }
+
+    #[test]
+    fn server_round_trip() {
+        let msgs = [
+            ServerMessage::RoomJoined {
+                you: MemberId(8),
+                room: room_info(),
+                token: SessionToken(42),
+            },
+            ServerMessage::RoomUpdate(room_info()),
+            ServerMessage::RoomLeft { kicked: true },
+            ServerMessage::RoleAssignment {
+                player: PlayerId(1),
+                num_players: 2,
+                scenario: 1,
+                seed: 0x0123_4567_89ab_cdef,
+                actions: game_roles(2).unwrap()[1].to_vec(),
+            },
+            ServerMessage::Events {
+                first_idx: 12,
+                events: vec![
+                    GameEvent::PawnMoved {
+                        pawn: Pawn::Green,
+                        from: BoardCoord::new(1, 1),
+                        to: BoardCoord::new(1, 0),
+                    },
+                    GameEvent::TileExplored(placed_tile()),
+                    GameEvent::LootStolen,
+                    GameEvent::PawnEscaped(Pawn::Purple),
+                    GameEvent::GameWon,
+                    GameEvent::TimerFlipped(BoardCoord::new(0, 0)),
+                    GameEvent::TimeUp,
+                    GameEvent::DoSomething {
+                        by: PlayerId(0),
+                        target: PlayerId(2),
+                    },
+                    GameEvent::DoSomethingAnswered(PlayerId(2)),
+                ],
+                acked: Some(7),
+            },
+            ServerMessage::Snapshot {
+                state: Box::new(game_state()),
+                num_events: 3,
+                acked: Some(7),
+            },
+            ServerMessage::TimerSync(TimerSync {
+                remaining_ms: 180_000,
+                running: true,
+            }),
+            ServerMessage::Pong { nonce: 3 },
+            ServerMessage::Error(ServerError::new(
+                ServerErrorKind::ActionRejected { seq: 7 },
+                "Pawn cannot move there",
+            )),
+        ];
+
+        let mut covered = [false; 9];
+        for msg in msgs.iter() {
+            covered[server_variant_idx(msg)] = true;
+            round_trip(msg);
+        }
+        assert!(covered.iter().all(|c| *c));
+
+        for kind in [
+            ServerErrorKind::NotInRoom,
+            ServerErrorKind::AlreadyInRoom,
+            ServerErrorKind::UnknownRoom,
+            ServerErrorKind::RoomFull,
+            ServerErrorKind::NotHost,
+            ServerErrorKind::UnknownMember,
+            ServerErrorKind::UnknownSession,
+            ServerErrorKind::InvalidConfig,
+            ServerErrorKind::NotReady,
+            ServerErrorKind::GameInProgress,
+            ServerErrorKind::Malformed,
+        ] {
+            round_trip(&ServerMessage::Error(ServerError::new(kind, "")));
+        }
+    }
+
+    #[test]
+    fn invalid_tile_grid_coord() {
+        // escalator end points are tile grid coordinates; (9, 9) is out of range
+        let frame = encode(&(9u8, 9u8)).unwrap();
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&frame);
+        assert!(decoder.next_message::<TileGridCoord>().is_err());
+    }
+
+    #[test]
+    fn room_code() {
+        assert_eq!(RoomCode::parse(" abcd\n").unwrap().as_str(), "ABCD");
+        assert!(RoomCode::parse("ABC").is_none());
+        assert!(RoomCode::parse("ABCDE").is_none());
+        // ambiguous letters are never generated, so never accepted
+        assert!(RoomCode::parse("OOPS").is_none());
+
+        let mut idx = 0;
+        let code = RoomCode::generate(|len| {
+            idx += 7;
+            idx % len
+        });
+        assert_eq!(RoomCode::parse(code.as_str()), Some(code));
+
+        // decoding validates the code
+        let frame = encode(&"IOIO".to_string()).unwrap();
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&frame);
+        assert!(decoder.next_message::<RoomCode>().is_err());
+    }
+}
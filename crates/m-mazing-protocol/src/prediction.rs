@@ -0,0 +1,317 @@
+use thiserror::Error;
+
+use m_mazing_core::prelude::*;
+
+use crate::message::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PredictionError {
+    #[error("Missed events: expected event {expected}, got {got}")]
+    MissedEvents { expected: u64, got: u64 },
+}
+
+/// Game of a networked client that shows its own actions before the server confirms them.
+///
+/// The server's event stream is the only truth. Local actions are kept as pending until
+/// the server acknowledges or rejects them, and are replayed on top of the confirmed state
+/// whenever it changes. A pending action that no longer applies, e.g. because another
+/// player's pawn got to the cell first, is rolled back. Since the server applies actions
+/// in a fixed order and the replay only depends on the confirmed state and the pending
+/// actions, every client ends up with the same outcome.
+#[derive(Clone, Debug)]
+pub struct PredictedGame {
+    player: PlayerId,
+
+    /// State after every event received from the server
+    confirmed: GameState,
+    num_confirmed_events: u64,
+
+    /// Submitted but not yet acknowledged, in submission order
+    pending: Vec<(u32, Action)>,
+
+    /// `confirmed` with `pending` applied
+    predicted: GameState,
+    next_seq: u32,
+}
+
+impl PredictedGame {
+    pub fn new(state: GameState, player: PlayerId) -> Self {
+        Self {
+            player,
+            predicted: state.clone(),
+            confirmed: state,
+            num_confirmed_events: 0,
+            pending: Vec::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// State to show to the player
+    pub fn state(&self) -> &GameState {
+        &self.predicted
+    }
+
+    /// State the server has confirmed
+    pub fn confirmed(&self) -> &GameState {
+        &self.confirmed
+    }
+
+    /// Number of events received from the server; needed to reconnect
+    pub fn num_confirmed_events(&self) -> u64 {
+        self.num_confirmed_events
+    }
+
+    /// Actions that are shown but not confirmed yet
+    pub fn pending(&self) -> impl Iterator<Item = &Action> {
+        self.pending.iter().map(|(_, action)| action)
+    }
+
+    /// Apply `action` locally; returns the message that submits it to the server
+    pub fn submit(&mut self, action: Action) -> Result<ClientMessage, ActionError> {
+        self.predicted.apply_action(self.player, &action)?;
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.pending.push((seq, action.clone()));
+        Ok(ClientMessage::SubmitAction { seq, action })
+    }
+
+    /// Reconcile with a message from the server; returns the pending actions that were
+    /// rolled back.
+    ///
+    /// Messages that do not concern the game are ignored.
+    pub fn handle(&mut self, msg: &ServerMessage) -> Result<Vec<Action>, PredictionError> {
+        match msg {
+            ServerMessage::Events {
+                first_idx,
+                events,
+                acked,
+            } => {
+                if *first_idx > self.num_confirmed_events {
+                    return Err(PredictionError::MissedEvents {
+                        expected: self.num_confirmed_events,
+                        got: *first_idx,
+                    });
+                }
+                // skip events that were already applied
+                let already_applied = (self.num_confirmed_events - first_idx) as usize;
+                for event in events.iter().skip(already_applied) {
+                    self.confirmed.apply_event(event);
+                    self.num_confirmed_events += 1;
+                }
+                if let Some(seq) = acked {
+                    self.pending.retain(|(pending_seq, _)| pending_seq != seq);
+                }
+            }
+            ServerMessage::Snapshot {
+                state,
+                num_events,
+                acked,
+            } => {
+                // acknowledged actions are part of the snapshot; the rest were lost
+                let num_acked = acked
+                    .and_then(|seq| {
+                        self.pending
+                            .iter()
+                            .position(|(pending_seq, _)| *pending_seq == seq)
+                    })
+                    .map_or(0, |idx| idx + 1);
+                self.pending.drain(..num_acked);
+                self.confirmed = state.as_ref().clone();
+                self.num_confirmed_events = *num_events;
+                self.predicted = self.confirmed.clone();
+                return Ok(self.pending.drain(..).map(|(_, action)| action).collect());
+            }
+            ServerMessage::Error(ServerError {
+                kind: ServerErrorKind::ActionRejected { seq },
+                ..
+            }) => {
+                let Some(idx) = self
+                    .pending
+                    .iter()
+                    .position(|(pending_seq, _)| pending_seq == seq)
+                else {
+                    return Ok(Vec::new());
+                };
+                let (_, action) = self.pending.remove(idx);
+                let mut rolled_back = vec![action];
+                rolled_back.extend(self.replay());
+                return Ok(rolled_back);
+            }
+            _ => return Ok(Vec::new()),
+        }
+        Ok(self.replay())
+    }
+
+    /// Rebuild the predicted state; returns pending actions that no longer apply
+    fn replay(&mut self) -> Vec<Action> {
+        self.predicted = self.confirmed.clone();
+        let mut rolled_back = Vec::new();
+        let (player, predicted) = (self.player, &mut self.predicted);
+        self.pending
+            .retain(|(_, action)| match predicted.apply_action(player, action) {
+                Ok(_) => true,
+                Err(_) => {
+                    rolled_back.push(action.clone());
+                    false
+                }
+            });
+        rolled_back
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state() -> GameState {
+        let tileset = game_tileset().unwrap();
+        GameState::new(2, &Scenario::first(), &tileset, 0).unwrap()
+    }
+
+    fn all_actions(state: &GameState, player: PlayerId) -> Vec<Action> {
+        Pawn::ALL
+            .iter()
+            .flat_map(|pawn| state.legal_actions(player, *pawn))
+            .map(Action::BoardAction)
+            .collect()
+    }
+
+    fn pawn_coords(state: &GameState) -> Vec<Option<BoardCoord>> {
+        Pawn::ALL
+            .iter()
+            .map(|pawn| state.pawn_coord(*pawn))
+            .collect()
+    }
+
+    fn events(first_idx: u64, events: Vec<GameEvent>, acked: Option<u32>) -> ServerMessage {
+        ServerMessage::Events {
+            first_idx,
+            events,
+            acked,
+        }
+    }
+
+    #[test]
+    fn acked() {
+        let mut server = state();
+        let mut game = PredictedGame::new(state(), PlayerId(0));
+        let action = all_actions(game.state(), PlayerId(0)).remove(0);
+
+        let ClientMessage::SubmitAction { seq, .. } = game.submit(action.clone()).unwrap() else {
+            panic!("expected submit");
+        };
+        assert_eq!(game.pending().count(), 1);
+        assert_ne!(pawn_coords(game.state()), pawn_coords(game.confirmed()));
+
+        let applied = server.apply_action(PlayerId(0), &action).unwrap();
+        let rolled_back = game.handle(&events(0, applied, Some(seq))).unwrap();
+        assert!(rolled_back.is_empty());
+        assert_eq!(game.pending().count(), 0);
+        assert_eq!(pawn_coords(game.state()), pawn_coords(&server));
+        assert_eq!(pawn_coords(game.confirmed()), pawn_coords(&server));
+    }
+
+    #[test]
+    fn rejected() {
+        let mut game = PredictedGame::new(state(), PlayerId(0));
+        let action = all_actions(game.state(), PlayerId(0)).remove(0);
+        let ClientMessage::SubmitAction { seq, .. } = game.submit(action.clone()).unwrap() else {
+            panic!("expected submit");
+        };
+
+        let rejected = ServerMessage::Error(ServerError::new(
+            ServerErrorKind::ActionRejected { seq },
+            "",
+        ));
+        assert_eq!(game.handle(&rejected).unwrap(), vec![action]);
+        assert_eq!(pawn_coords(game.state()), pawn_coords(&state()));
+    }
+
+    /// Other player's confirmed action makes a pending action illegal
+    #[test]
+    fn conflict_rolled_back() {
+        let start = state();
+        let conflict = all_actions(&start, PlayerId(0))
+            .into_iter()
+            .flat_map(|ours| {
+                all_actions(&start, PlayerId(1))
+                    .into_iter()
+                    .map(move |theirs| (ours.clone(), theirs))
+            })
+            .find(|(ours, theirs)| {
+                let mut state = start.clone();
+                state.apply_action(PlayerId(1), theirs).unwrap();
+                state.action_events(PlayerId(0), ours).is_err()
+            })
+            .expect("conflicting actions");
+        let (ours, theirs) = conflict;
+
+        let mut server = start.clone();
+        let mut game = PredictedGame::new(start.clone(), PlayerId(0));
+        game.submit(ours.clone()).unwrap();
+
+        // server got the other player's action first
+        let applied = server.apply_action(PlayerId(1), &theirs).unwrap();
+        let num_events = applied.len() as u64;
+        assert_eq!(
+            game.handle(&events(0, applied.clone(), None)).unwrap(),
+            vec![ours]
+        );
+        assert_eq!(game.pending().count(), 0);
+        assert_eq!(pawn_coords(game.state()), pawn_coords(&server));
+
+        // resent events are skipped
+        assert!(game.handle(&events(0, applied, None)).unwrap().is_empty());
+        assert_eq!(pawn_coords(game.confirmed()), pawn_coords(&server));
+        assert_eq!(
+            game.handle(&events(num_events + 1, vec![GameEvent::TimeUp], None)),
+            Err(PredictionError::MissedEvents {
+                expected: num_events,
+                got: num_events + 1
+            })
+        );
+    }
+
+    /// Reconnected client gets a snapshot, then events it already has
+    #[test]
+    fn snapshot() {
+        let mut server = state();
+        let mut game = PredictedGame::new(state(), PlayerId(0));
+        let ours = all_actions(game.state(), PlayerId(0)).remove(0);
+        let mut missed = server.apply_action(PlayerId(0), &ours).unwrap();
+        let ClientMessage::SubmitAction { seq, .. } = game.submit(ours).unwrap() else {
+            panic!("expected submit");
+        };
+        let theirs = all_actions(&server, PlayerId(1)).remove(0);
+        missed.extend(server.apply_action(PlayerId(1), &theirs).unwrap());
+        let num_events = missed.len() as u64;
+
+        // submitted as the connection dropped, so the server never got it
+        let lost = all_actions(game.state(), PlayerId(0)).remove(0);
+        game.submit(lost.clone()).unwrap();
+
+        let snapshot = ServerMessage::Snapshot {
+            state: Box::new(server.clone()),
+            num_events,
+            acked: Some(seq),
+        };
+        assert_eq!(game.handle(&snapshot).unwrap(), vec![lost]);
+        assert_eq!(game.pending().count(), 0);
+        assert_eq!(game.num_confirmed_events(), num_events);
+        assert_eq!(game.confirmed(), &server);
+        assert!(game.handle(&events(0, missed, None)).unwrap().is_empty());
+        assert_eq!(game.state(), &server);
+    }
+
+    #[test]
+    fn illegal_submit() {
+        let mut game = PredictedGame::new(state(), PlayerId(1));
+        let action = all_actions(game.state(), PlayerId(0)).remove(0);
+        assert!(game.submit(action).is_err());
+        assert_eq!(game.pending().count(), 0);
+    }
+}
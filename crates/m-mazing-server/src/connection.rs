@@ -0,0 +1,243 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+use thiserror::Error;
+use tungstenite::{
+    handshake::{server::NoCallback, MidHandshake},
+    HandshakeError, Message, ServerHandshake, WebSocket,
+};
+
+use m_mazing_core::bevy::log::*;
+use m_mazing_protocol::prelude::*;
+
+#[derive(Error, Debug)]
+pub enum ConnectionError {
+    #[error("Connection closed by peer")]
+    Closed,
+
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tungstenite::Error>),
+
+    #[error("WebSocket handshake is not finished")]
+    HandshakePending,
+}
+
+impl From<tungstenite::Error> for ConnectionError {
+    fn from(err: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+type WsHandshake = MidHandshake<ServerHandshake<TcpStream, NoCallback>>;
+
+/// Non-blocking byte stream to a client
+enum Transport {
+    Tcp {
+        stream: TcpStream,
+
+        /// Bytes not yet accepted by the socket
+        unsent: Vec<u8>,
+    },
+    /// Only `None` while the handshake is being advanced
+    WebSocketHandshake(Option<WsHandshake>),
+    WebSocket(WebSocket<TcpStream>),
+}
+
+impl Transport {
+    fn is_would_block(err: &std::io::Error) -> bool {
+        matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+    }
+
+    /// Append received bytes to `buf`
+    fn recv(&mut self, buf: &mut Vec<u8>) -> Result<(), ConnectionError> {
+        loop {
+            match self {
+                Self::Tcp { stream, .. } => {
+                    let mut chunk = [0; 4096];
+                    match stream.read(&mut chunk) {
+                        Ok(0) => return Err(ConnectionError::Closed),
+                        Ok(len) => buf.extend_from_slice(&chunk[..len]),
+                        Err(err) if Self::is_would_block(&err) => return Ok(()),
+                        Err(err) => return Err(ProtocolError::from(err).into()),
+                    }
+                }
+                Self::WebSocketHandshake(_) => {
+                    self.continue_handshake()?;
+                    if matches!(self, Self::WebSocketHandshake(_)) {
+                        return Ok(());
+                    }
+                }
+                Self::WebSocket(ws) => match ws.read() {
+                    Ok(Message::Binary(data)) => buf.extend_from_slice(&data),
+                    Ok(Message::Close(_)) => return Err(ConnectionError::Closed),
+                    Ok(_) => (),
+                    Err(tungstenite::Error::Io(err)) if Self::is_would_block(&err) => return Ok(()),
+                    Err(
+                        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed,
+                    ) => return Err(ConnectionError::Closed),
+                    Err(err) => return Err(err.into()),
+                },
+            }
+        }
+    }
+
+    fn continue_handshake(&mut self) -> Result<(), ConnectionError> {
+        let Self::WebSocketHandshake(mid) = self else {
+            return Ok(());
+        };
+        let mid = mid.take().expect("handshake in progress");
+        match mid.handshake() {
+            Ok(ws) => *self = Self::WebSocket(ws),
+            Err(HandshakeError::Interrupted(mid)) => *self = Self::WebSocketHandshake(Some(mid)),
+            Err(HandshakeError::Failure(err)) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    /// Queue `bytes` and send as much as possible without blocking
+    fn send(&mut self, bytes: Vec<u8>) -> Result<(), ConnectionError> {
+        match self {
+            Self::Tcp { unsent, .. } => unsent.extend_from_slice(&bytes),
+            Self::WebSocketHandshake(_) => return Err(ConnectionError::HandshakePending),
+            Self::WebSocket(ws) => match ws.write(Message::Binary(bytes)) {
+                Ok(()) => (),
+                Err(tungstenite::Error::Io(err)) if Self::is_would_block(&err) => (),
+                Err(err) => return Err(err.into()),
+            },
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), ConnectionError> {
+        match self {
+            Self::Tcp { stream, unsent } => {
+                while !unsent.is_empty() {
+                    match stream.write(unsent) {
+                        Ok(0) => return Err(ConnectionError::Closed),
+                        Ok(len) => {
+                            unsent.drain(..len);
+                        }
+                        Err(err) if Self::is_would_block(&err) => break,
+                        Err(err) => return Err(ProtocolError::from(err).into()),
+                    }
+                }
+                Ok(())
+            }
+            Self::WebSocketHandshake(_) => Ok(()),
+            Self::WebSocket(ws) => match ws.flush() {
+                Ok(()) => Ok(()),
+                Err(tungstenite::Error::Io(err)) if Self::is_would_block(&err) => Ok(()),
+                Err(err) => Err(err.into()),
+            },
+        }
+    }
+}
+
+/// Connected client, in a room or not
+pub struct Connection {
+    pub id: MemberId,
+    pub addr: SocketAddr,
+    transport: Transport,
+    received: Vec<u8>,
+
+    /// Set once the peer's handshake has been checked
+    handshake_done: bool,
+    decoder: FrameDecoder,
+}
+
+impl Connection {
+    fn new(id: MemberId, addr: SocketAddr, transport: Transport) -> Self {
+        Self {
+            id,
+            addr,
+            transport,
+            received: Vec::new(),
+            handshake_done: false,
+            decoder: FrameDecoder::default(),
+        }
+    }
+
+    /// Raw TCP connection; sends our handshake right away
+    pub fn tcp(id: MemberId, stream: TcpStream, addr: SocketAddr) -> Result<Self, ConnectionError> {
+        stream.set_nonblocking(true).map_err(ProtocolError::from)?;
+        stream.set_nodelay(true).map_err(ProtocolError::from)?;
+        let mut conn = Self::new(
+            id,
+            addr,
+            Transport::Tcp {
+                stream,
+                unsent: Vec::new(),
+            },
+        );
+        conn.transport
+            .send(Handshake::default().to_bytes().to_vec())?;
+        Ok(conn)
+    }
+
+    /// WebSocket connection; binary messages carry the same bytes as a TCP stream
+    pub fn websocket(
+        id: MemberId,
+        stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Result<Self, ConnectionError> {
+        stream.set_nonblocking(true).map_err(ProtocolError::from)?;
+        stream.set_nodelay(true).map_err(ProtocolError::from)?;
+        let transport = match tungstenite::accept(stream) {
+            Ok(ws) => Transport::WebSocket(ws),
+            Err(HandshakeError::Interrupted(mid)) => Transport::WebSocketHandshake(Some(mid)),
+            Err(HandshakeError::Failure(err)) => return Err(err.into()),
+        };
+        let mut conn = Self::new(id, addr, transport);
+        if conn.is_websocket_ready() {
+            conn.transport
+                .send(Handshake::default().to_bytes().to_vec())?;
+        }
+        Ok(conn)
+    }
+
+    fn is_websocket_ready(&self) -> bool {
+        !matches!(self.transport, Transport::WebSocketHandshake(_))
+    }
+
+    /// Messages received since the last call
+    pub fn poll(&mut self) -> Result<Vec<ClientMessage>, ConnectionError> {
+        let was_ready = self.is_websocket_ready();
+        self.transport.recv(&mut self.received)?;
+        if !was_ready && self.is_websocket_ready() {
+            debug!("WebSocket handshake done with {}", self.addr);
+            self.transport
+                .send(Handshake::default().to_bytes().to_vec())?;
+        }
+
+        if !self.handshake_done {
+            let Some(bytes) = self.received.get(..Handshake::SIZE) else {
+                return Ok(Vec::new());
+            };
+            let bytes: [u8; Handshake::SIZE] = bytes.try_into().expect("handshake size");
+            Handshake::from_bytes(bytes)?.check()?;
+            self.received.drain(..Handshake::SIZE);
+            self.handshake_done = true;
+        }
+
+        self.decoder.extend(&self.received);
+        self.received.clear();
+        let mut msgs = Vec::new();
+        while let Some(msg) = self.decoder.next_message()? {
+            msgs.push(msg);
+        }
+        Ok(msgs)
+    }
+
+    pub fn send(&mut self, msg: &ServerMessage) -> Result<(), ConnectionError> {
+        self.transport.send(encode(msg)?)
+    }
+
+    pub fn flush(&mut self) -> Result<(), ConnectionError> {
+        self.transport.flush()
+    }
+}
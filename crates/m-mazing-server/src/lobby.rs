@@ -0,0 +1,1482 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+use m_mazing_core::bevy::{
+    log::*,
+    time::{Timer, TimerMode},
+};
+use m_mazing_core::prelude::*;
+use m_mazing_protocol::prelude::*;
+
+/// How often the timer state is re-sent while it runs, to correct client drift
+const TIMER_SYNC_PERIOD: Duration = Duration::from_secs(5);
+
+/// Messages produced while handling a request; the server delivers them afterwards
+#[derive(Debug, Default)]
+pub struct Outbox {
+    msgs: Vec<(MemberId, ServerMessage)>,
+}
+
+impl Outbox {
+    pub fn send(&mut self, to: MemberId, msg: ServerMessage) {
+        self.msgs.push((to, msg));
+    }
+
+    pub fn take(&mut self) -> Vec<(MemberId, ServerMessage)> {
+        std::mem::take(&mut self.msgs)
+    }
+}
+
+type RoomResult = Result<(), ServerError>;
+
+/// Game being played in a room
+struct RoomGame {
+    scenario: u8,
+    state: GameState,
+
+    /// Every event applied to `state`, so late joiners can catch up
+    history: Vec<GameEvent>,
+    timer: SandTimer,
+    timer_sync: Timer,
+
+    /// Time the sand timer has been running
+    clock: Duration,
+    replay: Replay,
+
+    /// Member in each player seat; `None` once they left, or if a bot plays it
+    seats: Vec<Option<MemberId>>,
+
+    /// Players of seats without a member
+    bots: Vec<Bot>,
+
+    /// `seq` of the last action performed from each seat, for snapshots to acknowledge
+    acked: Vec<Option<u32>>,
+}
+
+impl RoomGame {
+    fn player(&self, member: MemberId) -> Option<PlayerId> {
+        self.seats
+            .iter()
+            .position(|seat| *seat == Some(member))
+            .map(|idx| PlayerId(idx as u8))
+    }
+
+    /// Seat has neither a member nor a bot
+    fn is_vacant(&self, player: PlayerId, members: &[RoomMember]) -> bool {
+        let has_member = self.seats[player.0 as usize]
+            .is_some_and(|id| members.iter().any(|m| m.id == id && m.connected));
+        !has_member && !self.bots.iter().any(|bot| bot.player() == player)
+    }
+
+    fn timer_sync(&self) -> ServerMessage {
+        ServerMessage::TimerSync((&self.timer).into())
+    }
+
+    fn role_assignment(&self, player: PlayerId) -> ServerMessage {
+        ServerMessage::RoleAssignment {
+            player,
+            num_players: self.state.num_players(),
+            scenario: self.scenario,
+            seed: self.state.seed(),
+            actions: self
+                .state
+                .player_actions(player)
+                .expect("seat has a role")
+                .to_vec(),
+        }
+    }
+
+    /// Messages that bring a newly seated player up to date
+    fn catch_up(&self, player: PlayerId) -> [ServerMessage; 3] {
+        [
+            self.role_assignment(player),
+            ServerMessage::Events {
+                first_idx: 0,
+                events: self.history.clone(),
+                acked: None,
+            },
+            self.timer_sync(),
+        ]
+    }
+
+    /// Messages that bring a reconnected player up to date; `next_event` is the number of
+    /// events it already has
+    fn resume(&self, player: PlayerId, next_event: u64) -> [ServerMessage; 4] {
+        let num_events = self.history.len() as u64;
+        let first_idx = next_event.min(num_events);
+        [
+            self.role_assignment(player),
+            ServerMessage::Snapshot {
+                state: Box::new(self.state.clone()),
+                num_events,
+                acked: self.acked[player.0 as usize],
+            },
+            ServerMessage::Events {
+                first_idx,
+                events: self.history[first_idx as usize..].to_vec(),
+                acked: None,
+            },
+            self.timer_sync(),
+        ]
+    }
+}
+
+/// Lets a member take back their place from a new connection
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    token: SessionToken,
+
+    /// Time since the connection dropped; `None` while connected
+    away_for: Option<Duration>,
+}
+
+impl Session {
+    fn new(token: SessionToken) -> Self {
+        Self {
+            token,
+            away_for: None,
+        }
+    }
+}
+
+/// Members waiting for, or playing, a game together
+struct Room {
+    code: RoomCode,
+    host: MemberId,
+
+    /// In order of joining
+    members: Vec<RoomMember>,
+    sessions: HashMap<MemberId, Session>,
+    config: RoomConfig,
+
+    /// Latest game, kept after it is over until the next one starts
+    game: Option<RoomGame>,
+
+    /// Time since the last member left
+    empty_for: Duration,
+}
+
+impl Room {
+    fn new(code: RoomCode, host: MemberId, name: String, token: SessionToken) -> Self {
+        Self {
+            code,
+            host,
+            members: vec![RoomMember {
+                id: host,
+                name,
+                ready: false,
+                connected: true,
+            }],
+            sessions: HashMap::from([(host, Session::new(token))]),
+            config: RoomConfig::default(),
+            game: None,
+            empty_for: Duration::ZERO,
+        }
+    }
+
+    fn in_game(&self) -> bool {
+        self.game
+            .as_ref()
+            .is_some_and(|game| !game.state.phase().is_over())
+    }
+
+    fn info(&self) -> RoomInfo {
+        RoomInfo {
+            code: self.code.clone(),
+            host: self.host,
+            members: self.members.clone(),
+            config: self.config,
+            in_game: self.in_game(),
+        }
+    }
+
+    fn contains(&self, id: MemberId) -> bool {
+        self.members.iter().any(|member| member.id == id)
+    }
+
+    /// Members that can receive messages
+    fn connected(&self) -> impl Iterator<Item = &RoomMember> {
+        self.members.iter().filter(|member| member.connected)
+    }
+
+    fn broadcast(&self, msg: ServerMessage, outbox: &mut Outbox) {
+        for member in self.connected() {
+            outbox.send(member.id, msg.clone());
+        }
+    }
+
+    /// Send `RoomJoined` to `id` and an update to everyone else
+    fn announce_joined(&self, id: MemberId, outbox: &mut Outbox) {
+        outbox.send(
+            id,
+            ServerMessage::RoomJoined {
+                you: id,
+                room: self.info(),
+                token: self.sessions[&id].token,
+            },
+        );
+        for member in self.connected().filter(|member| member.id != id) {
+            outbox.send(member.id, ServerMessage::RoomUpdate(self.info()));
+        }
+    }
+
+    fn broadcast_update(&self, outbox: &mut Outbox) {
+        self.broadcast(ServerMessage::RoomUpdate(self.info()), outbox);
+    }
+
+    fn check_host(&self, id: MemberId) -> RoomResult {
+        if self.host != id {
+            return Err(ServerError::new(
+                ServerErrorKind::NotHost,
+                "Only the host can do that",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_not_in_game(&self) -> RoomResult {
+        if self.in_game() {
+            return Err(ServerError::new(
+                ServerErrorKind::GameInProgress,
+                "Not allowed during a game",
+            ));
+        }
+        Ok(())
+    }
+
+    fn join(
+        &mut self,
+        id: MemberId,
+        name: String,
+        token: SessionToken,
+        outbox: &mut Outbox,
+    ) -> RoomResult {
+        let vacant_seat = match &self.game {
+            Some(game) if self.in_game() => {
+                Some(game.seats.iter().position(Option::is_none).ok_or_else(|| {
+                    ServerError::new(ServerErrorKind::GameInProgress, "All seats are taken")
+                })?)
+            }
+            _ => {
+                if self.members.len() >= usize::from(self.config.num_players) {
+                    return Err(ServerError::new(
+                        ServerErrorKind::RoomFull,
+                        format!("Room is full with {} players", self.members.len()),
+                    ));
+                }
+                None
+            }
+        };
+
+        info!("{id:?} {name:?} joined room {}", self.code);
+        if self.members.is_empty() {
+            self.host = id;
+        }
+        self.members.push(RoomMember {
+            id,
+            name,
+            ready: false,
+            connected: true,
+        });
+        self.sessions.insert(id, Session::new(token));
+        self.empty_for = Duration::ZERO;
+        self.announce_joined(id, outbox);
+
+        if let Some(seat) = vacant_seat {
+            let game = self.game.as_mut().expect("game in progress");
+            game.seats[seat] = Some(id);
+            game.acked[seat] = None;
+            game.bots.retain(|bot| bot.player() != PlayerId(seat as u8));
+            let resumed = self.pause_for_vacant_seats();
+            let game = self.game.as_ref().expect("game in progress");
+            for msg in game.catch_up(PlayerId(seat as u8)) {
+                outbox.send(id, msg);
+            }
+            if resumed {
+                self.broadcast_timer(outbox);
+            }
+        }
+        Ok(())
+    }
+
+    /// Connection of `id` dropped; returns false if they left instead, because their seat
+    /// does not need to be held
+    fn drop_connection(&mut self, id: MemberId, outbox: &mut Outbox) -> bool {
+        let seated = self.in_game()
+            && self
+                .game
+                .as_ref()
+                .is_some_and(|game| game.player(id).is_some());
+        if !seated {
+            return false;
+        }
+
+        info!("Holding seat of {id:?} in room {}", self.code);
+        let member = self
+            .members
+            .iter_mut()
+            .find(|member| member.id == id)
+            .expect("member of room");
+        member.connected = false;
+        self.sessions
+            .get_mut(&id)
+            .expect("member has a session")
+            .away_for = Some(Duration::ZERO);
+        self.broadcast_update(outbox);
+        self.update_pause(outbox);
+        true
+    }
+
+    /// New connection `id` takes the place of the member with `token`; returns the
+    /// replaced member id.
+    ///
+    /// The replaced connection may not have been noticed as dropped yet.
+    fn reconnect(
+        &mut self,
+        id: MemberId,
+        token: SessionToken,
+        next_event: u64,
+        outbox: &mut Outbox,
+    ) -> Result<MemberId, ServerError> {
+        let old = self
+            .sessions
+            .iter()
+            .find(|(_, session)| session.token == token)
+            .map(|(old, _)| *old)
+            .ok_or_else(|| {
+                ServerError::new(
+                    ServerErrorKind::UnknownSession,
+                    format!("No member of room {} with that token", self.code),
+                )
+            })?;
+
+        info!("{id:?} reconnected to room {} as {old:?}", self.code);
+        self.sessions.remove(&old);
+        self.sessions.insert(id, Session::new(token));
+        let member = self
+            .members
+            .iter_mut()
+            .find(|member| member.id == old)
+            .expect("member of room");
+        member.id = id;
+        member.connected = true;
+        if self.host == old {
+            self.host = id;
+        }
+        if let Some(game) = self.game.as_mut() {
+            for seat in game.seats.iter_mut().filter(|seat| **seat == Some(old)) {
+                *seat = Some(id);
+            }
+        }
+        self.announce_joined(id, outbox);
+
+        // timer sync in the catch up should already show the timer running again
+        let resumed = self.pause_for_vacant_seats();
+        if let Some(game) = self.game.as_ref() {
+            if let Some(player) = game.player(id) {
+                for msg in game.resume(player, next_event) {
+                    outbox.send(id, msg);
+                }
+            }
+        }
+        if resumed {
+            self.broadcast_timer(outbox);
+        }
+        Ok(old)
+    }
+
+    fn leave(&mut self, id: MemberId, kicked: bool, outbox: &mut Outbox) {
+        let connected = self.connected().any(|member| member.id == id);
+        self.members.retain(|member| member.id != id);
+        self.sessions.remove(&id);
+        let in_game = self.in_game();
+        if let Some(game) = self.game.as_mut() {
+            if let Some(player) = game.player(id) {
+                game.seats[player.0 as usize] = None;
+                if let Some(difficulty) = self.config.bots.filter(|_| in_game) {
+                    info!("Bot takes over seat {player:?} in room {}", self.code);
+                    game.bots.push(Bot::new(player, difficulty));
+                }
+            }
+        }
+        if self.host == id {
+            if let Some(member) = self.members.first() {
+                self.host = member.id;
+                info!("{:?} is now host of room {}", self.host, self.code);
+            }
+        }
+        info!("{id:?} left room {}", self.code);
+
+        if connected {
+            outbox.send(id, ServerMessage::RoomLeft { kicked });
+        }
+        self.broadcast_update(outbox);
+        self.update_pause(outbox);
+    }
+
+    /// Remove members whose connection dropped more than `grace` ago
+    fn expire_sessions(&mut self, elapsed: Duration, grace: Duration, outbox: &mut Outbox) {
+        let mut expired = Vec::new();
+        for (id, session) in self.sessions.iter_mut() {
+            if let Some(away_for) = session.away_for.as_mut() {
+                *away_for += elapsed;
+                if *away_for >= grace {
+                    expired.push(*id);
+                }
+            }
+        }
+        expired.sort();
+        for id in expired {
+            info!("{id:?} did not reconnect to room {} in time", self.code);
+            self.leave(id, false, outbox);
+        }
+    }
+
+    /// Pause or resume the sand timer if the room wants it stopped while a seat is vacant
+    fn update_pause(&mut self, outbox: &mut Outbox) {
+        if self.pause_for_vacant_seats() {
+            self.broadcast_timer(outbox);
+        }
+    }
+
+    /// Like [`Self::update_pause`] without telling anyone; returns true if the timer changed
+    fn pause_for_vacant_seats(&mut self) -> bool {
+        if !self.config.pause_when_vacant || !self.in_game() {
+            return false;
+        }
+        let game = self.game.as_mut().expect("game in progress");
+        let vacant =
+            (0..game.seats.len()).any(|idx| game.is_vacant(PlayerId(idx as u8), &self.members));
+        if vacant != game.timer.is_running() {
+            return false;
+        }
+        if vacant {
+            info!("Pausing sand timer in room {} for a vacant seat", self.code);
+            game.timer.pause();
+        } else {
+            info!("Resuming sand timer in room {}", self.code);
+            game.timer.start();
+        }
+        true
+    }
+
+    fn configure(&mut self, id: MemberId, config: RoomConfig, outbox: &mut Outbox) -> RoomResult {
+        self.check_host(id)?;
+        self.check_not_in_game()?;
+        if Scenario::builtin(config.scenario).is_none() {
+            return Err(ServerError::new(
+                ServerErrorKind::InvalidConfig,
+                format!("Unknown scenario {}", config.scenario),
+            ));
+        }
+        if game_roles(config.num_players).is_none() {
+            return Err(ServerError::new(
+                ServerErrorKind::InvalidConfig,
+                format!(
+                    "Number of players must be between 1 and {MAX_PLAYERS}, not {}",
+                    config.num_players
+                ),
+            ));
+        }
+        if usize::from(config.num_players) < self.members.len() {
+            return Err(ServerError::new(
+                ServerErrorKind::InvalidConfig,
+                format!("Room already has {} members", self.members.len()),
+            ));
+        }
+
+        self.config = config;
+        for member in self.members.iter_mut() {
+            member.ready = false;
+        }
+        self.broadcast_update(outbox);
+        Ok(())
+    }
+
+    fn set_ready(&mut self, id: MemberId, ready: bool, outbox: &mut Outbox) -> RoomResult {
+        self.check_not_in_game()?;
+        let member = self
+            .members
+            .iter_mut()
+            .find(|member| member.id == id)
+            .expect("member of room");
+        member.ready = ready;
+        self.broadcast_update(outbox);
+        Ok(())
+    }
+
+    fn start(
+        &mut self,
+        id: MemberId,
+        lobby: &LobbyConfig,
+        rng: &mut fastrand::Rng,
+        outbox: &mut Outbox,
+    ) -> RoomResult {
+        self.check_host(id)?;
+        self.check_not_in_game()?;
+        let num_ready = self.members.iter().filter(|member| member.ready).count();
+        let num_humans = match self.config.bots {
+            Some(_) => self.members.len(),
+            None => self.config.num_players.into(),
+        };
+        if num_ready != num_humans {
+            return Err(ServerError::new(
+                ServerErrorKind::NotReady,
+                format!("{num_ready} of {num_humans} players are ready"),
+            ));
+        }
+
+        let seed = rng.u64(..);
+        let scenario = Scenario::builtin(self.config.scenario).expect("validated scenario");
+        let state = GameState::new(self.config.num_players, &scenario, &lobby.tileset, seed)
+            .map_err(|err| ServerError::new(ServerErrorKind::InvalidConfig, err.to_string()))?;
+
+        // roles are dealt by the game, so seats simply follow the join order
+        let mut seats: Vec<Option<MemberId>> =
+            self.members.iter().map(|member| Some(member.id)).collect();
+        let bots = match self.config.bots {
+            Some(difficulty) => (seats.len()..self.config.num_players.into())
+                .map(|idx| Bot::new(PlayerId(idx as u8), difficulty))
+                .collect(),
+            None => Vec::new(),
+        };
+        seats.resize(self.config.num_players.into(), None);
+
+        let mut timer = SandTimer::new(lobby.timer_duration);
+        timer.start();
+        let replay = Replay::new(
+            self.config.scenario,
+            self.config.num_players,
+            seed,
+            lobby.timer_duration,
+        );
+        let game = RoomGame {
+            scenario: self.config.scenario,
+            state,
+            history: Vec::new(),
+            timer,
+            timer_sync: Timer::new(TIMER_SYNC_PERIOD, TimerMode::Repeating),
+            clock: Duration::ZERO,
+            replay,
+            acked: vec![None; seats.len()],
+            seats,
+            bots,
+        };
+        info!(
+            "Room {} started scenario {} with seats {:?} and {} bots",
+            self.code,
+            self.config.scenario,
+            game.seats,
+            game.bots.len()
+        );
+        for member in self.members.iter_mut() {
+            member.ready = false;
+        }
+        self.game = Some(game);
+        self.broadcast_update(outbox);
+
+        let game = self.game.as_ref().expect("game just started");
+        for (idx, seat) in game.seats.iter().enumerate() {
+            let Some(member) = seat else {
+                continue;
+            };
+            for msg in game.catch_up(PlayerId(idx as u8)) {
+                outbox.send(*member, msg);
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_host(&mut self, id: MemberId, target: MemberId, outbox: &mut Outbox) -> RoomResult {
+        self.check_host(id)?;
+        if !self.contains(target) {
+            return Err(ServerError::new(
+                ServerErrorKind::UnknownMember,
+                format!("{target:?} is not in the room"),
+            ));
+        }
+        self.host = target;
+        self.broadcast_update(outbox);
+        Ok(())
+    }
+
+    fn submit_action(
+        &mut self,
+        id: MemberId,
+        seq: u32,
+        action: Action,
+        lobby: &LobbyConfig,
+        outbox: &mut Outbox,
+    ) -> RoomResult {
+        let rejected = |msg: String| ServerError::new(ServerErrorKind::ActionRejected { seq }, msg);
+        let Some(game) = self.game.as_ref() else {
+            return Err(rejected("No game has started".to_string()));
+        };
+        let Some(player) = game.player(id) else {
+            return Err(rejected("Not seated in the game".to_string()));
+        };
+        if matches!(action, Action::DoSomething(_)) && !self.config.do_something {
+            return Err(rejected(
+                "\"Do Something!\" is disabled in this room".to_string(),
+            ));
+        }
+
+        self.perform(player, action, Some((id, seq)), lobby, outbox)
+            .map_err(|err| rejected(err.to_string()))
+    }
+
+    /// Apply `action` of `player` and tell everyone.
+    ///
+    /// `cause` is the member and `seq` the action came with, if any.
+    fn perform(
+        &mut self,
+        player: PlayerId,
+        action: Action,
+        cause: Option<(MemberId, u32)>,
+        lobby: &LobbyConfig,
+        outbox: &mut Outbox,
+    ) -> Result<(), ActionError> {
+        let game = self.game.as_mut().expect("game started");
+        let events = game.state.apply_action(player, &action)?;
+        debug!("{player:?} performed {action:?}");
+        game.replay.record(game.clock, player, action);
+        if let Some((_, seq)) = cause {
+            game.acked[player.0 as usize] = Some(seq);
+        }
+        let flipped = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::TimerFlipped(_)));
+        if flipped {
+            game.timer.flip();
+        }
+        self.publish_events(events, cause, lobby, outbox);
+        if flipped || !self.in_game() {
+            self.broadcast_timer(outbox);
+        }
+        Ok(())
+    }
+
+    /// Let bots react to the game after `elapsed` time
+    fn run_bots(&mut self, elapsed: Duration, lobby: &LobbyConfig, outbox: &mut Outbox) {
+        if !self.in_game() {
+            return;
+        }
+        let game = self.game.as_mut().expect("game in progress");
+        let mut actions = Vec::new();
+        for bot in game.bots.iter_mut() {
+            if let Some(action) = bot.tick(&game.state, elapsed) {
+                actions.push((bot.player(), action));
+            }
+        }
+        // bots that acted on the same state may get in each other's way
+        for (player, action) in actions {
+            if let Err(err) = self.perform(player, action, None, lobby, outbox) {
+                debug!("Bot {player:?} failed to perform action: {err}");
+            }
+        }
+    }
+
+    /// Record and broadcast events that were already applied to the game state.
+    ///
+    /// `cause` is the member and `seq` of the action that caused them, if any.
+    fn publish_events(
+        &mut self,
+        events: Vec<GameEvent>,
+        cause: Option<(MemberId, u32)>,
+        lobby: &LobbyConfig,
+        outbox: &mut Outbox,
+    ) {
+        let game = self.game.as_mut().expect("game in progress");
+        let first_idx = game.history.len() as u64;
+        game.history.extend_from_slice(&events);
+        let over = game.state.phase().is_over();
+        if over {
+            game.timer.pause();
+            game.replay.finish(game.clock);
+            if let Some(dir) = lobby.replay_dir.as_deref() {
+                Self::save_replay(&self.code, &game.replay, dir);
+            }
+        }
+        for member in self.members.iter().filter(|member| member.connected) {
+            let acked = cause.filter(|(id, _)| *id == member.id).map(|(_, seq)| seq);
+            outbox.send(
+                member.id,
+                ServerMessage::Events {
+                    first_idx,
+                    events: events.clone(),
+                    acked,
+                },
+            );
+        }
+        if over {
+            info!("Game in room {} is over", self.code);
+            self.broadcast_update(outbox);
+        }
+    }
+
+    fn save_replay(code: &RoomCode, replay: &Replay, dir: &Path) {
+        let path = dir.join(format!("{code}-{}.ron", GameRng::format_seed(replay.seed)));
+        match replay.save(&path) {
+            Ok(()) => info!("Saved replay of room {code} to {path:?}"),
+            Err(err) => warn!("{err}"),
+        }
+    }
+
+    fn broadcast_timer(&mut self, outbox: &mut Outbox) {
+        let game = self.game.as_mut().expect("game in progress");
+        game.timer_sync.reset();
+        let msg = game.timer_sync();
+        self.broadcast(msg, outbox);
+    }
+
+    fn run_timer(&mut self, elapsed: Duration, lobby: &LobbyConfig, outbox: &mut Outbox) {
+        let Some(game) = self.game.as_mut() else {
+            return;
+        };
+        if game.timer.is_running() && !game.timer.is_expired() {
+            game.clock += elapsed;
+        }
+        if game.timer.tick(elapsed) {
+            info!("Sand timer ran out in room {}", self.code);
+            let event = GameEvent::TimeUp;
+            game.state.apply_event(&event);
+            self.publish_events(vec![event], None, lobby, outbox);
+            self.broadcast_timer(outbox);
+        } else if game.timer.is_running() && game.timer_sync.tick(elapsed).just_finished() {
+            self.broadcast_timer(outbox);
+        }
+    }
+}
+
+/// Settings shared by all rooms
+struct LobbyConfig {
+    tileset: Vec<(String, Tile)>,
+    timer_duration: Duration,
+
+    /// Rooms without members are closed after this long
+    empty_room_timeout: Duration,
+
+    /// Seats of dropped players are held this long for them to reconnect
+    reconnect_grace: Duration,
+
+    /// Replays of finished games are saved here
+    replay_dir: Option<PathBuf>,
+}
+
+/// All rooms on the server
+pub struct Lobby {
+    rooms: HashMap<RoomCode, Room>,
+
+    /// Room of each member
+    member_rooms: HashMap<MemberId, RoomCode>,
+    config: LobbyConfig,
+    rng: fastrand::Rng,
+}
+
+impl Lobby {
+    pub fn new(
+        timer_duration: Duration,
+        empty_room_timeout: Duration,
+        reconnect_grace: Duration,
+        replay_dir: Option<PathBuf>,
+        rng: fastrand::Rng,
+    ) -> Result<Self> {
+        Ok(Self {
+            rooms: HashMap::new(),
+            member_rooms: HashMap::new(),
+            config: LobbyConfig {
+                tileset: game_tileset().context("Failed to parse game tiles")?,
+                timer_duration,
+                empty_room_timeout,
+                reconnect_grace,
+                replay_dir,
+            },
+            rng,
+        })
+    }
+
+    fn room_mut(&mut self, id: MemberId) -> Result<&mut Room, ServerError> {
+        let code = self.member_rooms.get(&id).ok_or_else(|| {
+            ServerError::new(ServerErrorKind::NotInRoom, "Create or join a room first")
+        })?;
+        Ok(self.rooms.get_mut(code).expect("member's room exists"))
+    }
+
+    fn check_not_in_room(&self, id: MemberId) -> RoomResult {
+        if let Some(code) = self.member_rooms.get(&id) {
+            return Err(ServerError::new(
+                ServerErrorKind::AlreadyInRoom,
+                format!("Already in room {code}"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn new_token(&mut self) -> SessionToken {
+        SessionToken(self.rng.u64(..))
+    }
+
+    /// Handle `msg` from member `from`; errors are answered in `outbox`
+    pub fn handle(&mut self, from: MemberId, msg: ClientMessage, outbox: &mut Outbox) {
+        trace!("{from:?} sent {msg:?}");
+        if let Err(err) = self.try_handle(from, msg, outbox) {
+            debug!("Rejected message from {from:?}: {}", err.msg);
+            outbox.send(from, ServerMessage::Error(err));
+        }
+    }
+
+    fn try_handle(
+        &mut self,
+        from: MemberId,
+        msg: ClientMessage,
+        outbox: &mut Outbox,
+    ) -> RoomResult {
+        match msg {
+            ClientMessage::CreateRoom { name } => {
+                self.check_not_in_room(from)?;
+                let code = loop {
+                    let code = RoomCode::generate(|len| self.rng.usize(..len));
+                    if !self.rooms.contains_key(&code) {
+                        break code;
+                    }
+                };
+                info!("{from:?} {name:?} created room {code}");
+                let room = Room::new(code.clone(), from, name, self.new_token());
+                room.announce_joined(from, outbox);
+                self.rooms.insert(code.clone(), room);
+                self.member_rooms.insert(from, code);
+                Ok(())
+            }
+            ClientMessage::JoinRoom { code, name } => {
+                self.check_not_in_room(from)?;
+                let token = self.new_token();
+                let room = self.rooms.get_mut(&code).ok_or_else(|| {
+                    ServerError::new(ServerErrorKind::UnknownRoom, format!("No room {code}"))
+                })?;
+                room.join(from, name, token, outbox)?;
+                self.member_rooms.insert(from, code);
+                Ok(())
+            }
+            ClientMessage::Reconnect {
+                code,
+                token,
+                next_event,
+            } => {
+                self.check_not_in_room(from)?;
+                let room = self.rooms.get_mut(&code).ok_or_else(|| {
+                    ServerError::new(ServerErrorKind::UnknownRoom, format!("No room {code}"))
+                })?;
+                let old = room.reconnect(from, token, next_event, outbox)?;
+                room.empty_for = Duration::ZERO;
+                self.member_rooms.remove(&old);
+                self.member_rooms.insert(from, code);
+                Ok(())
+            }
+            ClientMessage::LeaveRoom => {
+                self.room_mut(from)?.leave(from, false, outbox);
+                self.member_rooms.remove(&from);
+                Ok(())
+            }
+            ClientMessage::Configure(config) => {
+                self.room_mut(from)?.configure(from, config, outbox)
+            }
+            ClientMessage::SetReady(ready) => self.room_mut(from)?.set_ready(from, ready, outbox),
+            ClientMessage::StartGame => {
+                let code = self.room_mut(from)?.code.clone();
+                let room = self.rooms.get_mut(&code).expect("member's room exists");
+                room.start(from, &self.config, &mut self.rng, outbox)
+            }
+            ClientMessage::Kick(target) => {
+                let room = self.room_mut(from)?;
+                room.check_host(from)?;
+                if target == from || !room.contains(target) {
+                    return Err(ServerError::new(
+                        ServerErrorKind::UnknownMember,
+                        format!("Cannot kick {target:?}"),
+                    ));
+                }
+                room.leave(target, true, outbox);
+                self.member_rooms.remove(&target);
+                Ok(())
+            }
+            ClientMessage::TransferHost(target) => {
+                self.room_mut(from)?.transfer_host(from, target, outbox)
+            }
+            ClientMessage::SubmitAction { seq, action } => {
+                let code = self.room_mut(from)?.code.clone();
+                let room = self.rooms.get_mut(&code).expect("member's room exists");
+                room.submit_action(from, seq, action, &self.config, outbox)
+            }
+            ClientMessage::Ping { nonce } => {
+                outbox.send(from, ServerMessage::Pong { nonce });
+                Ok(())
+            }
+        }
+    }
+
+    /// Member's connection is gone; their seat is held if they are playing
+    pub fn disconnect(&mut self, id: MemberId, outbox: &mut Outbox) {
+        if let Ok(room) = self.room_mut(id) {
+            if !room.drop_connection(id, outbox) {
+                room.leave(id, false, outbox);
+            }
+            self.member_rooms.remove(&id);
+        }
+        // nobody is left to read the goodbye
+        outbox.msgs.retain(|(to, _)| *to != id);
+    }
+
+    /// Let `elapsed` time pass for sand timers and empty rooms
+    pub fn tick(&mut self, elapsed: Duration, outbox: &mut Outbox) {
+        let grace = self.config.reconnect_grace;
+        for room in self.rooms.values_mut() {
+            room.expire_sessions(elapsed, grace, outbox);
+            room.run_timer(elapsed, &self.config, outbox);
+            room.run_bots(elapsed, &self.config, outbox);
+            if room.members.is_empty() {
+                room.empty_for += elapsed;
+            }
+        }
+
+        let timeout = self.config.empty_room_timeout;
+        self.rooms.retain(|code, room| {
+            let keep = room.empty_for < timeout;
+            if !keep {
+                info!("Closing empty room {code}");
+            }
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const GRACE: Duration = Duration::from_secs(30);
+
+    fn lobby() -> Lobby {
+        Lobby::new(
+            SandTimer::DEFAULT_DURATION,
+            TIMEOUT,
+            GRACE,
+            None,
+            fastrand::Rng::with_seed(7),
+        )
+        .unwrap()
+    }
+
+    /// Handle `msg` and return what `to` receives
+    fn handle(
+        lobby: &mut Lobby,
+        from: MemberId,
+        msg: ClientMessage,
+        to: MemberId,
+    ) -> Vec<ServerMessage> {
+        let mut outbox = Outbox::default();
+        lobby.handle(from, msg, &mut outbox);
+        outbox
+            .take()
+            .into_iter()
+            .filter(|(id, _)| *id == to)
+            .map(|(_, msg)| msg)
+            .collect()
+    }
+
+    fn error_kind(msgs: &[ServerMessage]) -> Option<ServerErrorKind> {
+        match msgs {
+            [ServerMessage::Error(err)] => Some(err.kind),
+            _ => None,
+        }
+    }
+
+    fn create(lobby: &mut Lobby, host: MemberId) -> RoomCode {
+        let msgs = handle(
+            lobby,
+            host,
+            ClientMessage::CreateRoom {
+                name: "host".to_string(),
+            },
+            host,
+        );
+        let [ServerMessage::RoomJoined { you, room, .. }] = msgs.as_slice() else {
+            panic!("expected room joined, got {msgs:?}");
+        };
+        assert_eq!((*you, room.host), (host, host));
+        room.code.clone()
+    }
+
+    fn join(lobby: &mut Lobby, code: &RoomCode, id: MemberId) -> Vec<ServerMessage> {
+        handle(
+            lobby,
+            id,
+            ClientMessage::JoinRoom {
+                code: code.clone(),
+                name: format!("{id:?}"),
+            },
+            id,
+        )
+    }
+
+    fn configure(lobby: &mut Lobby, host: MemberId, num_players: u8) -> Vec<ServerMessage> {
+        handle(
+            lobby,
+            host,
+            ClientMessage::Configure(RoomConfig {
+                scenario: 2,
+                num_players,
+                ..Default::default()
+            }),
+            host,
+        )
+    }
+
+    fn room<'a>(lobby: &'a Lobby, code: &RoomCode) -> &'a Room {
+        &lobby.rooms[code]
+    }
+
+    #[test]
+    fn ready_and_start() {
+        let mut lobby = lobby();
+        let members = [MemberId(0), MemberId(1), MemberId(2)];
+        let code = create(&mut lobby, members[0]);
+
+        // default room is for one player
+        assert_eq!(
+            error_kind(&join(&mut lobby, &code, members[1])),
+            Some(ServerErrorKind::RoomFull)
+        );
+        assert_eq!(
+            error_kind(&configure(&mut lobby, members[0], MAX_PLAYERS + 1)),
+            Some(ServerErrorKind::InvalidConfig)
+        );
+        configure(&mut lobby, members[0], 3);
+        for id in members[1..].iter() {
+            assert!(matches!(
+                join(&mut lobby, &code, *id).as_slice(),
+                [ServerMessage::RoomJoined { .. }]
+            ));
+        }
+        assert_eq!(
+            error_kind(&configure(&mut lobby, members[0], 2)),
+            Some(ServerErrorKind::InvalidConfig)
+        );
+        assert_eq!(
+            error_kind(&configure(&mut lobby, members[1], 3)),
+            Some(ServerErrorKind::NotHost)
+        );
+
+        for id in members.iter() {
+            handle(&mut lobby, *id, ClientMessage::SetReady(true), *id);
+        }
+        // changing the config resets ready flags
+        configure(&mut lobby, members[0], 3);
+        assert_eq!(
+            error_kind(&handle(
+                &mut lobby,
+                members[0],
+                ClientMessage::StartGame,
+                members[0]
+            )),
+            Some(ServerErrorKind::NotReady)
+        );
+        for id in members.iter() {
+            handle(&mut lobby, *id, ClientMessage::SetReady(true), *id);
+        }
+        assert_eq!(
+            error_kind(&handle(
+                &mut lobby,
+                members[1],
+                ClientMessage::StartGame,
+                members[1]
+            )),
+            Some(ServerErrorKind::NotHost)
+        );
+
+        let mut outbox = Outbox::default();
+        lobby.handle(members[0], ClientMessage::StartGame, &mut outbox);
+        let mut players = Vec::new();
+        let mut dealt = Vec::new();
+        for (to, msg) in outbox.take() {
+            if let ServerMessage::RoleAssignment {
+                player, actions, ..
+            } = msg
+            {
+                players.push((to, player));
+                dealt.push((player, actions));
+            }
+        }
+        players.sort();
+        // seats follow the join order
+        assert_eq!(
+            players,
+            [
+                (members[0], PlayerId(0)),
+                (members[1], PlayerId(1)),
+                (members[2], PlayerId(2))
+            ]
+        );
+        let game = room(&lobby, &code).game.as_ref().unwrap();
+        for (player, actions) in &dealt {
+            assert_eq!(Some(actions.as_slice()), game.state.player_actions(*player));
+        }
+        assert_eq!(game.replay.seed, game.state.seed());
+
+        let room = room(&lobby, &code);
+        assert!(room.in_game());
+        assert!(room.members.iter().all(|member| !member.ready));
+        assert!(room.game.as_ref().unwrap().timer.is_running());
+        assert_eq!(
+            error_kind(&handle(
+                &mut lobby,
+                members[0],
+                ClientMessage::SetReady(true),
+                members[0]
+            )),
+            Some(ServerErrorKind::GameInProgress)
+        );
+    }
+
+    #[test]
+    fn kick_and_host() {
+        let mut lobby = lobby();
+        let (host, guest, other) = (MemberId(10), MemberId(11), MemberId(12));
+        let code = create(&mut lobby, host);
+        configure(&mut lobby, host, 3);
+        join(&mut lobby, &code, guest);
+        join(&mut lobby, &code, other);
+
+        assert_eq!(
+            error_kind(&handle(
+                &mut lobby,
+                guest,
+                ClientMessage::Kick(other),
+                guest
+            )),
+            Some(ServerErrorKind::NotHost)
+        );
+        assert_eq!(
+            handle(&mut lobby, host, ClientMessage::Kick(other), other),
+            [ServerMessage::RoomLeft { kicked: true }]
+        );
+        assert!(!room(&lobby, &code).contains(other));
+        assert_eq!(
+            error_kind(&handle(&mut lobby, other, ClientMessage::LeaveRoom, other)),
+            Some(ServerErrorKind::NotInRoom)
+        );
+
+        handle(&mut lobby, host, ClientMessage::TransferHost(guest), host);
+        assert_eq!(room(&lobby, &code).host, guest);
+        assert_eq!(
+            error_kind(&handle(&mut lobby, host, ClientMessage::Kick(guest), host)),
+            Some(ServerErrorKind::NotHost)
+        );
+
+        // host leaving hands over to the longest present member
+        join(&mut lobby, &code, other);
+        handle(&mut lobby, guest, ClientMessage::LeaveRoom, guest);
+        assert_eq!(room(&lobby, &code).host, host);
+    }
+
+    #[test]
+    fn join_game_in_progress() {
+        let mut lobby = lobby();
+        let (host, guest, late) = (MemberId(0), MemberId(1), MemberId(2));
+        let code = create(&mut lobby, host);
+        configure(&mut lobby, host, 2);
+        join(&mut lobby, &code, guest);
+        for id in [host, guest] {
+            handle(&mut lobby, id, ClientMessage::SetReady(true), id);
+        }
+        handle(&mut lobby, host, ClientMessage::StartGame, host);
+        assert_eq!(
+            error_kind(&join(&mut lobby, &code, late)),
+            Some(ServerErrorKind::GameInProgress)
+        );
+
+        let guest_player = room(&lobby, &code)
+            .game
+            .as_ref()
+            .unwrap()
+            .player(guest)
+            .unwrap();
+        let mut outbox = Outbox::default();
+        lobby.disconnect(guest, &mut outbox);
+        assert!(outbox.take().iter().all(|(to, _)| *to == host));
+
+        // seat is held until the guest had time to reconnect
+        assert_eq!(
+            error_kind(&join(&mut lobby, &code, late)),
+            Some(ServerErrorKind::GameInProgress)
+        );
+        lobby.tick(GRACE, &mut outbox);
+        assert!(!room(&lobby, &code).contains(guest));
+
+        // late member takes over the vacant seat
+        let msgs = join(&mut lobby, &code, late);
+        assert!(matches!(
+            msgs.as_slice(),
+            [
+                ServerMessage::RoomJoined { .. },
+                ServerMessage::RoleAssignment { player, .. },
+                ServerMessage::Events { first_idx: 0, .. },
+                ServerMessage::TimerSync(TimerSync { running: true, .. }),
+            ] if *player == guest_player
+        ));
+    }
+
+    #[test]
+    fn bots_fill_seats() {
+        let mut lobby = lobby();
+        let (host, late) = (MemberId(0), MemberId(1));
+        let code = create(&mut lobby, host);
+        let bots = BotDifficulty {
+            reaction: Duration::from_millis(100),
+            depth: 1,
+        };
+        handle(
+            &mut lobby,
+            host,
+            ClientMessage::Configure(RoomConfig {
+                num_players: 3,
+                bots: Some(bots),
+                ..Default::default()
+            }),
+            host,
+        );
+        handle(&mut lobby, host, ClientMessage::SetReady(true), host);
+        let msgs = handle(&mut lobby, host, ClientMessage::StartGame, host);
+        assert!(msgs
+            .iter()
+            .any(|msg| matches!(msg, ServerMessage::RoleAssignment { .. })));
+        let game = room(&lobby, &code).game.as_ref().unwrap();
+        assert_eq!(game.seats, [Some(host), None, None]);
+        assert_eq!(game.bots.len(), 2);
+
+        // bots play without anyone else
+        let mut outbox = Outbox::default();
+        for _ in 0..20 {
+            lobby.tick(Duration::from_millis(50), &mut outbox);
+        }
+        assert!(outbox
+            .take()
+            .iter()
+            .any(|(to, msg)| *to == host && matches!(msg, ServerMessage::Events { .. })));
+        let game = room(&lobby, &code).game.as_ref().unwrap();
+        assert!(!game.history.is_empty());
+        assert!(game
+            .replay
+            .actions
+            .iter()
+            .all(|action| action.player != PlayerId(0)));
+
+        // late member takes a seat from a bot, and a bot takes the seat of who leaves
+        let msgs = join(&mut lobby, &code, late);
+        assert!(msgs.iter().any(|msg| matches!(
+            msg,
+            ServerMessage::RoleAssignment {
+                player: PlayerId(1),
+                ..
+            }
+        )));
+        assert_eq!(room(&lobby, &code).game.as_ref().unwrap().bots.len(), 1);
+        handle(&mut lobby, host, ClientMessage::LeaveRoom, host);
+        let game = room(&lobby, &code).game.as_ref().unwrap();
+        assert_eq!(game.seats, [None, Some(late), None]);
+        assert_eq!(game.bots.len(), 2);
+    }
+
+    #[test]
+    fn do_something_disabled() {
+        let mut lobby = lobby();
+        let (host, guest) = (MemberId(0), MemberId(1));
+        let code = create(&mut lobby, host);
+        handle(
+            &mut lobby,
+            host,
+            ClientMessage::Configure(RoomConfig {
+                num_players: 2,
+                do_something: false,
+                ..Default::default()
+            }),
+            host,
+        );
+        join(&mut lobby, &code, guest);
+        for id in [host, guest] {
+            handle(&mut lobby, id, ClientMessage::SetReady(true), id);
+        }
+        handle(&mut lobby, host, ClientMessage::StartGame, host);
+        let target = room(&lobby, &code)
+            .game
+            .as_ref()
+            .unwrap()
+            .player(guest)
+            .unwrap();
+        let ask = ClientMessage::SubmitAction {
+            seq: 1,
+            action: Action::DoSomething(target),
+        };
+        assert_eq!(
+            error_kind(&handle(&mut lobby, host, ask.clone(), host)),
+            Some(ServerErrorKind::ActionRejected { seq: 1 })
+        );
+
+        lobby.rooms.get_mut(&code).unwrap().config.do_something = true;
+        let msgs = handle(&mut lobby, host, ask, guest);
+        assert!(matches!(
+            msgs.as_slice(),
+            [ServerMessage::Events { events, .. }]
+                if events == &[GameEvent::DoSomething { by: PlayerId(1 - target.0), target }]
+        ));
+    }
+
+    /// Host reconnects before its old connection is noticed as dropped
+    #[test]
+    fn reconnect_replaces_connection() {
+        let mut lobby = lobby();
+        let (host, guest, new_host) = (MemberId(0), MemberId(1), MemberId(2));
+        let code = create(&mut lobby, host);
+        lobby.rooms.get_mut(&code).unwrap().config.pause_when_vacant = true;
+        configure(&mut lobby, host, 2);
+        join(&mut lobby, &code, guest);
+        for id in [host, guest] {
+            handle(&mut lobby, id, ClientMessage::SetReady(true), id);
+        }
+        handle(&mut lobby, host, ClientMessage::StartGame, host);
+        let token = room(&lobby, &code).sessions[&host].token;
+        let host_player = room(&lobby, &code).game.as_ref().unwrap().player(host);
+
+        let reconnect = |next_event| ClientMessage::Reconnect {
+            code: code.clone(),
+            token,
+            next_event,
+        };
+        let msgs = handle(&mut lobby, new_host, reconnect(7), new_host);
+        assert!(matches!(
+            msgs.as_slice(),
+            [
+                ServerMessage::RoomJoined { you, token: t, .. },
+                ServerMessage::RoleAssignment { player, .. },
+                ServerMessage::Snapshot {
+                    num_events: 0,
+                    acked: None,
+                    ..
+                },
+                ServerMessage::Events { first_idx: 0, .. },
+                ServerMessage::TimerSync(TimerSync { running: true, .. }),
+            ] if *you == new_host && *t == token && Some(*player) == host_player
+        ));
+        let room = room(&lobby, &code);
+        assert_eq!(room.host, new_host);
+        assert!(!room.contains(host));
+        assert_eq!(
+            error_kind(&handle(&mut lobby, host, ClientMessage::StartGame, host)),
+            Some(ServerErrorKind::NotInRoom)
+        );
+
+        // old connection closing later does not affect the room
+        let mut outbox = Outbox::default();
+        lobby.disconnect(host, &mut outbox);
+        assert!(outbox.take().is_empty());
+
+        // token is only good for its own room
+        let other = create(&mut lobby, MemberId(3));
+        assert_eq!(
+            error_kind(&handle(
+                &mut lobby,
+                MemberId(4),
+                ClientMessage::Reconnect {
+                    code: other,
+                    token,
+                    next_event: 0
+                },
+                MemberId(4)
+            )),
+            Some(ServerErrorKind::UnknownSession)
+        );
+    }
+
+    #[test]
+    fn replay_saved() {
+        let dir = std::env::temp_dir().join(format!("m-mazing-replays-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let timer = Duration::from_secs(10);
+        let mut lobby = Lobby::new(
+            timer,
+            TIMEOUT,
+            GRACE,
+            Some(dir.clone()),
+            fastrand::Rng::with_seed(7),
+        )
+        .unwrap();
+        let host = MemberId(0);
+        let code = create(&mut lobby, host);
+        handle(&mut lobby, host, ClientMessage::SetReady(true), host);
+        handle(&mut lobby, host, ClientMessage::StartGame, host);
+
+        let mut outbox = Outbox::default();
+        lobby.tick(Duration::from_secs(3), &mut outbox);
+        let state = &room(&lobby, &code).game.as_ref().unwrap().state;
+        let action = Pawn::ALL
+            .iter()
+            .flat_map(|pawn| state.legal_actions(PlayerId(0), *pawn))
+            .next()
+            .map(Action::BoardAction)
+            .unwrap();
+        handle(
+            &mut lobby,
+            host,
+            ClientMessage::SubmitAction { seq: 0, action },
+            host,
+        );
+        lobby.tick(timer * 2, &mut outbox);
+
+        let game = room(&lobby, &code).game.as_ref().unwrap();
+        assert_eq!(game.state.phase(), GamePhase::Lost);
+        let path = dir.join(format!(
+            "{code}-{}.ron",
+            GameRng::format_seed(game.replay.seed)
+        ));
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(replay.actions.len(), 1);
+        assert_eq!(replay.actions[0].time(), Duration::from_secs(3));
+
+        let mut playback = ReplayPlayback::new(replay, &lobby.config.tileset).unwrap();
+        playback.seek(playback.length());
+        assert_eq!(playback.state(), &game.state);
+    }
+
+    #[test]
+    fn empty_room_cleanup() {
+        let mut lobby = lobby();
+        let host = MemberId(0);
+        let code = create(&mut lobby, host);
+        let mut outbox = Outbox::default();
+
+        lobby.tick(TIMEOUT * 2, &mut outbox);
+        assert_eq!(lobby.rooms.len(), 1);
+
+        lobby.disconnect(host, &mut outbox);
+        lobby.tick(TIMEOUT / 2, &mut outbox);
+        assert_eq!(lobby.rooms.len(), 1);
+
+        // rejoining resets the countdown
+        join(&mut lobby, &code, MemberId(1));
+        lobby.disconnect(MemberId(1), &mut outbox);
+        lobby.tick(TIMEOUT / 2, &mut outbox);
+        assert_eq!(lobby.rooms.len(), 1);
+        lobby.tick(TIMEOUT / 2, &mut outbox);
+        assert_eq!(lobby.rooms.len(), 0);
+        assert_eq!(
+            error_kind(&join(&mut lobby, &code, MemberId(2))),
+            Some(ServerErrorKind::UnknownRoom)
+        );
+    }
+}
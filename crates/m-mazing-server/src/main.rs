@@ -0,0 +1,87 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use clap::Parser;
+
+use m_mazing_core::{
+    bevy::{self, app::ScheduleRunnerPlugin, log::LogPlugin},
+    log_level,
+};
+
+use bevy::ecs as bevy_ecs; // needed for Resource derive
+use bevy::prelude::*;
+
+mod connection;
+mod lobby;
+mod server;
+use crate::{lobby::*, server::*};
+
+/// How often the server polls its connections
+const TICK_PERIOD: Duration = Duration::from_millis(10);
+
+/// Headless game server
+#[derive(Parser, Debug, Clone)]
+#[clap(about, version, author)]
+pub struct Args {
+    /// Log verbosity
+    #[clap(long, short, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Quiet log
+    #[clap(long, short, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+
+    /// Address to accept raw TCP clients on
+    #[clap(long, default_value = "127.0.0.1:7878")]
+    tcp: String,
+
+    /// Address to accept WebSocket clients on
+    #[clap(long)]
+    websocket: Option<String>,
+
+    /// Sand timer duration in seconds
+    #[clap(long, default_value = "180")]
+    timer: u64,
+
+    /// Seconds after which a room without members is closed
+    #[clap(long, default_value = "300")]
+    room_timeout: u64,
+
+    /// Seconds the seat of a dropped player is held for them to reconnect
+    #[clap(long, default_value = "60")]
+    reconnect_grace: u64,
+
+    /// Directory to save replays of finished games in
+    #[clap(long)]
+    replay_dir: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let level = log_level(args.verbose, args.quiet);
+
+    let lobby = Lobby::new(
+        Duration::from_secs(args.timer),
+        Duration::from_secs(args.room_timeout),
+        Duration::from_secs(args.reconnect_grace),
+        args.replay_dir,
+        fastrand::Rng::new(),
+    )?;
+    let server = GameServer::new(lobby, &args.tcp, args.websocket.as_deref())?;
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(TICK_PERIOD)))
+        .add_plugins(LogPlugin {
+            level,
+            ..Default::default()
+        });
+    info!("Listening for TCP clients on {}", server.tcp_addr());
+    if let Some(addr) = server.websocket_addr() {
+        info!("Listening for WebSocket clients on {addr}");
+    }
+    app.insert_resource(server);
+    add_server_systems(&mut app);
+    app.run();
+
+    Ok(())
+}
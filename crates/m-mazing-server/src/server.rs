@@ -0,0 +1,715 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+use anyhow::{Context, Result};
+
+use m_mazing_core::bevy::{log::*, prelude::*};
+use m_mazing_protocol::prelude::*;
+
+use crate::{bevy_ecs, connection::*, lobby::*};
+
+/// Accepts clients and routes their messages to the lobby
+#[derive(Resource)]
+pub struct GameServer {
+    tcp: TcpListener,
+    websocket: Option<TcpListener>,
+    next_member_id: u64,
+    connections: Vec<Connection>,
+
+    /// Connections that failed and are dropped at the end of the frame
+    closed: Vec<MemberId>,
+    lobby: Lobby,
+    outbox: Outbox,
+}
+
+impl GameServer {
+    pub fn new(lobby: Lobby, tcp_addr: &str, websocket_addr: Option<&str>) -> Result<Self> {
+        let bind = |addr: &str| -> Result<TcpListener> {
+            let listener =
+                TcpListener::bind(addr).with_context(|| format!("Failed to bind to {addr}"))?;
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        };
+
+        Ok(Self {
+            tcp: bind(tcp_addr)?,
+            websocket: websocket_addr.map(bind).transpose()?,
+            next_member_id: 0,
+            connections: Vec::new(),
+            closed: Vec::new(),
+            lobby,
+            outbox: Outbox::default(),
+        })
+    }
+
+    pub fn tcp_addr(&self) -> SocketAddr {
+        self.tcp.local_addr().expect("bound listener")
+    }
+
+    pub fn websocket_addr(&self) -> Option<SocketAddr> {
+        self.websocket
+            .as_ref()
+            .map(|listener| listener.local_addr().expect("bound listener"))
+    }
+
+    fn accept(&mut self) {
+        let listeners = [Some(&self.tcp), self.websocket.as_ref()];
+        let mut accepted: Vec<(TcpStream, SocketAddr, bool)> = Vec::new();
+        for (listener, is_websocket) in listeners.into_iter().zip([false, true]) {
+            let Some(listener) = listener else {
+                continue;
+            };
+            loop {
+                match listener.accept() {
+                    Ok((stream, addr)) => accepted.push((stream, addr, is_websocket)),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        warn!("Failed to accept connection: {err}");
+                        break;
+                    }
+                }
+            }
+        }
+
+        for (stream, addr, is_websocket) in accepted {
+            let id = MemberId(self.next_member_id);
+            self.next_member_id += 1;
+            let conn = if is_websocket {
+                Connection::websocket(id, stream, addr)
+            } else {
+                Connection::tcp(id, stream, addr)
+            };
+            match conn {
+                Ok(conn) => {
+                    info!("Accepted {id:?} from {addr}");
+                    self.connections.push(conn);
+                }
+                Err(err) => warn!("Failed to set up connection from {addr}: {err}"),
+            }
+        }
+    }
+
+    /// Send everything the lobby queued up
+    fn deliver(&mut self) {
+        for (to, msg) in self.outbox.take() {
+            let Some(conn) = self.connections.iter_mut().find(|conn| conn.id == to) else {
+                continue;
+            };
+            if let Err(err) = conn.send(&msg) {
+                debug!("Failed to send to {to:?}: {err}");
+                self.closed.push(to);
+            }
+        }
+    }
+
+    fn receive(&mut self) {
+        let mut received = Vec::new();
+        for conn in self.connections.iter_mut() {
+            match conn.poll() {
+                Ok(msgs) => received.extend(msgs.into_iter().map(|msg| (conn.id, msg))),
+                Err(ConnectionError::Closed) => {
+                    info!("{:?} closed by peer", conn.id);
+                    self.closed.push(conn.id);
+                }
+                Err(err @ ConnectionError::Protocol(ProtocolError::Codec(_))) => {
+                    warn!("Malformed message from {:?}: {err}", conn.id);
+                    let msg = ServerMessage::Error(ServerError::new(
+                        ServerErrorKind::Malformed,
+                        err.to_string(),
+                    ));
+                    let _ = conn.send(&msg);
+                    self.closed.push(conn.id);
+                }
+                Err(err) => {
+                    warn!("Dropping {:?}: {err}", conn.id);
+                    self.closed.push(conn.id);
+                }
+            }
+        }
+
+        // actions that arrive in the same tick are applied in member order, so
+        // conflicts between them are resolved the same way every time
+        received.sort_by_key(|(id, _)| *id);
+        for (id, msg) in received {
+            if !self.closed.contains(&id) {
+                self.lobby.handle(id, msg, &mut self.outbox);
+            }
+        }
+        self.deliver();
+    }
+
+    fn run_timers(&mut self, elapsed: std::time::Duration) {
+        self.lobby.tick(elapsed, &mut self.outbox);
+        self.deliver();
+    }
+
+    fn flush(&mut self) {
+        for id in std::mem::take(&mut self.closed) {
+            let Some(idx) = self.connections.iter().position(|conn| conn.id == id) else {
+                continue;
+            };
+            let mut conn = self.connections.swap_remove(idx);
+            // best effort, e.g. for a malformed message error
+            let _ = conn.flush();
+            self.lobby.disconnect(id, &mut self.outbox);
+        }
+        self.deliver();
+
+        for conn in self.connections.iter_mut() {
+            if let Err(err) = conn.flush() {
+                debug!("Failed to flush {:?}: {err}", conn.id);
+                self.closed.push(conn.id);
+            }
+        }
+    }
+}
+
+pub fn accept_system(mut server: ResMut<GameServer>) {
+    server.accept();
+}
+
+pub fn receive_system(mut server: ResMut<GameServer>) {
+    server.receive();
+}
+
+pub fn timer_system(time: Res<Time>, mut server: ResMut<GameServer>) {
+    server.run_timers(time.delta());
+}
+
+pub fn flush_system(mut server: ResMut<GameServer>) {
+    server.flush();
+}
+
+/// Add the server systems; the caller inserts the [`GameServer`] resource
+pub fn add_server_systems(app: &mut App) {
+    app.add_systems(
+        Update,
+        (accept_system, receive_system, timer_system, flush_system).chain(),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        net::TcpStream,
+        thread::JoinHandle,
+        time::{Duration, Instant},
+    };
+
+    use m_mazing_core::{bevy::time::TimeUpdateStrategy, prelude::*};
+
+    use super::*;
+
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn test_app(timer_duration: Duration, websocket: bool) -> App {
+        let lobby = Lobby::new(
+            timer_duration,
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            None,
+            fastrand::Rng::with_seed(1),
+        )
+        .unwrap();
+        let server =
+            GameServer::new(lobby, "127.0.0.1:0", websocket.then_some("127.0.0.1:0")).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).insert_resource(server);
+        add_server_systems(&mut app);
+        app
+    }
+
+    fn tcp_addr(app: &App) -> SocketAddr {
+        app.world.resource::<GameServer>().tcp_addr()
+    }
+
+    /// Run the server until the client thread is done
+    fn run_client<T>(app: &mut App, client: JoinHandle<T>) -> T {
+        let start = Instant::now();
+        while !client.is_finished() {
+            assert!(start.elapsed() < CLIENT_TIMEOUT, "client timed out");
+            app.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        client.join().expect("client panicked")
+    }
+
+    /// Blocking client that keeps a local copy of the game state
+    struct TestClient {
+        stream: TcpStream,
+        state: Option<GameState>,
+        player: Option<PlayerId>,
+        num_events: u64,
+        token: Option<SessionToken>,
+    }
+
+    impl TestClient {
+        fn connect(addr: SocketAddr) -> Self {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT)).unwrap();
+            handshake(&mut stream).unwrap();
+            Self {
+                stream,
+                state: None,
+                player: None,
+                num_events: 0,
+                token: None,
+            }
+        }
+
+        fn send(&mut self, msg: ClientMessage) {
+            write_message(&mut self.stream, &msg).unwrap();
+        }
+
+        fn recv(&mut self) -> ServerMessage {
+            let msg = read_message(&mut self.stream).unwrap();
+            match &msg {
+                ServerMessage::RoomJoined { token, .. } => self.token = Some(*token),
+                ServerMessage::RoleAssignment {
+                    player,
+                    num_players,
+                    scenario,
+                    seed,
+                    ..
+                } => {
+                    let tileset = game_tileset().unwrap();
+                    let scenario = Scenario::builtin(*scenario).unwrap();
+                    self.state =
+                        Some(GameState::new(*num_players, &scenario, &tileset, *seed).unwrap());
+                    self.player = Some(*player);
+                    self.num_events = 0;
+                }
+                ServerMessage::Snapshot {
+                    state, num_events, ..
+                } => {
+                    self.state = Some(state.as_ref().clone());
+                    self.num_events = *num_events;
+                }
+                ServerMessage::Events {
+                    first_idx, events, ..
+                } => {
+                    // events already in a snapshot are skipped
+                    let skip = self.num_events - first_idx;
+                    let state = self.state.as_mut().unwrap();
+                    for event in events.iter().skip(skip as usize) {
+                        state.apply_event(event);
+                    }
+                    self.num_events = first_idx + events.len() as u64;
+                }
+                _ => (),
+            }
+            msg
+        }
+
+        /// Receive until a message matches `pred`
+        fn recv_until(&mut self, pred: impl Fn(&ServerMessage) -> bool) -> ServerMessage {
+            loop {
+                let msg = self.recv();
+                if pred(&msg) {
+                    return msg;
+                }
+            }
+        }
+
+        fn recv_error(&mut self) -> ServerErrorKind {
+            let ServerMessage::Error(err) =
+                self.recv_until(|msg| matches!(msg, ServerMessage::Error(_)))
+            else {
+                unreachable!();
+            };
+            err.kind
+        }
+
+        fn recv_events(&mut self) -> Vec<GameEvent> {
+            let ServerMessage::Events { events, .. } =
+                self.recv_until(|msg| matches!(msg, ServerMessage::Events { .. }))
+            else {
+                unreachable!();
+            };
+            events
+        }
+
+        fn create_room(&mut self) -> RoomCode {
+            self.send(ClientMessage::CreateRoom {
+                name: "host".to_string(),
+            });
+            let ServerMessage::RoomJoined { room, .. } = self.recv() else {
+                panic!("expected room joined");
+            };
+            room.code
+        }
+
+        fn join_room(&mut self, code: &RoomCode) -> RoomInfo {
+            self.send(ClientMessage::JoinRoom {
+                code: code.clone(),
+                name: "guest".to_string(),
+            });
+            let ServerMessage::RoomJoined { room, .. } = self.recv() else {
+                panic!("expected room joined");
+            };
+            room
+        }
+
+        /// Wait for the game to start; returns the first timer sync
+        fn recv_start(&mut self) -> TimerSync {
+            self.recv_until(|msg| matches!(msg, ServerMessage::RoleAssignment { .. }));
+            assert!(matches!(
+                self.recv(),
+                ServerMessage::Events { first_idx: 0, .. }
+            ));
+            let ServerMessage::TimerSync(sync) = self.recv() else {
+                panic!("expected timer sync");
+            };
+            sync
+        }
+
+        fn legal_action(&self) -> PawnAction {
+            let state = self.state.as_ref().unwrap();
+            let player = self.player.unwrap();
+            Pawn::ALL
+                .iter()
+                .flat_map(|pawn| state.legal_actions(player, *pawn))
+                .next()
+                .expect("a legal action")
+        }
+    }
+
+    #[test]
+    fn room_and_play() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut alice = TestClient::connect(addr);
+            let code = alice.create_room();
+            alice.send(ClientMessage::Configure(RoomConfig {
+                scenario: 1,
+                num_players: 2,
+                ..Default::default()
+            }));
+            alice.recv_until(|msg| matches!(msg, ServerMessage::RoomUpdate(_)));
+
+            let mut bob = TestClient::connect(addr);
+            let room = bob.join_room(&code);
+            assert_eq!(room.members.len(), 2);
+            assert_eq!(room.config.num_players, 2);
+
+            let mut carol = TestClient::connect(addr);
+            carol.send(ClientMessage::JoinRoom {
+                code: code.clone(),
+                name: "carol".to_string(),
+            });
+            assert_eq!(carol.recv_error(), ServerErrorKind::RoomFull);
+
+            alice.send(ClientMessage::StartGame);
+            assert_eq!(alice.recv_error(), ServerErrorKind::NotReady);
+            for client in [&mut alice, &mut bob] {
+                client.send(ClientMessage::SetReady(true));
+            }
+            bob.send(ClientMessage::StartGame);
+            assert_eq!(bob.recv_error(), ServerErrorKind::NotHost);
+            alice.send(ClientMessage::StartGame);
+            for client in [&mut alice, &mut bob] {
+                assert!(client.recv_start().running);
+            }
+            assert_ne!(alice.player, bob.player);
+
+            let action = alice.legal_action();
+            alice.send(ClientMessage::SubmitAction {
+                seq: 1,
+                action: Action::BoardAction(action),
+            });
+            let ServerMessage::Events { events, acked, .. } =
+                alice.recv_until(|msg| matches!(msg, ServerMessage::Events { .. }))
+            else {
+                unreachable!();
+            };
+            assert!(!events.is_empty());
+            assert_eq!(acked, Some(1));
+            assert!(matches!(
+                bob.recv_until(|msg| matches!(msg, ServerMessage::Events { .. })),
+                ServerMessage::Events { events: theirs, acked: None, .. } if theirs == events
+            ));
+
+            // pawn is no longer where it was
+            alice.send(ClientMessage::SubmitAction {
+                seq: 2,
+                action: Action::BoardAction(action),
+            });
+            assert_eq!(
+                alice.recv_error(),
+                ServerErrorKind::ActionRejected { seq: 2 }
+            );
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn not_in_room_and_ping() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut client = TestClient::connect(addr);
+            client.send(ClientMessage::Ping { nonce: 99 });
+            assert_eq!(client.recv(), ServerMessage::Pong { nonce: 99 });
+
+            client.send(ClientMessage::StartGame);
+            assert_eq!(client.recv_error(), ServerErrorKind::NotInRoom);
+            client.send(ClientMessage::JoinRoom {
+                code: RoomCode::parse("ZZZZ").unwrap(),
+                name: "lost".to_string(),
+            });
+            assert_eq!(client.recv_error(), ServerErrorKind::UnknownRoom);
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn disconnect_leaves_room() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut host = TestClient::connect(addr);
+            let code = host.create_room();
+            host.send(ClientMessage::Configure(RoomConfig {
+                scenario: 1,
+                num_players: 2,
+                ..Default::default()
+            }));
+            host.recv_until(|msg| matches!(msg, ServerMessage::RoomUpdate(_)));
+
+            TestClient::connect(addr).join_room(&code);
+            // guest was dropped at the end of the statement
+            host.recv_until(
+                |msg| matches!(msg, ServerMessage::RoomUpdate(room) if room.members.len() == 1),
+            );
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn reconnect() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut host = TestClient::connect(addr);
+            let code = host.create_room();
+            host.send(ClientMessage::Configure(RoomConfig {
+                scenario: 1,
+                num_players: 2,
+                pause_when_vacant: true,
+                ..Default::default()
+            }));
+            host.recv_until(|msg| matches!(msg, ServerMessage::RoomUpdate(_)));
+            let mut guest = TestClient::connect(addr);
+            guest.join_room(&code);
+            for client in [&mut host, &mut guest] {
+                client.send(ClientMessage::SetReady(true));
+            }
+            host.recv_until(|msg| {
+                matches!(msg, ServerMessage::RoomUpdate(room)
+                    if room.members.iter().all(|member| member.ready))
+            });
+            host.send(ClientMessage::StartGame);
+            for client in [&mut host, &mut guest] {
+                client.recv_start();
+            }
+            let action = host.legal_action();
+            host.send(ClientMessage::SubmitAction {
+                seq: 0,
+                action: Action::BoardAction(action),
+            });
+            host.recv_events();
+            let (player, token) = (guest.player, guest.token.unwrap());
+            drop(guest);
+
+            // seat is held and the timer waits for the guest
+            host.recv_until(|msg| {
+                matches!(msg, ServerMessage::RoomUpdate(room)
+                    if room.members.iter().any(|member| !member.connected))
+            });
+            assert!(matches!(
+                host.recv_until(|msg| matches!(msg, ServerMessage::TimerSync(_))),
+                ServerMessage::TimerSync(TimerSync { running: false, .. })
+            ));
+
+            let mut guest = TestClient::connect(addr);
+            guest.send(ClientMessage::Reconnect {
+                code: code.clone(),
+                token: SessionToken(token.0 ^ 1),
+                next_event: 0,
+            });
+            assert_eq!(guest.recv_error(), ServerErrorKind::UnknownSession);
+            guest.send(ClientMessage::Reconnect {
+                code,
+                token,
+                next_event: 0,
+            });
+            assert!(matches!(
+                guest.recv(),
+                ServerMessage::RoomJoined { room, .. } if room.in_game
+            ));
+            assert!(matches!(
+                guest.recv(),
+                ServerMessage::RoleAssignment { player: p, .. } if Some(p) == player
+            ));
+            assert!(matches!(guest.recv(), ServerMessage::Snapshot { .. }));
+            assert!(!guest.recv_events().is_empty());
+            assert_eq!(guest.state, host.state);
+            assert!(matches!(
+                guest.recv(),
+                ServerMessage::TimerSync(TimerSync { running: true, .. })
+            ));
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT)).unwrap();
+            Handshake {
+                version: PROTOCOL_VERSION + 1,
+            }
+            .write(&mut stream)
+            .unwrap();
+            Handshake::read(&mut stream).unwrap();
+
+            // server hangs up instead of answering
+            let _ = write_message(&mut stream, &ClientMessage::Ping { nonce: 0 });
+            assert!(read_message::<_, ServerMessage>(&mut stream).is_err());
+        });
+        run_client(&mut app, client);
+        assert!(app.world.resource::<GameServer>().connections.is_empty());
+    }
+
+    #[test]
+    fn malformed_message() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, false);
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut client = TestClient::connect(addr);
+            let mut frame = 1u32.to_be_bytes().to_vec();
+            frame.push(0xff);
+            client.stream.write_all(&frame).unwrap();
+            assert_eq!(client.recv_error(), ServerErrorKind::Malformed);
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn websocket() {
+        let mut app = test_app(SandTimer::DEFAULT_DURATION, true);
+        let addr = app.world.resource::<GameServer>().websocket_addr().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let (mut ws, _) = tungstenite::connect(format!("ws://{addr}")).unwrap();
+            let mut decoder = FrameDecoder::default();
+            let mut recv = |ws: &mut tungstenite::WebSocket<_>| loop {
+                if let Some(msg) = decoder.next_message::<ServerMessage>().unwrap() {
+                    return msg;
+                }
+                if let tungstenite::Message::Binary(data) = ws.read().unwrap() {
+                    decoder.extend(&data);
+                }
+            };
+
+            let mut bytes = Handshake::default().to_bytes().to_vec();
+            bytes.extend(
+                encode(&ClientMessage::CreateRoom {
+                    name: "web".to_string(),
+                })
+                .unwrap(),
+            );
+            ws.send(tungstenite::Message::Binary(bytes)).unwrap();
+
+            // server handshake arrives as a message of its own
+            let tungstenite::Message::Binary(data) = ws.read().unwrap() else {
+                panic!("expected binary handshake");
+            };
+            let handshake: [u8; Handshake::SIZE] = data.as_slice().try_into().unwrap();
+            Handshake::from_bytes(handshake).unwrap().check().unwrap();
+
+            assert!(matches!(
+                recv(&mut ws),
+                ServerMessage::RoomJoined { you, room, .. } if room.host == you
+            ));
+        });
+        run_client(&mut app, client);
+    }
+
+    #[test]
+    fn send_during_websocket_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, addr) = listener.accept().unwrap();
+        let mut conn = Connection::websocket(MemberId(1), stream, addr).unwrap();
+
+        // the client has not sent its upgrade request yet
+        assert!(matches!(
+            conn.send(&ServerMessage::Pong { nonce: 1 }),
+            Err(ConnectionError::HandshakePending)
+        ));
+        drop(client);
+    }
+
+    #[test]
+    fn time_up() {
+        let mut app = test_app(Duration::from_secs(3), false);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+        let addr = tcp_addr(&app);
+
+        let client = std::thread::spawn(move || {
+            let mut client = TestClient::connect(addr);
+            client.create_room();
+            client.send(ClientMessage::SetReady(true));
+            client.send(ClientMessage::StartGame);
+            assert_eq!(
+                client.recv_start(),
+                TimerSync {
+                    remaining_ms: 3000,
+                    running: true
+                }
+            );
+            let action = client.legal_action();
+
+            assert_eq!(client.recv_events(), vec![GameEvent::TimeUp]);
+            assert_eq!(client.state.as_ref().unwrap().phase(), GamePhase::Lost);
+            assert!(matches!(
+                client.recv(),
+                ServerMessage::RoomUpdate(RoomInfo { in_game: false, .. })
+            ));
+            assert!(matches!(
+                client.recv(),
+                ServerMessage::TimerSync(TimerSync {
+                    remaining_ms: 0,
+                    running: false
+                })
+            ));
+
+            client.send(ClientMessage::SubmitAction {
+                seq: 5,
+                action: Action::BoardAction(action),
+            });
+            assert_eq!(
+                client.recv_error(),
+                ServerErrorKind::ActionRejected { seq: 5 }
+            );
+        });
+        run_client(&mut app, client);
+    }
+}
@@ -54,13 +54,14 @@ fn print_entity(
 
 pub fn debug_entity(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<TileUtilInput>>,
     root_entities: Query<Entity, Without<Parent>>,
     entities_with_parent: Query<(Entity, &Parent)>,
     all_entities: &Entities,
     archetypes: &Archetypes,
     components: &Components,
 ) {
-    if !keyboard_input.just_pressed(KeyCode::F1) {
+    if !bindings.just_pressed(&keyboard_input, TileUtilInput::DebugEntities) {
         return;
     }
 
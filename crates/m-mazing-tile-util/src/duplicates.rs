@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+/// Print the tiles of `tile_file` that are the same up to rotation as another tile in it
+/// or as a game tile
+pub fn print_duplicates(tile_file: &Path) -> Result<()> {
+    let tile_str = std::fs::read_to_string(tile_file)
+        .with_context(|| format!("Failed to read tile file {tile_file:?}"))?;
+    let custom = m_mazing_core::tile::tileset::tileset_from_str(&tile_str)
+        .context("failed to parse tileset")?;
+    let game = game_tileset().context("Failed to parse game tiles")?;
+
+    let labels: Vec<String> = custom
+        .iter()
+        .map(|(name, _)| name.clone())
+        .chain(game.iter().map(|(name, _)| format!("{name} (game)")))
+        .collect();
+    let tiles: Vec<&Tile> = custom
+        .iter()
+        .chain(game.iter())
+        .map(|(_, tile)| tile)
+        .collect();
+    let orientations: Vec<Orientation> = tiles.iter().map(|tile| tile.canonical().1).collect();
+
+    let groups: Vec<Vec<usize>> = duplicate_tiles(tiles.iter().copied())
+        .into_iter()
+        // game tiles are known to be unique
+        .filter(|group| group[0] < custom.len())
+        .collect();
+    if groups.is_empty() {
+        println!("no duplicates in {} tiles", custom.len());
+        return Ok(());
+    }
+    for group in groups.iter() {
+        let first = group[0];
+        println!("{} ({})", labels[first], tiles[first].fingerprint());
+        for idx in group[1..].iter().copied() {
+            // turns the first tile of the group into this one
+            let turned = orientations[first].then(orientations[idx].inverse());
+            println!("  same as {} rotated {}", labels[idx], turned);
+        }
+    }
+    Ok(())
+}
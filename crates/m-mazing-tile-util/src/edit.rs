@@ -0,0 +1,138 @@
+use bevy::window::PrimaryWindow;
+use m_mazing_core::prelude::*;
+use m_mazing_core::render::render_tile::TilePart;
+
+use crate::*;
+
+/// Distance from the line of a wall within which a click hits the wall instead of the
+/// cell
+const WALL_MARGIN: f32 = 0.2;
+
+#[derive(Debug, Default, Resource)]
+pub struct TileEditor {
+    pub enabled: bool,
+
+    /// Part of the tile the mouse button went down on
+    pub pressed: Option<TilePart>,
+}
+
+/// Edit made by pressing the mouse on `pressed` and releasing it on `released`: a click
+/// cycles a wall or cell, and a drag from one cell to another adds or removes an
+/// escalator between them
+fn mouse_edit(pressed: TilePart, released: TilePart) -> Option<TileEdit> {
+    match (pressed, released) {
+        (TilePart::Cell(from), TilePart::Cell(to)) if from != to => {
+            Some(TileEdit::Escalator(EscalatorLocation([from, to])))
+        }
+        (TilePart::Cell(coord), TilePart::Cell(_)) => Some(TileEdit::Cell(coord)),
+        (TilePart::Wall(coord, dir), _) if released == pressed => Some(TileEdit::Wall(coord, dir)),
+        _ => None,
+    }
+}
+
+pub fn edit_keyboard_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<TileUtilInput>>,
+    mut editor: ResMut<TileEditor>,
+    mut ctx: NonSendMut<Ctx>,
+) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::ToggleEdit) {
+        editor.enabled = !editor.enabled;
+        editor.pressed = None;
+        info!("editor enabled = {}", editor.enabled);
+    }
+
+    if !editor.enabled {
+        return;
+    }
+
+    // only edits are saved, so viewing a file never rewrites it
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::Save) && ctx.unsaved {
+        match ctx.save() {
+            Ok(()) => info!("Saved {:?}", ctx.tile_file),
+            Err(err) => error!("Failed to save: {:#}", err),
+        }
+    }
+    let undo = bindings.just_pressed(&keyboard_input, TileUtilInput::Undo);
+    let redo = bindings.just_pressed(&keyboard_input, TileUtilInput::Redo);
+    if !(undo || redo) {
+        return;
+    }
+    let idx = ctx.tile_idx as usize;
+    let ctx = &mut *ctx;
+    let Some((name, tile)) = ctx.tileset.get_mut(idx) else {
+        return;
+    };
+    let history = ctx.histories.entry(idx).or_default();
+    let done = if undo {
+        history.undo(tile)
+    } else {
+        history.redo(tile)
+    };
+    if done {
+        ctx.unsaved = true;
+    } else {
+        info!(
+            "{}: nothing to {}",
+            name,
+            if undo { "undo" } else { "redo" }
+        );
+    }
+}
+
+pub fn edit_mouse_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tile_rotation: Res<TileRotation>,
+    mut editor: ResMut<TileEditor>,
+    mut ctx: NonSendMut<Ctx>,
+) {
+    if !editor.enabled {
+        return;
+    }
+    let pressed = mouse_input.just_pressed(MouseButton::Left);
+    let released = mouse_input.just_released(MouseButton::Left);
+    if !(pressed || released) {
+        return;
+    }
+
+    let part = match (windows.get_single(), cameras.get_single()) {
+        (Ok(window), Ok((camera, camera_transform))) => window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+            .and_then(|world| TilePart::from_world(world, Vec2::ZERO, WALL_MARGIN)),
+        _ => None,
+    };
+    // only edits change what is shown
+    let editor = editor.bypass_change_detection();
+    if pressed {
+        editor.pressed = part;
+    }
+    if !released {
+        return;
+    }
+    let Some(edit) = editor
+        .pressed
+        .take()
+        .zip(part)
+        .and_then(|(pressed, released)| mouse_edit(pressed, released))
+    else {
+        return;
+    };
+
+    // the tile is shown turned, but edited as it is in the tile file
+    let edit = edit.as_oriented(tile_rotation.orientation.inverse());
+    let idx = ctx.tile_idx as usize;
+    let ctx = &mut *ctx;
+    let Some((name, tile)) = ctx.tileset.get_mut(idx) else {
+        return;
+    };
+    match ctx.histories.entry(idx).or_default().apply(tile, edit) {
+        Ok(()) => {
+            debug!("{}: {:?}", name, edit);
+            ctx.unsaved = true;
+        }
+        Err(err) => warn!("{}: {}", name, err),
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+
+/// Constraints on generated tiles that the mall shares
+#[derive(clap::Args, Debug, Clone)]
+pub struct TileShapeArgs {
+    /// Explore exits of generated tiles, besides the entrance
+    #[clap(long, default_value = "2")]
+    explore_exits: u8,
+
+    /// Escalators on generated tiles
+    #[clap(long, default_value = "0")]
+    escalators: u8,
+
+    /// Walls to open in generated tiles beyond the ones needed to reach every cell
+    #[clap(long, default_value = "3")]
+    extra_openings: u8,
+}
+
+impl TileShapeArgs {
+    pub fn constraints(&self) -> TileConstraints {
+        TileConstraints {
+            explore_exits: self.explore_exits,
+            escalators: self.escalators,
+            extra_openings: self.extra_openings,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct GenerateArgs {
+    /// Tiles to generate
+    tiles: usize,
+
+    /// Seed of the first tile as 16 hex digits or any phrase such as a date; the
+    /// following ones count up from it, random if not given
+    #[clap(long)]
+    seed: Option<String>,
+
+    #[clap(flatten)]
+    shape: TileShapeArgs,
+
+    /// Warp colors (green, orange, yellow, purple)
+    #[clap(long, value_delimiter = ',', value_parser = parse_pawn)]
+    warps: Vec<Pawn>,
+
+    /// Put a camera on each tile
+    #[clap(long)]
+    camera: bool,
+
+    /// Put a timer flip on each tile
+    #[clap(long)]
+    timer_flip: bool,
+
+    /// Put a crystal ball on each tile
+    #[clap(long)]
+    crystal_ball: bool,
+}
+
+pub fn parse_pawn(name: &str) -> Result<Pawn, String> {
+    Pawn::ALL
+        .iter()
+        .copied()
+        .find(|pawn| format!("{pawn:?}").eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Unknown pawn {name:?}, expected green, orange, yellow or purple"))
+}
+
+/// Print `args.tiles` random tiles as a tileset, each named after the seed it was made
+/// from
+pub fn generate(args: &GenerateArgs) -> Result<()> {
+    let constraints = TileConstraints {
+        warps: args.warps.clone(),
+        camera: args.camera,
+        timer_flip: args.timer_flip,
+        crystal_ball: args.crystal_ball,
+        ..args.shape.constraints()
+    };
+    let seed = args
+        .seed
+        .as_deref()
+        .map_or_else(GameRng::random_seed, GameRng::parse_seed);
+
+    let tiles = (0..args.tiles)
+        .map(|idx| {
+            let seed = seed.wrapping_add(idx as u64);
+            let tile = constraints.generate(&mut GameRng::new(seed))?;
+            Ok((format!("gen-{}", GameRng::format_seed(seed)), tile))
+        })
+        .collect::<Result<Vec<_>, TileGenError>>()
+        .context("Failed to generate tiles")?;
+    print!(
+        "{}",
+        m_mazing_core::tile::tileset::tileset_to_string(&tiles)
+    );
+    Ok(())
+}
@@ -5,19 +5,22 @@ use m_mazing_core::render::RenderState;
 
 use crate::*;
 
+#[allow(clippy::too_many_arguments)]
 pub fn keyboard_input_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<TileUtilInput>>,
     mut app_exit_events: EventWriter<AppExit>,
     mut should_refresh: ResMut<RefreshTile>,
     mut ctx: NonSendMut<Ctx>,
     mut availability: ResMut<TileAvailability>,
     mut tile_rotation: ResMut<TileRotation>,
+    mut render: ResMut<RenderState>,
 ) {
-    if keyboard_input.any_pressed([KeyCode::Escape, KeyCode::KeyQ]) {
+    if bindings.pressed(&keyboard_input, TileUtilInput::Quit) {
         app_exit_events.send(AppExit);
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyR) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::Reload) {
         should_refresh.0 = true;
         match ctx.refresh() {
             Ok(()) => info!("Manually reloading"),
@@ -25,10 +28,10 @@ pub fn keyboard_input_system(
         }
     }
 
-    if keyboard_input.any_just_pressed([KeyCode::ArrowRight, KeyCode::ArrowDown]) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::NextTile) {
         ctx.tile_idx += 1;
     }
-    if keyboard_input.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowUp]) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::PrevTile) {
         ctx.tile_idx -= 1;
     }
 
@@ -41,14 +44,20 @@ pub fn keyboard_input_system(
         ctx.tile_idx = new_tile_idx;
     }
 
-    if keyboard_input.just_pressed(KeyCode::Home) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::FirstTile) {
         ctx.tile_idx = 0;
     }
-    if keyboard_input.just_pressed(KeyCode::End) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::LastTile) {
         ctx.tile_idx = ctx.tileset.len() as isize - 1;
     }
 
-    if keyboard_input.any_just_pressed([KeyCode::KeyK, KeyCode::KeyU]) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::NextTheme) {
+        let (name, theme) = Theme::next_builtin(&render.theme);
+        info!("Switching to theme {:?}", name);
+        render.theme = theme;
+    }
+
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::ToggleUsed) {
         availability.0 = match availability.0 {
             CellItemAvailability::Available => CellItemAvailability::Used,
             CellItemAvailability::Used => CellItemAvailability::Available,
@@ -56,13 +65,16 @@ pub fn keyboard_input_system(
         info!("availability = {:?}", availability.0);
     }
 
-    const NUM_SPIN_DIRS: u8 = 4;
-    if keyboard_input.any_just_pressed([KeyCode::BracketLeft]) {
-        tile_rotation.left_turns = (tile_rotation.left_turns + 1).rem_euclid(NUM_SPIN_DIRS);
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::RotateLeft) {
+        tile_rotation.orientation = tile_rotation
+            .orientation
+            .rotated(SpinDirection::CounterClockwise);
+    }
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::RotateRight) {
+        tile_rotation.orientation = tile_rotation.orientation.rotated(SpinDirection::Clockwise);
     }
-    if keyboard_input.any_just_pressed([KeyCode::BracketRight]) {
-        tile_rotation.left_turns =
-            (tile_rotation.left_turns as i8 - 1).rem_euclid(NUM_SPIN_DIRS as i8) as u8;
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::Mirror) {
+        tile_rotation.orientation = tile_rotation.orientation.mirrored();
     }
 }
 
@@ -83,20 +95,6 @@ pub fn notify_tileset_change(mut should_refresh: ResMut<RefreshTile>, mut ctx: N
     }
 }
 
-/*
-pub fn update(ctx: &mut Ctx) {
-    // todo: smarter fit rect for all tiles
-    let fit_rect = Rect {
-        x: -3.,
-        y: -3.,
-        w: 6.,
-        h: 6.,
-    };
-    let whole_camera = camera_zoom_to_fit(fit_rect);
-    set_camera(&whole_camera);
-}
-*/
-
 /*
 pub fn draw(ctx: NonSend<Ctx>, render: Res<RenderState>, mut commands: Commands) {
     // screen space camera for text
@@ -112,10 +110,17 @@ pub fn spawn_tile(
     refresh: Res<RefreshTile>,
     tile: Option<ResMut<CurrentTile>>,
     tile_rotation: Res<TileRotation>,
+    editor: Res<TileEditor>,
     mut commands: Commands,
     mut query: Query<&mut Text, With<TitleString>>,
 ) {
-    if !(refresh.0 || ctx.is_changed() || availability.is_changed() || tile_rotation.is_changed()) {
+    if !(refresh.0
+        || ctx.is_changed()
+        || availability.is_changed()
+        || tile_rotation.is_changed()
+        || editor.is_changed()
+        || render.is_changed())
+    {
         return;
     }
 
@@ -127,9 +132,14 @@ pub fn spawn_tile(
 
     let title_str = &mut query.single_mut().sections[0].value;
     let tile = if let Some(item) = ctx.tileset.get(ctx.tile_idx as usize) {
+        let editing = match (editor.enabled, ctx.unsaved) {
+            (false, _) => "",
+            (true, false) => ", editing",
+            (true, true) => ", editing (unsaved)",
+        };
         *title_str = format!(
-            "TILE: {} (idx={})\navail={:?}, left_turns={}",
-            item.0, ctx.tile_idx, availability.0, tile_rotation.left_turns
+            "TILE: {} (idx={})\navail={:?}, orientation={}{}",
+            item.0, ctx.tile_idx, availability.0, tile_rotation.orientation, editing
         );
         &item.1
     } else {
@@ -141,9 +151,7 @@ pub fn spawn_tile(
     for cell in tile.cells_iter_mut() {
         cell.set_availability(availability.0);
     }
-    for _ in 0..tile_rotation.left_turns {
-        tile.rotate(SpinDirection::CounterClockwise);
-    }
+    tile.orient(tile_rotation.orientation);
 
     let id = tile.spawn(Vec2::ZERO, &render, &mut commands);
 
@@ -151,8 +159,12 @@ pub fn spawn_tile(
     commands.insert_resource(new_tile);
 }
 
-pub fn print_tile(keyboard_input: Res<ButtonInput<KeyCode>>, tile: Option<Res<CurrentTile>>) {
-    if keyboard_input.just_pressed(KeyCode::KeyP) {
+pub fn print_tile(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings<TileUtilInput>>,
+    tile: Option<Res<CurrentTile>>,
+) {
+    if bindings.just_pressed(&keyboard_input, TileUtilInput::Print) {
         match tile {
             None => println!("No tile"),
             Some(tile) => println!("{:#?}", tile.tile),
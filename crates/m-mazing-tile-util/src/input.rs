@@ -0,0 +1,122 @@
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileUtilInput {
+    Quit,
+    NextTile,
+    PrevTile,
+    FirstTile,
+    LastTile,
+    ToggleUsed,
+    RotateLeft,
+    RotateRight,
+    Mirror,
+    ToggleEdit,
+    Undo,
+    Redo,
+    Save,
+    Print,
+    Reload,
+    DebugEntities,
+    NextTheme,
+}
+
+impl InputAction for TileUtilInput {
+    const ALL: &'static [Self] = &[
+        Self::Quit,
+        Self::NextTile,
+        Self::PrevTile,
+        Self::FirstTile,
+        Self::LastTile,
+        Self::ToggleUsed,
+        Self::RotateLeft,
+        Self::RotateRight,
+        Self::Mirror,
+        Self::ToggleEdit,
+        Self::Undo,
+        Self::Redo,
+        Self::Save,
+        Self::Print,
+        Self::Reload,
+        Self::DebugEntities,
+        Self::NextTheme,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NextTile => "next-tile",
+            Self::PrevTile => "prev-tile",
+            Self::FirstTile => "first-tile",
+            Self::LastTile => "last-tile",
+            Self::ToggleUsed => "toggle-used",
+            Self::RotateLeft => "rotate-left",
+            Self::RotateRight => "rotate-right",
+            Self::Mirror => "mirror",
+            Self::ToggleEdit => "toggle-edit",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Save => "save",
+            Self::Print => "print",
+            Self::Reload => "reload",
+            Self::DebugEntities => "debug-entities",
+            Self::NextTheme => "next-theme",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NextTile => "next tile",
+            Self::PrevTile => "previous tile",
+            Self::FirstTile => "first tile",
+            Self::LastTile => "last tile",
+            Self::ToggleUsed => "toggle cell used",
+            Self::RotateLeft => "rotate left",
+            Self::RotateRight => "rotate right",
+            Self::Mirror => "mirror left to right",
+            Self::ToggleEdit => "toggle editor",
+            Self::Undo => "undo edit",
+            Self::Redo => "redo edit",
+            Self::Save => "save edits",
+            Self::Print => "print",
+            Self::Reload => "reload",
+            Self::DebugEntities => "print entities",
+            Self::NextTheme => "next theme",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Self::Quit => &[KeyCode::Escape, KeyCode::KeyQ],
+            Self::NextTile => &[KeyCode::ArrowRight, KeyCode::ArrowDown],
+            Self::PrevTile => &[KeyCode::ArrowLeft, KeyCode::ArrowUp],
+            Self::FirstTile => &[KeyCode::Home],
+            Self::LastTile => &[KeyCode::End],
+            Self::ToggleUsed => &[KeyCode::KeyK, KeyCode::KeyU],
+            Self::RotateLeft => &[KeyCode::BracketLeft],
+            Self::RotateRight => &[KeyCode::BracketRight],
+            Self::Mirror => &[KeyCode::KeyM],
+            Self::ToggleEdit => &[KeyCode::KeyE],
+            Self::Undo => &[KeyCode::KeyZ],
+            Self::Redo => &[KeyCode::KeyY],
+            Self::Save => &[KeyCode::KeyS],
+            Self::Print => &[KeyCode::KeyP],
+            Self::Reload => &[KeyCode::KeyR],
+            Self::DebugEntities => &[KeyCode::F1],
+            Self::NextTheme => &[KeyCode::KeyT],
+        }
+    }
+}
+
+/// Key legend shown on screen
+pub fn legend(bindings: &KeyBindings<TileUtilInput>) -> String {
+    TileUtilInput::ALL
+        .iter()
+        .filter(|input| **input != TileUtilInput::DebugEntities)
+        .map(|input| format!("{} - {};", bindings.describe(*input), input.description()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,35 +1,42 @@
-use std::{fs::File, io::Read, path::PathBuf, sync::mpsc};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use m_mazing_core::bevy;
 use m_mazing_core::bevy::log::LogPlugin;
 use m_mazing_core::bevy_prototype_lyon::plugin::ShapePlugin;
 use m_mazing_core::prelude::*;
+use m_mazing_core::render::camera::{camera_auto_fit_system, AutoFitCamera};
+use m_mazing_core::render::theme::{theme_clear_color_system, theme_reload_system, ThemeFile};
 use m_mazing_core::render::RenderState;
 use notify::Watcher;
 
 use bevy::ecs as bevy_ecs; // needed for Component derive
 use bevy::prelude::*;
-use bevy::render::camera::ScalingMode;
 
 mod debug;
+mod duplicates;
+mod edit;
+mod generate;
 mod gui;
-use crate::{debug::*, gui::*};
-
-const LEGEND: &str = "
-Arrow keys - cycle;
-K/U - toggle cell used;
-[] - rotate;
-P - print;
-R - reload;
-Home/End - start/end;
-";
+mod input;
+mod mall;
+mod simulate;
+mod solve;
+use crate::{
+    debug::*, duplicates::*, edit::*, generate::*, gui::*, input::*, mall::*, simulate::*, solve::*,
+};
 
 /// Utility to debug Tiles
 #[derive(Parser, Debug, Clone)]
-#[clap(about, version, author)]
+#[clap(about, version, author, subcommand_negates_reqs = true)]
 pub struct Args {
     /// Log verbosity
     #[clap(long, short, action = clap::ArgAction::Count)]
@@ -40,13 +47,56 @@ pub struct Args {
     quiet: u8,
 
     /// File with tile data
-    #[clap(long, short)]
-    tile_file: PathBuf,
+    #[clap(long, short, required = true)]
+    tile_file: Option<PathBuf>,
 
     /// Start idx
     #[clap(long = "start-idx", short = 'i', default_value = "0")]
     index: usize,
+
+    /// Key bindings config file
+    #[clap(long, short)]
+    key_bindings: Option<PathBuf>,
+
+    /// Builtin theme name (light, dark, high-contrast, colorblind) or theme file to watch
+    #[clap(long, default_value = "light")]
+    theme: String,
+
+    /// Run without a window instead of showing the tile file
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Play a replay file, print the final state and exit
+    Replay {
+        /// Replay file
+        path: PathBuf,
+    },
+
+    /// List tiles of the tile file that are the same as another tile there or a game tile,
+    /// up to rotation, and exit
+    Duplicates {
+        /// File with tile data
+        tile_file: PathBuf,
+    },
+
+    /// Let bots play games of each scenario and timer length, print statistics and exit
+    Simulate(SimulateArgs),
+
+    /// Generate random tiles in which every cell can be reached, print them as a tileset
+    /// and exit
+    Generate(GenerateArgs),
+
+    /// Find a random scenario that can be won, print it with its difficulty and exit
+    Mall(MallArgs),
+
+    /// Find actions that win a scenario with the pile shuffled from one seed, print them
+    /// and exit
+    Solve(SolveArgs),
 }
+
 #[derive(Debug, Resource)]
 pub struct CurrentTile {
     pub tile: Tile,
@@ -60,8 +110,15 @@ pub struct RefreshTile(pub bool);
 #[derive(Debug)]
 pub struct Ctx {
     pub args: Args,
+    pub tile_file: PathBuf,
     pub tileset: Vec<(String, Tile)>,
     pub tile_idx: isize,
+
+    /// Edits of each tile in `tileset`, by index
+    pub histories: HashMap<usize, TileEditHistory>,
+
+    /// Whether `tileset` has edits that are not in the tile file
+    pub unsaved: bool,
     pub notify_rx: mpsc::Receiver<notify::Result<notify::Event>>,
     pub notify_watcher: notify::RecommendedWatcher,
 }
@@ -74,7 +131,7 @@ pub struct TileAvailability(pub CellItemAvailability);
 
 #[derive(Debug, Default, Resource)]
 pub struct TileRotation {
-    pub left_turns: u8,
+    pub orientation: Orientation,
 }
 
 impl Default for TileAvailability {
@@ -84,22 +141,25 @@ impl Default for TileAvailability {
 }
 
 impl Ctx {
-    fn new() -> Result<Ctx> {
-        let args = Args::parse();
+    fn new(args: Args) -> Result<Ctx> {
+        let tile_file = args.tile_file.clone().context("No tile file given")?;
 
         let (notify_tx, notify_rx) = mpsc::channel();
         let mut notify_watcher =
             notify::RecommendedWatcher::new(notify_tx, notify::Config::default())
                 .context("Failed to create notify watcher")?;
         notify_watcher
-            .watch(&args.tile_file, notify::RecursiveMode::Recursive)
-            .context(format!("Failed to watch file {:?}", args.tile_file))?;
+            .watch(&tile_file, notify::RecursiveMode::Recursive)
+            .context(format!("Failed to watch file {:?}", tile_file))?;
 
         let tile_idx = args.index as isize;
         let mut ctx = Ctx {
             args,
+            tile_file,
             tileset: Default::default(),
             tile_idx,
+            histories: Default::default(),
+            unsaved: false,
             notify_rx,
             notify_watcher,
         };
@@ -109,36 +169,56 @@ impl Ctx {
     }
 
     fn refresh(&mut self) -> Result<()> {
-        let mut tile_input_file = File::open(&self.args.tile_file)
-            .with_context(|| format!("Failed to open input file {:?}", &self.args.tile_file))?;
+        let mut tile_input_file = File::open(&self.tile_file)
+            .with_context(|| format!("Failed to open input file {:?}", self.tile_file))?;
         let mut tile_str = String::new();
         tile_input_file
             .read_to_string(&mut tile_str)
             .with_context(|| "Failed to read input")?;
-        self.tileset = m_mazing_core::tile::tileset::tileset_from_str(&tile_str)
+        let tileset = m_mazing_core::tile::tileset::tileset_from_str(&tile_str)
             .with_context(|| "failed to parse tileset")?;
 
+        // saving reloads the same tiles, which keeps the edits that can be undone
+        if tileset != self.tileset {
+            if self.unsaved {
+                warn!("Dropping unsaved edits");
+            }
+            self.histories.clear();
+            self.unsaved = false;
+        }
+        self.tileset = tileset;
+        Ok(())
+    }
+
+    /// Write `tileset` to the tile file, keeping the comments in it
+    fn save(&mut self) -> Result<()> {
+        let original = std::fs::read_to_string(&self.tile_file)
+            .with_context(|| format!("Failed to read tile file {:?}", self.tile_file))?;
+        let tile_str =
+            m_mazing_core::tile::tileset::tileset_to_string_with_comments(&self.tileset, &original);
+        std::fs::write(&self.tile_file, tile_str)
+            .with_context(|| format!("Failed to write tile file {:?}", self.tile_file))?;
+        self.unsaved = false;
         Ok(())
     }
 }
 
 fn setup_system(mut commands: Commands) {
-    const CAMERA_EXTENT: f32 = 3.0;
-    let mut camera_bundle = Camera2dBundle::default();
-
-    // able to re-size window if pop out **after** moving window
-    camera_bundle.projection.scaling_mode = ScalingMode::AutoMin {
-        min_width: CAMERA_EXTENT,
-        min_height: CAMERA_EXTENT,
-    };
-
-    // hack to modify camera
-    camera_bundle.transform.scale = Vec3::new(3.0, 3.0, 1.0);
-
-    commands.spawn(camera_bundle);
+    commands.spawn((
+        Camera2dBundle::default(),
+        AutoFitCamera {
+            // leave room for the legend and title text
+            margin: 2.5,
+            ..default()
+        },
+    ));
 }
 
-fn ui_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn ui_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<KeyBindings<TileUtilInput>>,
+) {
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
 
     commands
@@ -198,7 +278,7 @@ fn ui_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..Default::default()
         },
         text: Text::from_section(
-            LEGEND.trim().to_string(),
+            legend(&bindings),
             TextStyle {
                 font,
                 font_size: 40.0,
@@ -224,19 +304,89 @@ fn debug_system(query: Query<Entity>) {
     info!("entities: {}", query.iter().count());
 }
 
+/// Play `path` to the end and print where the game ended up
+fn print_replay(path: &Path) -> Result<()> {
+    let replay = Replay::load(path)?;
+    let num_actions = replay.actions.len();
+    let tileset = game_tileset().context("Failed to parse game tiles")?;
+    let mut playback = ReplayPlayback::new(replay, &tileset).context("Failed to check replay")?;
+    playback.seek(playback.length());
+
+    let replay = playback.replay();
+    let state = playback.state();
+    println!(
+        "scenario {}, {} players, seed {}",
+        replay.scenario,
+        replay.num_players,
+        GameRng::format_seed(replay.seed)
+    );
+    println!(
+        "{} actions over {:.1}s",
+        num_actions,
+        playback.length().as_secs_f32()
+    );
+    println!("phase: {:?}", state.phase());
+    println!(
+        "timer: {:.1}s remaining",
+        playback.timer().remaining().as_secs_f32()
+    );
+    for pawn in Pawn::ALL {
+        println!("{:?}: {:?}", pawn, state.pawn_coord(pawn));
+    }
+    println!(
+        "tiles: {} placed, {} left in pile",
+        state.board().tiles().len(),
+        state.pile_len()
+    );
+    for placed in state.board().tiles() {
+        println!("  {} at {:?}", placed.name, placed.origin);
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
-    let ctx = Ctx::new().with_context(|| "Failed to generate context")?;
+    let args = Args::parse();
+    match &args.command {
+        Some(Command::Replay { path }) => return print_replay(path),
+        Some(Command::Duplicates { tile_file }) => return print_duplicates(tile_file),
+        Some(Command::Simulate(sim_args)) => return simulate(sim_args),
+        Some(Command::Generate(gen_args)) => return generate(gen_args),
+        Some(Command::Mall(mall_args)) => return print_mall(mall_args),
+        Some(Command::Solve(solve_args)) => return print_solution(solve_args),
+        None => (),
+    }
+    let ctx = Ctx::new(args).with_context(|| "Failed to generate context")?;
     let level = log_level(ctx.args.verbose, ctx.args.quiet);
 
+    let bindings = KeyBindings::<TileUtilInput>::load(ctx.args.key_bindings.as_deref())?;
+    let conflicts = bindings.conflicts();
+    if !conflicts.is_empty() {
+        anyhow::bail!("Conflicting key bindings: {:?}", conflicts);
+    }
+
+    let (theme, theme_file) = match Theme::builtin(&ctx.args.theme) {
+        Some(theme) => (theme, None),
+        None => {
+            let path = PathBuf::from(&ctx.args.theme);
+            let theme = Theme::load(&path).context("Failed to load theme")?;
+            (theme, Some(ThemeFile::new(path)))
+        }
+    };
+
     println!("tileset: {:#?}", ctx.tileset);
 
-    App::new()
-        .insert_resource(Msaa::Sample4)
+    let mut app = App::new();
+    if let Some(theme_file) = theme_file {
+        app.insert_resource(theme_file);
+    }
+    app.insert_resource(Msaa::Sample4)
         .insert_non_send_resource(ctx)
-        .init_resource::<RenderState>()
+        .insert_resource(bindings)
+        .insert_resource(RenderState { theme })
         .init_resource::<TileAvailability>()
         .insert_resource(RefreshTile(true))
         .init_resource::<TileRotation>()
+        .init_resource::<TileEditor>()
         .add_plugins(
             DefaultPlugins
                 .set(AssetPlugin {
@@ -253,8 +403,11 @@ fn main() -> Result<()> {
         .add_systems(Startup, ui_setup)
         .add_systems(Update, frame_init.before(MySystemSet::Input))
         .add_systems(Update, keyboard_input_system.in_set(MySystemSet::Input))
+        .add_systems(Update, edit_keyboard_system.in_set(MySystemSet::Input))
+        .add_systems(Update, edit_mouse_system.in_set(MySystemSet::Input))
         .add_systems(Update, debug_entity.in_set(MySystemSet::Input))
         .add_systems(Update, notify_tileset_change.in_set(MySystemSet::Input))
+        .add_systems(Update, theme_reload_system.in_set(MySystemSet::Input))
         .add_systems(
             Update,
             spawn_tile
@@ -262,6 +415,8 @@ fn main() -> Result<()> {
                 .after(MySystemSet::Input),
         )
         .add_systems(Update, print_tile.after(MySystemSet::SpawnTile))
+        .add_systems(Update, camera_auto_fit_system)
+        .add_systems(Update, theme_clear_color_system)
         //.add_system(Update, debug_system)
         .run();
 
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MallArgs {
+    /// File with tile data that replaces game tiles of the same names
+    #[clap(long, short)]
+    tile_file: Option<PathBuf>,
+
+    /// How pawns escape (purple-only, each-color)
+    #[clap(long, default_value = "purple-only", value_parser = parse_escape)]
+    escape: ScenarioEscape,
+
+    /// Tiles in the pile besides the ones with the loot and exits
+    #[clap(long, default_value = "3")]
+    extra: usize,
+
+    /// Generate the extra tiles instead of drawing them from the tileset
+    #[clap(long)]
+    generated: bool,
+
+    #[clap(flatten)]
+    shape: TileShapeArgs,
+
+    /// Number of players
+    #[clap(long, short = 'n', default_value = "4")]
+    players: u8,
+
+    /// Difficulty (easy, normal, hard) of the bots that play the shuffles the solver
+    /// gives up on
+    #[clap(long, default_value = "normal")]
+    bots: BotDifficulty,
+
+    /// Seed of the mall as 16 hex digits or any phrase such as a date, random if not
+    /// given
+    #[clap(long)]
+    seed: Option<String>,
+}
+
+pub fn parse_escape(name: &str) -> Result<ScenarioEscape, String> {
+    match name {
+        "purple-only" => Ok(ScenarioEscape::PurpleOnly),
+        "each-color" => Ok(ScenarioEscape::EachColor),
+        _ => Err(format!(
+            "Unknown escape {name:?}, expected purple-only or each-color"
+        )),
+    }
+}
+
+/// Find a winnable mall and print its scenario, rating and generated tiles
+pub fn print_mall(args: &MallArgs) -> Result<()> {
+    let seed = args
+        .seed
+        .as_deref()
+        .map_or_else(GameRng::random_seed, GameRng::parse_seed);
+    let generator = MallGenerator {
+        escape: args.escape,
+        num_players: args.players,
+        extra_tiles: args.extra,
+        generated: args.generated.then(|| args.shape.constraints()),
+        bots: args.bots,
+        ..Default::default()
+    };
+    let tileset = sim_tileset(args.tile_file.as_deref())?;
+    let mall = generator
+        .generate(&tileset, seed)
+        .context("Failed to generate mall")?;
+
+    let scenario = &mall.scenario;
+    let rating = &mall.rating;
+    println!(
+        "mall {}: {:?}, {} players",
+        GameRng::format_seed(seed),
+        scenario.escape,
+        args.players
+    );
+    println!("start: {}", scenario.start_tile_name);
+    println!("pile: {}", scenario.explore_tile_names.join(", "));
+    let seeds: Vec<String> = mall
+        .seeds
+        .iter()
+        .copied()
+        .map(GameRng::format_seed)
+        .collect();
+    println!("won with seeds: {}", seeds.join(", "));
+    println!(
+        "rating: {:.1} actions, {} timer cells, {:.1} actions per timer turn, {:?}",
+        rating.actions,
+        rating.timer_cells,
+        rating.actions_per_timer(),
+        rating.level()
+    );
+
+    let generated = &mall.tileset[tileset.len()..];
+    if !generated.is_empty() {
+        println!();
+        print!(
+            "{}",
+            m_mazing_core::tile::tileset::tileset_to_string(generated)
+        );
+    }
+    Ok(())
+}
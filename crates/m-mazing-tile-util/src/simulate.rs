@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SimulateArgs {
+    /// Games to play per scenario and timer length
+    games: usize,
+
+    /// File with tile data that replaces game tiles of the same names
+    #[clap(long, short)]
+    tile_file: Option<PathBuf>,
+
+    /// Scenarios to simulate; all supported ones if not given
+    #[clap(long, value_delimiter = ',')]
+    scenario: Vec<u8>,
+
+    /// Tiles to explore instead of the scenario's pile
+    #[clap(long, value_delimiter = ',')]
+    pile: Vec<String>,
+
+    /// Sand timer lengths in seconds to simulate; the rule book's if not given
+    #[clap(long, value_delimiter = ',')]
+    timer: Vec<u64>,
+
+    /// Number of players
+    #[clap(long, short = 'n', default_value = "4")]
+    players: u8,
+
+    /// Difficulty (easy, normal, hard) of the bots
+    #[clap(long, default_value = "normal")]
+    bots: BotDifficulty,
+
+    /// Seed of the first game as 16 hex digits or any phrase such as a date; the
+    /// following ones count up from it, random if not given
+    #[clap(long)]
+    seed: Option<String>,
+}
+
+/// Game tiles, with the tiles of `tile_file` taking the place of game tiles of the same
+/// name
+pub fn sim_tileset(tile_file: Option<&Path>) -> Result<Vec<(String, Tile)>> {
+    let mut tileset = game_tileset().context("Failed to parse game tiles")?;
+    if let Some(path) = tile_file {
+        let tile_str = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tile file {path:?}"))?;
+        let custom = m_mazing_core::tile::tileset::tileset_from_str(&tile_str)
+            .context("failed to parse tileset")?;
+        tileset.retain(|(name, _)| custom.iter().all(|(custom_name, _)| custom_name != name));
+        tileset.splice(0..0, custom);
+    }
+    Ok(tileset)
+}
+
+/// Play `num_games` games of `scenario` on all cores, with seeds counting up from `seed`
+fn run(
+    sim: &Simulation,
+    num_players: u8,
+    scenario: &Scenario,
+    tileset: &[(String, Tile)],
+    seed: u64,
+    num_games: usize,
+) -> Result<SimStats> {
+    // fail before starting threads if the setup is invalid
+    GameState::new(num_players, scenario, tileset, seed)?;
+
+    let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let stats = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..num_threads)
+            .map(|thread| {
+                scope.spawn(move || {
+                    let mut stats = SimStats::default();
+                    for idx in (thread..num_games).step_by(num_threads) {
+                        let seed = seed.wrapping_add(idx as u64);
+                        let state = GameState::new(num_players, scenario, tileset, seed)
+                            .expect("checked setup");
+                        stats.add(&sim.play(state));
+                    }
+                    stats
+                })
+            })
+            .collect();
+        let mut stats = SimStats::default();
+        for thread in threads {
+            stats.merge(&thread.join().expect("simulation thread"));
+        }
+        stats
+    });
+    Ok(stats)
+}
+
+fn print_stats(stats: &SimStats) {
+    println!("  win rate: {:.1}%", stats.win_rate() * 100.0);
+    if stats.wins > 0 {
+        println!(
+            "  time left: {:.1}s on average when won",
+            stats.avg_time_left().as_secs_f32()
+        );
+    }
+    println!("  timer flips: {:.2} on average", stats.avg_timer_flips());
+    println!(
+        "  tiles explored: {:.2} on average",
+        stats.avg_tiles_explored()
+    );
+    match stats.most_common_loss() {
+        Some((reason, count)) => println!(
+            "  most common loss: {reason:?} in {count} of {} lost games",
+            stats.games - stats.wins
+        ),
+        None => println!("  most common loss: none"),
+    }
+}
+
+/// Let bots play `args.games` games for each scenario and timer length and print how
+/// they went
+pub fn simulate(args: &SimulateArgs) -> Result<()> {
+    let num_games = args.games;
+    let tileset = sim_tileset(args.tile_file.as_deref())?;
+    let scenarios = if args.scenario.is_empty() {
+        (1..=Scenario::NUM_BUILTIN).collect()
+    } else {
+        args.scenario.clone()
+    };
+    let seed = args
+        .seed
+        .as_deref()
+        .map_or_else(GameRng::random_seed, GameRng::parse_seed);
+    let timers = if args.timer.is_empty() {
+        vec![SandTimer::DEFAULT_DURATION]
+    } else {
+        args.timer
+            .iter()
+            .copied()
+            .map(Duration::from_secs)
+            .collect()
+    };
+
+    for number in scenarios {
+        let mut scenario =
+            Scenario::builtin(number).with_context(|| format!("Unknown scenario {number}"))?;
+        if !args.pile.is_empty() {
+            scenario.explore_tile_names = args.pile.clone();
+        }
+        for timer_duration in timers.iter().copied() {
+            let sim = Simulation {
+                difficulty: args.bots,
+                timer_duration: Some(timer_duration),
+                ..Default::default()
+            };
+            println!(
+                "scenario {number}, {} players, {:.0}s timer, {num_games} games from seed {}",
+                args.players,
+                timer_duration.as_secs_f32(),
+                GameRng::format_seed(seed)
+            );
+            let stats = run(&sim, args.players, &scenario, &tileset, seed, num_games)
+                .with_context(|| format!("Failed to set up scenario {number}"))?;
+            print_stats(&stats);
+        }
+    }
+    Ok(())
+}
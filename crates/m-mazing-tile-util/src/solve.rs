@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+
+use m_mazing_core::prelude::*;
+
+use crate::*;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SolveArgs {
+    /// File with tile data that replaces game tiles of the same names
+    #[clap(long, short)]
+    tile_file: Option<PathBuf>,
+
+    /// Rule book scenario to solve
+    #[clap(long, default_value = "1")]
+    scenario: u8,
+
+    /// Tiles to explore instead of the scenario's pile
+    #[clap(long, value_delimiter = ',')]
+    pile: Vec<String>,
+
+    /// Seed the pile is shuffled with, as 16 hex digits or any phrase such as a date;
+    /// random if not given
+    #[clap(long)]
+    seed: Option<String>,
+
+    /// Search for the fewest actions overall instead of stage by stage; only finishes
+    /// for short games
+    #[clap(long)]
+    exact: bool,
+
+    /// Give up after expanding this many positions in a stage
+    #[clap(long, default_value = "200000")]
+    max_positions: usize,
+}
+
+/// Solve the game of `args.seed` and print the actions that win it
+pub fn print_solution(args: &SolveArgs) -> Result<()> {
+    let tileset = sim_tileset(args.tile_file.as_deref())?;
+    let mut scenario = Scenario::builtin(args.scenario)
+        .with_context(|| format!("Unknown scenario {}", args.scenario))?;
+    if !args.pile.is_empty() {
+        scenario.explore_tile_names = args.pile.clone();
+    }
+    let seed = args
+        .seed
+        .as_deref()
+        .map_or_else(GameRng::random_seed, GameRng::parse_seed);
+    // one player may perform every action
+    let mut state = GameState::new(1, &scenario, &tileset, seed)
+        .with_context(|| format!("Failed to set up scenario {}", args.scenario))?;
+
+    let solver = Solver {
+        max_positions: args.max_positions,
+        staged: !args.exact,
+    };
+    let actions = solver.solve(&state).context("Failed to solve")?;
+    println!(
+        "scenario {}, seed {}: won in {} actions",
+        args.scenario,
+        GameRng::format_seed(seed),
+        actions.len()
+    );
+    for (idx, action) in actions.iter().enumerate() {
+        let events = state
+            .apply_action(PlayerId(0), &Action::BoardAction(*action))
+            .context("Solution is not legal")?;
+        let notes: Vec<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::TileExplored(placed) => Some(format!("explored {}", placed.name)),
+                GameEvent::LootStolen => Some("loot stolen".to_string()),
+                GameEvent::PawnEscaped(pawn) => Some(format!("{pawn:?} escaped")),
+                _ => None,
+            })
+            .collect();
+        println!(
+            "{:4}. {:?} {:?} to ({}, {}){}{}",
+            idx + 1,
+            action.pawn,
+            action.action,
+            action.dest.x,
+            action.dest.y,
+            if notes.is_empty() { "" } else { ": " },
+            notes.join(", ")
+        );
+    }
+    Ok(())
+}